@@ -0,0 +1,253 @@
+//! Byte classes: sets of bytes stored as 256-entry tables.
+
+/// The SIMD range descriptor of a class given as a table: the inclusive
+/// bounds of each run of excluded bytes, as pairs, and the length used in the
+/// 16-byte array. Maximal runs make this the smallest such set of ranges.
+///
+/// Returns `None` if there are more than 8 runs, which do not fit in the
+/// descriptor.
+pub const fn ranges_of(table: &[bool; 256]) -> Option<([u8; 16], usize)> {
+    let mut ranges = [0u8; 16];
+    let mut len = 0;
+
+    let mut b = 0;
+    while b < 256 {
+        if table[b] {
+            b += 1;
+            continue;
+        }
+        let start = b;
+        while b < 256 && !table[b] {
+            b += 1;
+        }
+        if len == 16 {
+            return None;
+        }
+        ranges[len] = start as u8;
+        ranges[len + 1] = (b - 1) as u8;
+        len += 2;
+    }
+
+    Some((ranges, len))
+}
+
+/// `ranges_of`, failing to compile when used in a constant for a class that
+/// cannot be expressed.
+#[doc(hidden)]
+pub const fn simd_ranges_of(table: &[bool; 256]) -> ([u8; 16], usize) {
+    match ranges_of(table) {
+        Some(ranges) => ranges,
+        None => panic!("the bytes excluded from this class do not fit in 8 ranges"),
+    }
+}
+
+/// A set of bytes, as a 256-entry lookup table along with the ranges of bytes
+/// outside the set, the descriptor used by the SIMD scanners.
+///
+/// Classes are built at compile time by [`byte_class!`](crate::byte_class)
+/// from a `const fn(u8) -> bool`.
+#[derive(Clone)]
+pub struct ByteClass {
+    table: [bool; 256],
+    ranges: [u8; 16],
+    ranges_len: usize,
+    /// False for classes built with `scalar_only`.
+    simd: bool,
+}
+
+impl ByteClass {
+    /// Builds a class from its table, deriving the ranges of excluded bytes.
+    ///
+    /// Panics if they do not fit in 8 ranges, which is a compile error when
+    /// building a `static` or `const`.
+    pub const fn new(table: [bool; 256]) -> ByteClass {
+        let (ranges, ranges_len) = simd_ranges_of(&table);
+        ByteClass {
+            table,
+            ranges,
+            ranges_len,
+            simd: true,
+        }
+    }
+
+    /// Builds a class that does not fit in the SIMD range descriptor, and is
+    /// always scanned one byte at a time.
+    pub const fn scalar_only(table: [bool; 256]) -> ByteClass {
+        ByteClass {
+            table,
+            ranges: [0; 16],
+            ranges_len: 0,
+            simd: false,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, b: u8) -> bool {
+        self.table[b as usize]
+    }
+
+    /// Inclusive bounds of the excluded bytes, as pairs, or `None` for a
+    /// class built with `scalar_only`.
+    ///
+    /// A class containing every byte has no ranges, and is described by an
+    /// empty slice.
+    pub fn ranges(&self) -> Option<&[u8]> {
+        if self.simd {
+            Some(&self.ranges[..self.ranges_len])
+        } else {
+            None
+        }
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! cl_map {
+    ($cl: expr, $($flag:expr,)*) => ([
+        $($cl($flag),)*
+    ])
+}
+
+/// Expands to the 256-entry table of a `const fn(u8) -> bool`, for
+/// `ByteClass::scalar_only` or `ranges_of`.
+#[macro_export]
+macro_rules! byte_table (
+  ($cl: expr) => (
+      $crate::cl_map!($cl,
+        0x00,0x01,0x02,0x03,0x04,0x05,0x06,0x07,0x08,0x09,0x0A,0x0B,0x0C,0x0D,0x0E,0x0F,
+        0x10,0x11,0x12,0x13,0x14,0x15,0x16,0x17,0x18,0x19,0x1A,0x1B,0x1C,0x1D,0x1E,0x1F,
+        0x20,0x21,0x22,0x23,0x24,0x25,0x26,0x27,0x28,0x29,0x2A,0x2B,0x2C,0x2D,0x2E,0x2F,
+        0x30,0x31,0x32,0x33,0x34,0x35,0x36,0x37,0x38,0x39,0x3A,0x3B,0x3C,0x3D,0x3E,0x3F,
+        0x40,0x41,0x42,0x43,0x44,0x45,0x46,0x47,0x48,0x49,0x4A,0x4B,0x4C,0x4D,0x4E,0x4F,
+        0x50,0x51,0x52,0x53,0x54,0x55,0x56,0x57,0x58,0x59,0x5A,0x5B,0x5C,0x5D,0x5E,0x5F,
+        0x60,0x61,0x62,0x63,0x64,0x65,0x66,0x67,0x68,0x69,0x6A,0x6B,0x6C,0x6D,0x6E,0x6F,
+        0x70,0x71,0x72,0x73,0x74,0x75,0x76,0x77,0x78,0x79,0x7A,0x7B,0x7C,0x7D,0x7E,0x7F,
+        0x80,0x81,0x82,0x83,0x84,0x85,0x86,0x87,0x88,0x89,0x8A,0x8B,0x8C,0x8D,0x8E,0x8F,
+        0x90,0x91,0x92,0x93,0x94,0x95,0x96,0x97,0x98,0x99,0x9A,0x9B,0x9C,0x9D,0x9E,0x9F,
+        0xA0,0xA1,0xA2,0xA3,0xA4,0xA5,0xA6,0xA7,0xA8,0xA9,0xAA,0xAB,0xAC,0xAD,0xAE,0xAF,
+        0xB0,0xB1,0xB2,0xB3,0xB4,0xB5,0xB6,0xB7,0xB8,0xB9,0xBA,0xBB,0xBC,0xBD,0xBE,0xBF,
+        0xC0,0xC1,0xC2,0xC3,0xC4,0xC5,0xC6,0xC7,0xC8,0xC9,0xCA,0xCB,0xCC,0xCD,0xCE,0xCF,
+        0xD0,0xD1,0xD2,0xD3,0xD4,0xD5,0xD6,0xD7,0xD8,0xD9,0xDA,0xDB,0xDC,0xDD,0xDE,0xDF,
+        0xE0,0xE1,0xE2,0xE3,0xE4,0xE5,0xE6,0xE7,0xE8,0xE9,0xEA,0xEB,0xEC,0xED,0xEE,0xEF,
+        0xF0,0xF1,0xF2,0xF3,0xF4,0xF5,0xF6,0xF7,0xF8,0xF9,0xFA,0xFB,0xFC,0xFD,0xFE,0xFF,
+      )
+  )
+);
+
+/// Defines `pub fn $name(c: u8) -> bool` as a lookup in the table of the
+/// `const fn` `$cl`.
+#[macro_export]
+macro_rules! make_map (
+  ($name: ident, $cl: expr) => (
+    pub fn $name(c: u8) -> bool {
+      static MAP: [bool;256] = $crate::byte_table!($cl);
+
+      MAP[c as usize]
+    }
+  )
+);
+
+/// Builds a [`ByteClass`] at compile time from a `const fn(u8) -> bool`:
+///
+/// ```
+/// use combinators::{byte_class, ByteClass};
+///
+/// const fn is_digit(c: u8) -> bool {
+///     c >= b'0' && c <= b'9'
+/// }
+///
+/// static DIGIT: ByteClass = byte_class!(is_digit);
+/// assert!(DIGIT.contains(b'7'));
+/// assert_eq!(DIGIT.ranges(), Some(&b"\0/:\xFF"[..]));
+/// ```
+#[macro_export]
+macro_rules! byte_class (
+  ($cl: expr) => (
+    $crate::ByteClass::new($crate::byte_table!($cl))
+  )
+);
+
+/// Expands to the `&'static [u8]` range descriptor of a `const fn(u8) ->
+/// bool`, for `take_while1_simd` next to a `make_map!` predicate built from
+/// the same function. Fails to compile if the class cannot be expressed.
+///
+/// ```
+/// use combinators::simd_ranges;
+///
+/// const fn is_digit(c: u8) -> bool {
+///     c >= b'0' && c <= b'9'
+/// }
+///
+/// const DIGIT_RANGES: &[u8] = simd_ranges!(is_digit);
+/// assert_eq!(DIGIT_RANGES, b"\0/:\xFF");
+/// ```
+///
+/// ```compile_fail
+/// use combinators::simd_ranges;
+///
+/// const fn is_even(c: u8) -> bool {
+///     c & 1 == 0
+/// }
+///
+/// const EVEN_RANGES: &[u8] = simd_ranges!(is_even);
+/// ```
+#[macro_export]
+macro_rules! simd_ranges (
+  ($cl: expr) => ({
+    const RANGES: ([u8; 16], usize) = $crate::class::simd_ranges_of(&$crate::byte_table!($cl));
+    RANGES.0.split_at(RANGES.1).0
+  })
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_test() {
+        const fn is_string_token_cst(c: u8) -> bool {
+            c != b'"' && c != b'\\' && c > 31 && c != 127
+        }
+
+        make_map!(is_string_token, is_string_token_cst);
+
+        for i in 0u8..255 {
+            assert_eq!(
+                is_string_token(i),
+                is_string_token_cst(i),
+                "not equal for {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "do not fit in 8 ranges")]
+    fn rejects_too_many_ranges() {
+        const fn nine_ranges(c: u8) -> bool {
+            c & 0x0F != 0 || c >= 0x90
+        }
+        ByteClass::new(byte_table!(nine_ranges));
+    }
+
+    #[test]
+    fn ranges() {
+        const fn all(_: u8) -> bool {
+            true
+        }
+        const fn even(c: u8) -> bool {
+            c & 1 == 0
+        }
+        const fn header_value(c: u8) -> bool {
+            c == b'\t' || (c > 31 && c != 127)
+        }
+
+        let class: ByteClass = byte_class!(all);
+        assert_eq!(class.ranges(), Some(&[][..]));
+        assert_eq!(ranges_of(&byte_table!(even)), None);
+        let class = ByteClass::scalar_only(byte_table!(even));
+        assert_eq!(class.ranges(), None);
+        let class: ByteClass = byte_class!(header_value);
+        assert_eq!(class.ranges(), Some(&b"\0\x08\x0A\x1F\x7F\x7F"[..]));
+    }
+}
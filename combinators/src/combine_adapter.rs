@@ -0,0 +1,56 @@
+//! combine 3 parsers.
+
+use combine::error::{Consumed, ParseError};
+use combine::stream::FullRangeStream;
+use combine::{parser, Parser};
+
+use crate::ByteClass;
+
+/// Longest non empty prefix matching `predicate`, scanned with SIMD
+/// instructions using `ranges`, see [`take_while1_simd`](crate::take_while1_simd).
+pub fn take_while1_simd<'a, I, F>(ranges: &'static [u8], mut predicate: F) -> impl Parser<Output = &'a [u8], Input = I>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+    F: FnMut(u8) -> bool,
+{
+    parser(move |input: &mut I| {
+        match crate::take_while1_simd(input.range(), &mut predicate, ranges) {
+            Ok((_, value)) => {
+                let _ = input.uncons_range(value.len());
+                Ok((value, Consumed::Consumed(())))
+            }
+            Err(()) => Err(Consumed::Empty(I::Error::empty(input.position()).into())),
+        }
+    })
+}
+
+/// Longest non empty prefix of bytes of `class`, as combine's `take_while1`.
+pub fn take_while1<'a, I>(class: &'static ByteClass) -> impl Parser<Output = &'a [u8], Input = I>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]>,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    parser(move |input: &mut I| {
+        match crate::take_while1(class, input.range()) {
+            Ok((_, value)) => {
+                let _ = input.uncons_range(value.len());
+                Ok((value, Consumed::Consumed(())))
+            }
+            Err(()) => Err(Consumed::Empty(I::Error::empty(input.position()).into())),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use combine::Parser;
+
+    #[test]
+    fn class_test() {
+        let mut uri = super::take_while1(&crate::http::URI);
+
+        assert_eq!(uri.parse(&b"/a/b HTTP/1.1"[..]), Ok((&b"/a/b"[..], &b" HTTP/1.1"[..])));
+        assert!(uri.parse(&b" HTTP/1.1"[..]).is_err());
+    }
+}
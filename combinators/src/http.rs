@@ -0,0 +1,42 @@
+//! Byte classes of HTTP/1.x messages (RFC 7230).
+
+use crate::ByteClass;
+
+const fn is_header_value_token_cst(c: u8) -> bool {
+    c == b'\t' || (c > 31 && c != 127)
+}
+
+make_map!(is_header_value_token, is_header_value_token_cst);
+
+const fn is_token_cst(c: u8) -> bool {
+    !(c <= 32 || c >= 127 || c == b'(' || c == b')' || c == b'<'
+        || c == b'>' || c == b'@' || c == b',' || c == b';'
+        || c == b':' || c == b'\\' || c == b'"' || c == b'/'
+        || c == b'[' || c == b']' || c == b'?' || c == b'='
+        || c == b'{' || c == b'}')
+}
+
+make_map!(is_token, is_token_cst);
+
+const fn is_url_token_cst(c: u8) -> bool {
+    c > 0x20 && c < 0x7F
+}
+
+make_map!(is_url_token, is_url_token_cst);
+
+/// Range descriptor of `is_url_token`, for `take_while1_simd`.
+pub const URI_RANGES: &[u8] = simd_ranges!(is_url_token_cst);
+
+/// Range descriptor of `is_header_value_token`, for `take_while1_simd`.
+pub const HEADER_VALUE_RANGES: &[u8] = simd_ranges!(is_header_value_token_cst);
+
+/// `tchar`, the characters of methods and header names. The separators it
+/// excludes need more than 8 ranges, so it is only scanned by the scalar loop.
+pub static TOKEN: ByteClass = ByteClass::scalar_only(byte_table!(is_token_cst));
+
+/// Characters of a header value or reason phrase: visible characters,
+/// spaces, tabs and `obs-text`.
+pub static HEADER_VALUE: ByteClass = byte_class!(is_header_value_token_cst);
+
+/// Characters of a request target: visible ASCII characters.
+pub static URI: ByteClass = byte_class!(is_url_token_cst);
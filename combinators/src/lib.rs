@@ -0,0 +1,123 @@
+//! Fast scanners over byte classes, shared by the parsers of every suite.
+//!
+//! A [`ByteClass`] is a set of bytes stored as a 256-entry table. Scanning the
+//! longest prefix of an input made of bytes of a class is the inner loop of
+//! most text parsers (tokens, header values, strings), so [`take_while1`]
+//! uses SIMD instructions when the class fits in the range descriptors they
+//! take, picking AVX2, SSE4.2 or a scalar loop at runtime (see
+//! [`simd::simd_path`]).
+//!
+//! The `nom` and `combine` features add adapters for those parser libraries.
+
+#[cfg(all(test, feature = "nom"))]
+#[macro_use]
+extern crate nom;
+
+#[macro_use]
+pub mod class;
+
+pub mod http;
+pub mod simd;
+
+#[cfg(feature = "combine")]
+pub mod combine_adapter;
+#[cfg(feature = "nom")]
+#[macro_use]
+pub mod nom_adapter;
+
+pub use crate::class::ByteClass;
+pub use crate::simd::{simd_path, take_while1_simd, take_while1_with, SimdPath};
+
+/// Splits `input` after its longest prefix of bytes of `class`, failing if
+/// that prefix is empty.
+#[inline(always)]
+#[allow(clippy::result_unit_err)]
+pub fn take_while1<'a>(class: &ByteClass, input: &'a [u8]) -> Result<(&'a [u8], &'a [u8]), ()> {
+    match class.ranges() {
+        Some(ranges) => take_while1_simd(input, |b| class.contains(b), ranges),
+        None => simd::take_while1_with(SimdPath::Scalar, input, |b| class.contains(b), &[]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_while1_test() {
+        let input = &b"/abcd/efgh/ij kl/pouet/ 1234579"[..];
+        assert_eq!(take_while1(&http::URI, input), Ok((&b" kl/pouet/ 1234579"[..], &b"/abcd/efgh/ij"[..])));
+        assert_eq!(take_while1(&http::TOKEN, input), Err(()));
+        assert_eq!(take_while1(&http::HEADER_VALUE, input), Ok((&b""[..], input)));
+    }
+
+    // xorshift, to get reproducible inputs without another dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// A class made of at most 8 runs of excluded bytes.
+    fn random_class(rng: &mut Rng) -> ByteClass {
+        let mut table = [true; 256];
+        for _ in 0..rng.below(9) {
+            let start = rng.below(256);
+            let end = (start + rng.below(40)).min(255);
+            for b in &mut table[start..=end] {
+                *b = false;
+            }
+        }
+        ByteClass::new(table)
+    }
+
+    /// Checks that the ranges describe exactly the bytes outside the class,
+    /// and that every scanner splits inputs stopping on each of the 256 bytes
+    /// as the scalar loop does.
+    fn assert_scanners_agree(class: &ByteClass, rng: &mut Rng) {
+        let ranges = class.ranges().unwrap();
+        for b in 0..=255u8 {
+            let excluded = ranges.chunks(2).any(|r| r[0] <= b && b <= r[1]);
+            assert_eq!(excluded, !class.contains(b), "byte {:#x} in {:?}", b, ranges);
+        }
+
+        let members: Vec<u8> = (0..=255u8).filter(|&b| class.contains(b)).collect();
+        for stop in 0..=255u8 {
+            for _ in 0..8 {
+                let len = rng.below(100);
+                let mut input: Vec<u8> = (0..len)
+                    .map(|_| if members.is_empty() { stop } else { members[rng.below(members.len())] })
+                    .collect();
+                input.insert(rng.below(len + 1), stop);
+
+                let expected = simd::take_while1_with(SimdPath::Scalar, &input, |b| class.contains(b), &[]);
+                for &path in &[SimdPath::Avx2, SimdPath::Sse42] {
+                    let split = simd::take_while1_with(path, &input, |b| class.contains(b), ranges);
+                    assert_eq!(split, expected, "{:?} on {:?} with {:?}", path, input, ranges);
+                }
+                assert_eq!(take_while1(class, &input), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn scanners_agree() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+        assert_scanners_agree(&http::URI, &mut rng);
+        assert_scanners_agree(&http::HEADER_VALUE, &mut rng);
+        for _ in 0..50 {
+            let class = random_class(&mut rng);
+            assert_scanners_agree(&class, &mut rng);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! nom 4 parsers and macros.
+
+#[doc(hidden)]
+pub use nom::{Context, Err, ErrorKind, Needed};
+
+use nom::IResult;
+
+use crate::ByteClass;
+
+/// Longest non empty prefix of bytes of `class`, as nom's `take_while1!`:
+/// `call!(take_while1, &CLASS)`. An input entirely made of such bytes is
+/// returned as is, like `take_while1_simd!` does.
+#[inline(always)]
+pub fn take_while1<'a>(input: &'a [u8], class: &ByteClass) -> IResult<&'a [u8], &'a [u8]> {
+    if input.is_empty() {
+        return Err(Err::Incomplete(Needed::Size(1)));
+    }
+    match crate::take_while1(class, input) {
+        Ok(x) => Ok(x),
+        Err(()) => Err(Err::Error(Context::Code(input, ErrorKind::TakeWhile1))),
+    }
+}
+
+/// `take_while1_class!(CLASS)` is `call!(take_while1, &CLASS)`.
+#[macro_export]
+macro_rules! take_while1_class (
+  ($input:expr, $class:expr) => (
+    $crate::nom_adapter::take_while1($input, &$class)
+  );
+);
+
+#[macro_export]
+macro_rules! take_while1_unrolled (
+  ($input:expr, $predicate: expr) => (
+    {
+      use $crate::nom_adapter::{Context, Err, ErrorKind, Needed};
+
+      let input = $input;
+
+      let mut i = 0usize;
+      let len = input.len();
+      let mut found = false;
+
+      loop {
+        if len - i < 8 {
+          break;
+        }
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+
+        if !$predicate(unsafe { *input.get_unchecked(i) }) {
+          found = true;
+          break;
+        }
+        i += 1;
+      }
+
+      if !found {
+        while i < len && $predicate(unsafe { *input.get_unchecked(i) }) {
+          i += 1;
+        }
+      }
+
+      if i == len {
+        // more data could extend the match, or provide its first byte
+        Err(Err::Incomplete(Needed::Unknown))
+      } else if i == 0 {
+        Err(Err::Error(Context::Code(input, ErrorKind::TakeWhile1)))
+      } else {
+        let (prefix, suffix) = input.split_at(i);
+        Ok((suffix, prefix))
+      }
+    }
+  );
+);
+
+#[macro_export]
+macro_rules! take_while1_simd (
+  ($input:expr, $predicate:expr, $ranges:expr) => ({
+      use $crate::nom_adapter::{Context, Err, ErrorKind, Needed};
+
+      let input = $input;
+      if input.is_empty() {
+          Err(Err::Incomplete(Needed::Size(1)))
+      } else {
+          match $crate::take_while1_simd(input, $predicate, $ranges) {
+              Ok(x) => Ok(x),
+              Err(()) => Err(Err::Error(Context::Code(input, ErrorKind::TakeWhile1))),
+          }
+      }
+  });
+  // forces an implementation instead of the one detected at runtime
+  ($input:expr, $predicate:expr, $ranges:expr, $path:expr) => ({
+      use $crate::nom_adapter::{Context, Err, ErrorKind, Needed};
+
+      let input = $input;
+      if input.is_empty() {
+          Err(Err::Incomplete(Needed::Size(1)))
+      } else {
+          match $crate::take_while1_with($path, input, $predicate, $ranges) {
+              Ok(x) => Ok(x),
+              Err(()) => Err(Err::Error(Context::Code(input, ErrorKind::TakeWhile1))),
+          }
+      }
+  });
+);
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use std::str::from_utf8;
+
+    #[test]
+    fn simd_test() {
+        fn is_token(c: u8) -> bool {
+            c > 0x20 && c < 0x7F
+        }
+
+        let range = b"\0 \x7F\x7F";
+        let input = &b"/abcd/efgh/ij kl/pouet/ 1234579"[..];
+        let res: IResult<&[u8], &[u8]> = take_while1_simd!(input, is_token, range);
+
+        let (i, o) = res.unwrap();
+        assert_eq!(from_utf8(i).unwrap(), " kl/pouet/ 1234579");
+        assert_eq!(from_utf8(o).unwrap(), "/abcd/efgh/ij");
+    }
+
+    #[test]
+    fn class_test() {
+        named!(uri, take_while1_class!(crate::http::URI));
+
+        assert_eq!(uri(&b"/a/b HTTP/1.1"[..]), Ok((&b" HTTP/1.1"[..], &b"/a/b"[..])));
+        assert!(uri(&b" HTTP/1.1"[..]).is_err());
+        assert!(uri(&b""[..]).unwrap_err().is_incomplete());
+    }
+}
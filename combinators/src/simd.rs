@@ -0,0 +1,339 @@
+//! SIMD scanners, and the runtime selection of their implementation.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Implementation used by `take_while1_simd`, picked once from the features
+/// of the CPU running the binary.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SimdPath {
+    /// Range comparisons on 32 bytes at a time.
+    Avx2,
+    /// `_mm_cmpestri`, 16 bytes at a time.
+    Sse42,
+    /// Unrolled loop over the predicate.
+    Scalar,
+}
+
+impl SimdPath {
+    pub fn name(self) -> &'static str {
+        match self {
+            SimdPath::Avx2 => "avx2",
+            SimdPath::Sse42 => "sse4.2",
+            SimdPath::Scalar => "scalar",
+        }
+    }
+
+    /// Whether the running CPU can use this implementation.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    pub fn is_supported(self) -> bool {
+        match self {
+            SimdPath::Avx2 => is_x86_feature_detected!("avx2"),
+            SimdPath::Sse42 => is_x86_feature_detected!("sse4.2"),
+            SimdPath::Scalar => true,
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    pub fn is_supported(self) -> bool {
+        self == SimdPath::Scalar
+    }
+
+    fn detect() -> SimdPath {
+        [SimdPath::Avx2, SimdPath::Sse42]
+            .iter()
+            .cloned()
+            .find(|path| path.is_supported())
+            .unwrap_or(SimdPath::Scalar)
+    }
+}
+
+// 0 until detected, then the `SimdPath` discriminant + 1
+static SIMD_PATH: AtomicU8 = AtomicU8::new(0);
+
+/// The implementation `take_while1_simd` uses on this CPU.
+#[inline(always)]
+pub fn simd_path() -> SimdPath {
+    match SIMD_PATH.load(Ordering::Relaxed) {
+        1 => SimdPath::Avx2,
+        2 => SimdPath::Sse42,
+        3 => SimdPath::Scalar,
+        _ => {
+            let path = SimdPath::detect();
+            SIMD_PATH.store(path as u8 + 1, Ordering::Relaxed);
+            path
+        }
+    }
+}
+
+/// Checks a range descriptor, which every implementation must read the same
+/// way.
+#[inline(always)]
+fn check_ranges(ranges: &[u8]) {
+    assert!(
+        ranges.len() <= 16 && ranges.len().is_multiple_of(2),
+        "a range descriptor holds at most 8 pairs of bounds, got {} bytes",
+        ranges.len()
+    );
+}
+
+/// Splits `input` after its longest prefix matching `predicate`, failing if
+/// that prefix is empty. `ranges` holds the same byte class as at most 8
+/// pairs of inclusive bounds of excluded bytes, as used by `_mm_cmpestri`.
+///
+/// Panics if `ranges` is longer than 16 bytes or has an odd length, whatever
+/// the implementation used.
+#[inline(always)]
+#[allow(clippy::result_unit_err)]
+pub fn take_while1_simd<'a, F>(
+    input: &'a [u8],
+    predicate: F,
+    ranges: &[u8],
+) -> Result<(&'a [u8], &'a [u8]), ()>
+where
+    F: FnMut(u8) -> bool,
+{
+    check_ranges(ranges);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    match simd_path() {
+        SimdPath::Avx2 => return unsafe { take_while1_avx2(input, predicate, ranges) },
+        SimdPath::Sse42 => return unsafe { take_while1_sse42(input, predicate, ranges) },
+        SimdPath::Scalar => {}
+    }
+
+    take_while1_scalar(input, predicate)
+}
+
+/// `take_while1_simd` using `path` instead of the detected implementation,
+/// or the scalar one if the CPU does not support `path`. Panics on the same
+/// descriptors.
+#[inline(always)]
+#[allow(clippy::result_unit_err)]
+pub fn take_while1_with<'a, F>(
+    path: SimdPath,
+    input: &'a [u8],
+    predicate: F,
+    ranges: &[u8],
+) -> Result<(&'a [u8], &'a [u8]), ()>
+where
+    F: FnMut(u8) -> bool,
+{
+    check_ranges(ranges);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    match path {
+        SimdPath::Avx2 if path.is_supported() => return unsafe { take_while1_avx2(input, predicate, ranges) },
+        SimdPath::Sse42 if path.is_supported() => return unsafe { take_while1_sse42(input, predicate, ranges) },
+        _ => {}
+    }
+
+    take_while1_scalar(input, predicate)
+}
+
+#[inline(always)]
+fn split(input: &[u8], i: usize) -> Result<(&[u8], &[u8]), ()> {
+    if i == 0 {
+        Err(())
+    } else {
+        let (prefix, suffix) = input.split_at(i);
+        Ok((suffix, prefix))
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn take_while1_avx2<'a, F>(
+    input: &'a [u8],
+    mut predicate: F,
+    ranges: &[u8],
+) -> Result<(&'a [u8], &'a [u8]), ()>
+where
+    F: FnMut(u8) -> bool,
+{
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{__m256i, _mm256_and_si256, _mm256_cmpeq_epi8, _mm256_loadu_si256,
+                         _mm256_max_epu8, _mm256_min_epu8, _mm256_movemask_epi8,
+                         _mm256_or_si256, _mm256_set1_epi8, _mm256_setzero_si256};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{__m256i, _mm256_and_si256, _mm256_cmpeq_epi8, _mm256_loadu_si256,
+                            _mm256_max_epu8, _mm256_min_epu8, _mm256_movemask_epi8,
+                            _mm256_or_si256, _mm256_set1_epi8, _mm256_setzero_si256};
+
+    let mut i = 0;
+
+    if input.len() >= 32 {
+        // `check_ranges` bounds the number of pairs
+        let pairs = ranges.len() / 2;
+        let mut bounds = [(_mm256_setzero_si256(), _mm256_setzero_si256()); 8];
+        for (bound, pair) in bounds.iter_mut().zip(ranges.chunks(2)) {
+            *bound = (_mm256_set1_epi8(pair[0] as i8), _mm256_set1_epi8(pair[1] as i8));
+        }
+
+        while input.len() - i >= 32 {
+            let bytes = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
+
+            // unsigned lo <= b <= hi, as max(b, lo) == b && min(b, hi) == b
+            let mut excluded = _mm256_setzero_si256();
+            for &(lo, hi) in &bounds[..pairs] {
+                let above = _mm256_cmpeq_epi8(_mm256_max_epu8(bytes, lo), bytes);
+                let below = _mm256_cmpeq_epi8(_mm256_min_epu8(bytes, hi), bytes);
+                excluded = _mm256_or_si256(excluded, _mm256_and_si256(above, below));
+            }
+
+            let mask = _mm256_movemask_epi8(excluded) as u32;
+            if mask != 0 {
+                return split(input, i + mask.trailing_zeros() as usize);
+            }
+            i += 32;
+        }
+    }
+
+    while i < input.len() && predicate(*input.get_unchecked(i)) {
+        i += 1;
+    }
+    split(input, i)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "sse4.2")]
+unsafe fn take_while1_sse42<'a, F>(
+    input: &'a [u8],
+    mut predicate: F,
+    ranges: &[u8],
+) -> Result<(&'a [u8], &'a [u8]), ()>
+where
+    F: FnMut(u8) -> bool,
+{
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{_mm_cmpestri, _mm_loadu_si128, _SIDD_CMP_RANGES,
+                         _SIDD_LEAST_SIGNIFICANT, _SIDD_UBYTE_OPS};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{_mm_cmpestri, _mm_loadu_si128, _SIDD_CMP_RANGES,
+                            _SIDD_LEAST_SIGNIFICANT, _SIDD_UBYTE_OPS};
+
+    let start = input.as_ptr() as usize;
+    let mut i = input.as_ptr() as usize;
+    let mut left = input.len();
+    let mut found = false;
+
+    if left >= 16 {
+        // `ranges` is usually shorter than the 16 bytes loaded
+        let mut padded = [0u8; 16];
+        padded[..ranges.len()].copy_from_slice(ranges);
+        let ranges16 = _mm_loadu_si128(padded.as_ptr() as *const _);
+        let ranges_len = ranges.len() as i32;
+        loop {
+            let sl = _mm_loadu_si128(i as *const _);
+
+            let idx = _mm_cmpestri(
+                ranges16,
+                ranges_len,
+                sl,
+                16,
+                _SIDD_LEAST_SIGNIFICANT | _SIDD_CMP_RANGES | _SIDD_UBYTE_OPS,
+            );
+
+            if idx != 16 {
+                i += idx as usize;
+                found = true;
+                break;
+            }
+
+            i += 16;
+            left -= 16;
+
+            if left < 16 {
+                break;
+            }
+        }
+    }
+
+    let mut i = i - start;
+    if !found {
+        while i < input.len() && predicate(*input.get_unchecked(i)) {
+            i += 1;
+        }
+    }
+
+    split(input, i)
+}
+
+#[inline(always)]
+fn take_while1_scalar<F>(input: &[u8], mut predicate: F) -> Result<(&[u8], &[u8]), ()>
+where
+    F: FnMut(u8) -> bool,
+{
+    let mut i = 0;
+
+    'unrolled: while input.len() - i >= 8 {
+        for _ in 0..8 {
+            if !predicate(input[i]) {
+                break 'unrolled;
+            }
+            i += 1;
+        }
+    }
+    while i < input.len() && predicate(input[i]) {
+        i += 1;
+    }
+
+    split(input, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simd_paths_test() {
+        use crate::http::{is_header_value_token, is_url_token, HEADER_VALUE_RANGES, URI_RANGES};
+
+
+        let mut inputs: Vec<Vec<u8>> = Vec::new();
+        for len in 0..70 {
+            for stop in 0..=len {
+                for &byte in &[b' ', b'\r', b'\t', 0x7F, 0x80, 0] {
+                    let mut input = vec![b'a'; len];
+                    if stop < len {
+                        input[stop] = byte;
+                    }
+                    inputs.push(input);
+                }
+            }
+        }
+
+        for input in &inputs {
+            let url = take_while1_scalar(input, is_url_token);
+            let header_value = take_while1_scalar(input, is_header_value_token);
+            for &path in &[SimdPath::Avx2, SimdPath::Sse42, SimdPath::Scalar] {
+                assert_eq!(take_while1_with(path, input, is_url_token, URI_RANGES), url, "{:?}", path);
+                assert_eq!(
+                    take_while1_with(path, input, is_header_value_token, HEADER_VALUE_RANGES),
+                    header_value,
+                    "{:?}",
+                    path
+                );
+            }
+            assert_eq!(take_while1_simd(input, is_url_token, URI_RANGES), url);
+        }
+    }
+
+    fn invalid_ranges(ranges: &[u8]) {
+        let input = [b'a'; 64];
+        for &path in &[SimdPath::Avx2, SimdPath::Sse42, SimdPath::Scalar] {
+            let result = std::panic::catch_unwind(|| take_while1_with(path, &input, |_| true, ranges));
+            assert!(result.is_err(), "{:?} accepted {} bytes of ranges", path, ranges.len());
+        }
+        assert!(std::panic::catch_unwind(|| take_while1_simd(&input, |_| true, ranges)).is_err());
+    }
+
+    #[test]
+    fn too_many_ranges_test() {
+        invalid_ranges(&[b'0'; 18]);
+        invalid_ranges(&[b'0'; 32]);
+    }
+
+    #[test]
+    fn odd_ranges_test() {
+        invalid_ranges(b"\0");
+        invalid_ranges(&[0, 0x20, 0x7F]);
+    }
+}
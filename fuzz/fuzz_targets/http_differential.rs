@@ -0,0 +1,16 @@
+//! Compares the HTTP request parsers to httparse on arbitrary pipelined
+//! requests, bodies included for the parsers that handle them.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use parser_benchmarks_fuzz::check_accepted;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(d) = check_accepted(data, &mut http_differential::contenders()) {
+        panic!("{}", d);
+    }
+    if let Some(d) = check_accepted(data, &mut http_differential::message_contenders()) {
+        panic!("{}", d);
+    }
+});
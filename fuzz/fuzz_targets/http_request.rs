@@ -0,0 +1,35 @@
+//! Feeds arbitrary bytes to every HTTP request parser, which must not crash,
+//! and checks that the streaming parser of nom-optimized reaches the same
+//! result whether the request arrives at once or in two parts.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nom_optimized_http::streaming::{RequestParser, Status};
+
+fuzz_target!(|data: &[u8]| {
+    for mut c in http_differential::contenders() {
+        let _ = c.parse(data);
+    }
+    for mut c in http_differential::message_contenders() {
+        let _ = c.parse(data);
+    }
+
+    let mut whole = RequestParser::new();
+    let expected = whole.parse(data);
+
+    // the first byte decides where the request is split
+    let split = data.first().map_or(0, |&b| b as usize % (data.len() + 1));
+    let mut parts = RequestParser::new();
+    let partial = parts.parse(&data[..split]);
+    let result = match partial {
+        Ok(Status::Partial) => parts.parse(data),
+        // an error or a complete request does not depend on the bytes after it
+        r => r,
+    };
+    assert_eq!(result, expected, "split at {}", split);
+    if let Ok(Status::Complete(_)) = result {
+        assert_eq!(parts.request(data), whole.request(data));
+        assert!(parts.headers(data).eq(whole.headers(data)));
+    }
+});
@@ -0,0 +1,11 @@
+//! Feeds arbitrary bytes to every JSON parser, which must not crash.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for mut c in json_differential::contenders() {
+        let _ = c.parse(data);
+    }
+});
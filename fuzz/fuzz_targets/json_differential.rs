@@ -0,0 +1,12 @@
+//! Compares the JSON parsers to serde_json on arbitrary documents.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use parser_benchmarks_fuzz::check_accepted;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(d) = check_accepted(data, &mut json_differential::contenders()) {
+        panic!("parsers disagree:\n{}", json_differential::report(&d));
+    }
+});
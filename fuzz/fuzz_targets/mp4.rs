@@ -0,0 +1,18 @@
+//! Feeds arbitrary bytes to the MP4 box parser, and to the sample index,
+//! fragments and headers built from the boxes, which must not crash.
+
+#![no_main]
+
+use harness::ParserUnderTest;
+use libfuzzer_sys::fuzz_target;
+use mp4::header::Movie;
+use mp4::{fragment, sample};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(boxes) = mp4::full_data_interpreter(data) {
+        let _ = sample::index(&boxes);
+        let _ = fragment::fragments(&boxes);
+        let _ = Movie::new(&boxes);
+    }
+    let _ = mp4::NomMp4.parse_normalized(data);
+});
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Seeds the corpus of every fuzz target with the benchmark fixtures.
+#
+# canada.json (2MB of numbers) is left out: libFuzzer sizes its inputs after
+# the largest seed, and would spend its time on huge documents.
+set -e
+
+cd "$(dirname "$0")"
+root=..
+
+seed() {
+  target=$1
+  shift
+  mkdir -p "corpus/$target"
+  cp "$@" "corpus/$target/"
+}
+
+for target in http_request http_differential; do
+  seed $target $root/http/http-requests.txt $root/http/bigger.txt $root/http/http-post-requests.txt
+done
+
+for target in json json_differential; do
+  seed $target $root/json/test.json $root/json/data.json $root/json/apache_builds.json
+done
+
+seed mp4 $root/mp4/small.mp4 $root/mp4/fragmented.mp4
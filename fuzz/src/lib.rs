@@ -0,0 +1,41 @@
+//! Helpers shared by the fuzz targets.
+
+use harness::differential::{Contender, Divergence};
+
+/// Walks the messages of `input` like `harness::differential::run`, using
+/// the first contender to find where each message ends, and returns the
+/// first message that a contender accepts but does not consume or produce
+/// the same as the reference.
+///
+/// Unlike `run`, a contender rejecting what the reference accepts is not a
+/// divergence: the parsers are not equally lenient, and random inputs would
+/// keep hitting the known differences instead of actual bugs.
+pub fn check_accepted<T: PartialEq>(input: &[u8], contenders: &mut [Contender<T>]) -> Option<Divergence<T>> {
+    let mut offset = 0;
+
+    while offset < input.len() {
+        let message = &input[offset..];
+        let outcomes: Vec<_> = contenders.iter_mut().map(|c| (c.name, c.parse(message))).collect();
+
+        let consumed = match &outcomes[0].1 {
+            Ok((consumed, reference)) => {
+                let agree = outcomes[1..].iter().all(|(_, outcome)| match outcome {
+                    Ok((c, o)) => c == consumed && o == reference,
+                    Err(_) => true,
+                });
+                if !agree {
+                    return Some(Divergence { offset, input: message[..*consumed].to_vec(), outcomes });
+                }
+                *consumed
+            }
+            Err(_) => return None,
+        };
+
+        if consumed == 0 {
+            return None;
+        }
+        offset += consumed;
+    }
+
+    None
+}
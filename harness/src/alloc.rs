@@ -0,0 +1,126 @@
+//! Global allocator counting the allocations of the benchmarks.
+//!
+//! The benchmark binaries install [`CountingAllocator`] with
+//! [`count_allocations!`](crate::count_allocations), which `http_benches!`
+//! and `json_benches!` do for them. The runner then reports, next to the
+//! timings, how many allocations and bytes one iteration costs and how much
+//! heap it needs at its peak.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// `System`, counting what goes through it. Reallocations count as
+/// allocations of their new size.
+pub struct CountingAllocator;
+
+fn allocated(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(live, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc(layout);
+        if !p.is_null() {
+            allocated(layout.size());
+        }
+        p
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let p = System.alloc_zeroed(layout);
+        if !p.is_null() {
+            allocated(layout.size());
+        }
+        p
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let p = System.realloc(ptr, layout, new_size);
+        if !p.is_null() {
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        p
+    }
+}
+
+/// Installs [`CountingAllocator`] as the global allocator of the binary.
+#[macro_export]
+macro_rules! count_allocations {
+    () => {
+        #[global_allocator]
+        static ALLOCATOR: $crate::alloc::CountingAllocator = $crate::alloc::CountingAllocator;
+    };
+}
+
+/// Counters since the start of the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub allocations: u64,
+    pub bytes: u64,
+    /// Bytes currently allocated.
+    pub live: usize,
+    /// Highest value of `live` since the last `reset_peak`.
+    pub peak: usize,
+}
+
+pub fn stats() -> Stats {
+    Stats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+        live: LIVE.load(Ordering::Relaxed),
+        peak: PEAK.load(Ordering::Relaxed),
+    }
+}
+
+/// Starts tracking the peak from the bytes currently allocated.
+pub fn reset_peak() {
+    PEAK.store(LIVE.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Whether `CountingAllocator` is the global allocator. Otherwise the
+/// counters stay at 0.
+pub fn is_installed() -> bool {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    drop(std::hint::black_box(Box::new(0u64)));
+    ALLOCATIONS.load(Ordering::Relaxed) != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    count_allocations!();
+
+    #[test]
+    fn counts() {
+        assert!(is_installed());
+
+        // the test harness allocates from other threads, only check lower
+        // bounds
+        let before = stats();
+        let v: Vec<u8> = Vec::with_capacity(1000);
+        let after = stats();
+        assert!(after.allocations > before.allocations);
+        assert!(after.bytes >= before.bytes + 1000);
+        drop(v);
+
+        reset_peak();
+        let v: Vec<u64> = Vec::with_capacity(1 << 20);
+        drop(v);
+        assert!(stats().peak >= 8 << 20);
+    }
+}
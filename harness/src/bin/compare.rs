@@ -0,0 +1,99 @@
+//! Compares two result files written with `--json` and reports the
+//! benchmarks that got slower by more than a threshold. Changes within the
+//! confidence intervals of the measurements are not counted.
+//!
+//! Usage: `compare [--threshold <percent>] <old.json> <new.json>`
+//!
+//! Exits with status 1 when there is a regression.
+
+use std::env;
+use std::process;
+
+use harness::results::{self, Results};
+use harness::runner;
+
+const DEFAULT_THRESHOLD: f64 = 5.0;
+
+fn usage() -> ! {
+    eprintln!("usage: compare [--threshold <percent>] <old.json> <new.json>");
+    process::exit(2);
+}
+
+fn load(path: &str) -> Results {
+    Results::load(path).unwrap_or_else(|e| {
+        eprintln!("could not read {}: {}", path, e);
+        process::exit(2);
+    })
+}
+
+fn main() {
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threshold" => {
+                threshold = match args.next().and_then(|t| t.parse().ok()) {
+                    Some(t) => t,
+                    None => usage(),
+                }
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        usage();
+    }
+
+    let old = load(&paths[0]);
+    let new = load(&paths[1]);
+
+    for (name, results) in [("old", &old), ("new", &new)] {
+        let env = &results.environment;
+        println!(
+            "{}: {} ({}), cpu: {}",
+            name,
+            env.revision.as_deref().unwrap_or("unknown revision"),
+            env.rustc,
+            env.cpu_features.join(",")
+        );
+    }
+    println!();
+
+    let (changes, removed, added) = results::compare(&old, &new);
+    let mut regressions = 0;
+    for change in &changes {
+        let status = if change.is_regression(threshold) {
+            regressions += 1;
+            "REGRESSION"
+        } else if change.is_improvement(threshold) {
+            "improvement"
+        } else if change.percent.abs() > threshold {
+            "within noise"
+        } else {
+            ""
+        };
+        println!(
+            "{:<5} {:<22} {:<24} {} -> {} {:+7.1}% {}",
+            change.new.suite,
+            change.new.parser,
+            change.new.fixture,
+            runner::format(change.old).trim_start(),
+            runner::format(change.new).trim_start(),
+            change.percent,
+            status
+        );
+    }
+    for m in removed {
+        println!("{:<5} {:<22} {:<24} only in old", m.suite, m.parser, m.fixture);
+    }
+    for m in added {
+        println!("{:<5} {:<22} {:<24} only in new", m.suite, m.parser, m.fixture);
+    }
+
+    println!("\n{} regressions above {}%", regressions, threshold);
+    if regressions > 0 {
+        process::exit(1);
+    }
+}
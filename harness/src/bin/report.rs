@@ -0,0 +1,60 @@
+//! Renders a result file written with `--json` as markdown tables, in the
+//! format of the READMEs, and optionally as an HTML page with bar charts.
+//!
+//! Usage: `report [--suite <name>] [--html <path>] <results.json>`
+
+use std::env;
+use std::fs;
+use std::process;
+
+use harness::report::{self, Table};
+use harness::results::Results;
+
+fn usage() -> ! {
+    eprintln!("usage: report [--suite <name>] [--html <path>] <results.json>");
+    process::exit(2);
+}
+
+fn main() {
+    let mut suite = None;
+    let mut html = None;
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--suite" => suite = Some(args.next().unwrap_or_else(|| usage())),
+            "--html" => html = Some(args.next().unwrap_or_else(|| usage())),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let path = path.unwrap_or_else(|| usage());
+
+    let results = Results::load(&path).unwrap_or_else(|e| {
+        eprintln!("could not read {}: {}", path, e);
+        process::exit(2);
+    });
+
+    let env = &results.environment;
+    for name in report::suites(&results) {
+        if suite.as_ref().is_some_and(|s| s != name) {
+            continue;
+        }
+        println!("### {}\n", name);
+        println!("{}", Table::new(&results, name).markdown());
+        println!(
+            "{}, revision {}, CPU features: {}\n",
+            env.rustc,
+            env.revision.as_deref().unwrap_or("unknown"),
+            env.cpu_features.join(", ")
+        );
+    }
+
+    if let Some(html) = html {
+        if let Err(e) = fs::write(&html, report::html(&results)) {
+            eprintln!("could not write {}: {}", html, e);
+            process::exit(1);
+        }
+    }
+}
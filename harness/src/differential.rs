@@ -0,0 +1,109 @@
+//! Runs several parsers over the same corpus and reports where their
+//! normalized results disagree, so that a parser cannot look fast by
+//! silently parsing less than the others.
+
+use std::fmt;
+
+use crate::{Error, ParserUnderTest};
+
+/// Consumed length and normalized output of one parser on one message.
+pub type Outcome<T> = Result<(usize, T), Error>;
+
+/// A parser taking part in a differential run.
+pub struct Contender<T> {
+    pub name: &'static str,
+    parse: Box<dyn FnMut(&[u8]) -> Outcome<T>>,
+}
+
+impl<T> Contender<T> {
+    pub fn new<P>(mut parser: P) -> Contender<T>
+    where
+        P: ParserUnderTest<Output = T> + 'static,
+    {
+        Contender {
+            name: P::NAME,
+            parse: Box::new(move |input| parser.parse_normalized(input)),
+        }
+    }
+
+    pub fn parse(&mut self, input: &[u8]) -> Outcome<T> {
+        (self.parse)(input)
+    }
+}
+
+/// A message on which the contenders did not all agree.
+#[derive(Debug)]
+pub struct Divergence<T> {
+    /// Offset of the message in the corpus.
+    pub offset: usize,
+    /// The offending message, as delimited by the reference parser, or the
+    /// rest of the corpus if the reference parser failed.
+    pub input: Vec<u8>,
+    pub outcomes: Vec<(&'static str, Outcome<T>)>,
+}
+
+/// Longest input excerpt shown when displaying a divergence.
+const MAX_EXCERPT: usize = 1024;
+
+impl<T: fmt::Display> fmt::Display for Divergence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "divergence at offset {}:", self.offset)?;
+
+        let excerpt = &self.input[..self.input.len().min(MAX_EXCERPT)];
+        writeln!(f, "  input: \"{}\"", excerpt.escape_ascii())?;
+        if excerpt.len() < self.input.len() {
+            writeln!(f, "  ({} more bytes)", self.input.len() - excerpt.len())?;
+        }
+
+        for (name, outcome) in &self.outcomes {
+            match outcome {
+                Ok((consumed, o)) => writeln!(f, "  {} consumed {} bytes:\n{}", name, consumed, o)?,
+                Err(e) => writeln!(f, "  {} failed: {}", name, e)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses every message of `corpus` with all `contenders` and returns the
+/// messages on which they disagree.
+///
+/// The first contender is the reference: its consumed length decides where
+/// the next message starts. Every contender is given the rest of the corpus
+/// from that point, as in the benchmark loop, and must consume the same
+/// number of bytes and produce the same output. The run stops at the first
+/// message the reference cannot parse.
+pub fn run<T: PartialEq>(corpus: &[u8], contenders: &mut [Contender<T>]) -> Vec<Divergence<T>> {
+    let mut divergences = Vec::new();
+    let mut offset = 0;
+
+    while offset < corpus.len() {
+        let input = &corpus[offset..];
+        let outcomes: Vec<_> = contenders.iter_mut().map(|c| (c.name, c.parse(input))).collect();
+
+        let next = match outcomes.first() {
+            Some((_, Ok((consumed, _)))) if *consumed > 0 => Some(offset + consumed),
+            _ => None,
+        };
+
+        let agree = outcomes.windows(2).all(|w| match (&w[0].1, &w[1].1) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        });
+        if !agree || next.is_none() {
+            divergences.push(Divergence {
+                offset,
+                input: corpus[offset..next.unwrap_or(corpus.len())].to_vec(),
+                outcomes,
+            });
+        }
+
+        match next {
+            Some(next) => offset = next,
+            None => break,
+        }
+    }
+
+    divergences
+}
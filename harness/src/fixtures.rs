@@ -0,0 +1,61 @@
+//! Inputs shared by every suite of the same format.
+
+pub mod http {
+    /// A small HTTP request.
+    pub const ONE: &[u8] = b"GET / HTTP/1.1
+Host: www.reddit.com
+User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10.8; rv:15.0) Gecko/20100101 Firefox/15.0.1
+Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8
+Accept-Language: en-us,en;q=0.5
+Accept-Encoding: gzip, deflate
+Connection: keep-alive
+
+";
+
+    /// A larger request (long path, complete user agent header and a long cookie).
+    pub const HTTPARSE_EXAMPLE: &[u8] = b"GET /wp-content/uploads/2010/03/hello-kitty-darth-vader-pink.jpg HTTP/1.1\r\n\
+Host: www.kittyhell.com\r\n\
+User-Agent: Mozilla/5.0 (Macintosh; U; Intel Mac OS X 10.6; ja-JP-mac; rv:1.9.2.3) Gecko/20100401 Firefox/3.6.3 Pathtraq/0.9\r\n\
+Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
+Accept-Language: ja,en-us;q=0.7,en;q=0.3\r\n\
+Accept-Encoding: gzip,deflate\r\n\
+Accept-Charset: Shift_JIS,utf-8;q=0.7,*;q=0.7\r\n\
+Keep-Alive: 115\r\n\
+Connection: keep-alive\r\n\
+Cookie: wp_ozh_wsa_visits=2; wp_ozh_wsa_visit_lasttime=xxxxxxxxxx; __utma=xxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.x; __utmz=xxxxxxxxx.xxxxxxxxxx.x.x.utmccn=(referral)|utmcsr=reader.livedoor.com|utmcct=/reader/|utmcmd=referral\r\n\r\n";
+
+    /// `http-requests.txt`, 55 pipelined requests.
+    pub const SMALL: &[u8] = include_bytes!("../../http/http-requests.txt");
+
+    /// `bigger.txt`, 275 pipelined requests.
+    pub const BIGGER: &[u8] = include_bytes!("../../http/bigger.txt");
+
+    /// `http-responses.txt`, 55 pipelined response heads without bodies.
+    pub const RESPONSES: &[u8] = include_bytes!("../../http/http-responses.txt");
+
+    /// `http-post-requests.txt`, 44 pipelined requests, most of them with a
+    /// body delimited by `Content-Length` or chunked, some with trailers.
+    pub const POST: &[u8] = include_bytes!("../../http/http-post-requests.txt");
+}
+
+pub mod json {
+    pub const BASIC: &[u8] = b"  { \"a\"\t: 42,
+  \"b\": [ \"x\", \"y\", 12 ] ,
+  \"c\": { \"hello\" : \"world\"
+  }
+  }  ";
+
+    pub const DATA: &[u8] = include_bytes!("../../json/data.json");
+    pub const CANADA: &[u8] = include_bytes!("../../json/canada.json");
+    pub const APACHE: &[u8] = include_bytes!("../../json/apache_builds.json");
+    pub const TEST: &[u8] = include_bytes!("../../json/test.json");
+}
+
+pub mod mp4 {
+    pub const SMALL: &[u8] = include_bytes!("../../mp4/small.mp4");
+
+    /// `fragmented.mp4`, written by `mp4::generate::dash`: an empty `moov`, a
+    /// `sidx`, 10 `moof` and `mdat` pairs with a video and an audio track,
+    /// and a `mfra`.
+    pub const FRAGMENTED: &[u8] = include_bytes!("../../mp4/fragmented.mp4");
+}
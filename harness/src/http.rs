@@ -0,0 +1,83 @@
+//! Normalized HTTP messages, shared by the HTTP suites.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: Vec<u8>,
+    pub uri: Vec<u8>,
+    /// Minor version, as in `HTTP/1.<version>`.
+    pub version: u8,
+    pub headers: Vec<Header>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// Minor version, as in `HTTP/1.<version>`.
+    pub version: u8,
+    pub code: u16,
+    pub reason: Vec<u8>,
+    pub headers: Vec<Header>,
+}
+
+/// A request along with its body, for parsers that handle body framing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub request: Request,
+    /// The body, with the chunked transfer coding removed.
+    pub body: Vec<u8>,
+    /// Trailer fields of a chunked body.
+    pub trailers: Vec<Header>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub name: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl Header {
+    pub fn new(name: &[u8], value: &[u8]) -> Header {
+        Header {
+            name: name.to_vec(),
+            value: value.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "    {} {} HTTP/1.{}",
+            self.method.escape_ascii(),
+            self.uri.escape_ascii(),
+            self.version
+        )?;
+        for h in &self.headers {
+            writeln!(f, "    {}: {}", h.name.escape_ascii(), h.value.escape_ascii())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.request)?;
+        writeln!(f, "    body ({} bytes): {}", self.body.len(), self.body.escape_ascii())?;
+        for h in &self.trailers {
+            writeln!(f, "    trailer {}: {}", h.name.escape_ascii(), h.value.escape_ascii())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "    HTTP/1.{} {} {}", self.version, self.code, self.reason.escape_ascii())?;
+        for h in &self.headers {
+            writeln!(f, "    {}: {}", h.name.escape_ascii(), h.value.escape_ascii())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//! Normalized JSON value, shared by the JSON suites.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    /// String contents with escape sequences resolved.
+    String(String),
+    Array(Vec<Value>),
+    /// Object members, sorted by key so that hash map based parsers compare
+    /// equal regardless of iteration order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Builds an `Object` from members in any order.
+    ///
+    /// A key appearing several times keeps its last value, as in serde_json.
+    /// Parsers that do not merge duplicate keys themselves must give the
+    /// members in document order.
+    pub fn object<I>(members: I) -> Value
+    where
+        I: IntoIterator<Item = (String, Value)>,
+    {
+        let mut members: Vec<_> = members.into_iter().collect();
+        // stable, so that duplicates stay in the given order
+        members.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut merged: Vec<(String, Value)> = Vec::with_capacity(members.len());
+        for (k, v) in members {
+            match merged.last_mut() {
+                Some(last) if last.0 == k => last.1 = v,
+                _ => merged.push((k, v)),
+            }
+        }
+        Value::Object(merged)
+    }
+
+    /// Returns the JSON pointer of the first place where `self` and `other`
+    /// differ, or `None` if they are equal.
+    pub fn first_difference(&self, other: &Value) -> Option<String> {
+        match (self, other) {
+            (Value::Array(a), Value::Array(b)) => {
+                for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                    if let Some(path) = a.first_difference(b) {
+                        return Some(format!("/{}{}", i, path));
+                    }
+                }
+                if a.len() != b.len() {
+                    Some(format!("/{}", a.len().min(b.len())))
+                } else {
+                    None
+                }
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                for ((ka, a), (kb, b)) in a.iter().zip(b) {
+                    if ka != kb {
+                        return Some(format!("/{}", ka.min(kb)));
+                    }
+                    if let Some(path) = a.first_difference(b) {
+                        return Some(format!("/{}{}", ka, path));
+                    }
+                }
+                if a.len() != b.len() {
+                    let (ka, _) = if a.len() > b.len() { &a[b.len()] } else { &b[a.len()] };
+                    Some(format!("/{}", ka))
+                } else {
+                    None
+                }
+            }
+            (a, b) if a == b => None,
+            _ => Some(String::new()),
+        }
+    }
+}
+
+/// Compact JSON-like rendering, strings use Rust escapes.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{:?}", n),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Array(v) => {
+                f.write_str("[")?;
+                for (i, v) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(m) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{:?}:{}", k, v)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Resolves the escape sequences of a JSON string body, for parsers that
+/// only recognize strings and hand back the raw slice between the quotes.
+///
+/// Invalid escapes and unpaired surrogates become U+FFFD, so that they show
+/// up as a divergence instead of a panic.
+pub fn unescape(raw: &str) -> String {
+    if !raw.contains('\\') {
+        return raw.to_string();
+    }
+
+    let mut s = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            s.push(c);
+            continue;
+        }
+
+        let c = match chars.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => match hex4(&mut chars) {
+                Some(hi @ 0xD800..=0xDBFF) => {
+                    let mut rest = chars.clone();
+                    match (rest.next(), rest.next(), hex4(&mut rest)) {
+                        (Some('\\'), Some('u'), Some(lo @ 0xDC00..=0xDFFF)) => {
+                            chars = rest;
+                            char::from_u32(0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00))
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => char::REPLACEMENT_CHARACTER,
+                    }
+                }
+                Some(c) => char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER),
+                None => char::REPLACEMENT_CHARACTER,
+            },
+            _ => char::REPLACEMENT_CHARACTER,
+        };
+        s.push(c);
+    }
+
+    s
+}
+
+fn hex4(chars: &mut std::str::Chars) -> Option<u32> {
+    let digits: String = chars.take(4).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_sequences() {
+        assert_eq!(unescape(r#"plain"#), "plain");
+        assert_eq!(unescape(r#"a\"b\\c\/d\n\r\t"#), "a\"b\\c/d\n\r\t");
+        assert_eq!(unescape(r#"\u00e9\ud83d\ude00"#), "é😀");
+        assert_eq!(unescape(r#"\x"#), "\u{fffd}");
+        assert_eq!(unescape(r#"\ud83d!"#), "\u{fffd}!");
+    }
+
+    #[test]
+    fn difference_path() {
+        let a = Value::object(vec![
+            ("a".to_string(), Value::Array(vec![Value::Null, Value::Number(1.0)])),
+            ("b".to_string(), Value::Bool(true)),
+        ]);
+        let mut b = a.clone();
+        assert_eq!(a.first_difference(&b), None);
+
+        if let Value::Object(m) = &mut b {
+            m[0].1 = Value::Array(vec![Value::Null, Value::Number(2.0)]);
+        }
+        assert_eq!(a.first_difference(&b), Some("/a/1".to_string()));
+        assert_eq!(a.to_string(), r#"{"a":[null,1.0],"b":true}"#);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let o = Value::object(vec![
+            ("b".to_string(), Value::Null),
+            ("a".to_string(), Value::Number(1.0)),
+            ("a".to_string(), Value::Number(2.0)),
+        ]);
+        assert_eq!(o.to_string(), r#"{"a":2.0,"b":null}"#);
+    }
+}
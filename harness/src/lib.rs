@@ -0,0 +1,210 @@
+//! Shared benchmark harness for the parser suites.
+//!
+//! Every suite implements [`ParserUnderTest`] for its parser and then uses
+//! one of the `*_benches!` macros to get the usual set of benchmark
+//! functions over the fixtures in [`fixtures`], instead of copying the
+//! benchmark loop and `include_bytes!` calls around.
+
+use std::fmt;
+
+pub use std::hint::black_box;
+
+pub use measure::Bencher;
+
+pub mod alloc;
+pub mod differential;
+pub mod fixtures;
+pub mod http;
+pub mod json;
+pub mod measure;
+pub mod report;
+pub mod results;
+pub mod runner;
+
+/// Error returned by a parser under test, already formatted for display.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(pub String);
+
+impl Error {
+    pub fn new<T: fmt::Debug>(err: T) -> Error {
+        Error(format!("{:?}", err))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A parser taking part in the benchmarks.
+pub trait ParserUnderTest {
+    /// Parser independent representation of one parsed message, used to
+    /// compare implementations against each other.
+    type Output;
+
+    /// Name used when reporting results.
+    const NAME: &'static str;
+
+    /// Parses one message at the start of `input` and returns the number of
+    /// bytes consumed. This is the code path being measured.
+    fn parse(&mut self, input: &[u8]) -> Result<usize, Error>;
+
+    /// Parses one message at the start of `input` and converts it to the
+    /// normalized `Output`, along with the number of bytes consumed.
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), Error>;
+
+    /// Implementation choices made at runtime, such as the SIMD code path
+    /// picked for the current CPU, as `(name, value)` pairs. They are printed
+    /// with the results and recorded along with them.
+    fn details() -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+/// Parses every message in `input` with `parser`.
+///
+/// `input` may hold several messages back to back, as in a pipelined HTTP
+/// buffer. Panics if the parser fails or stops making progress.
+pub fn parse_all<P: ParserUnderTest>(parser: &mut P, input: &[u8]) -> usize {
+    let mut buf = input;
+    let mut count = 0;
+
+    while !buf.is_empty() {
+        match parser.parse(buf) {
+            Ok(0) => panic!("{} consumed no input", P::NAME),
+            Ok(consumed) => buf = &buf[consumed..],
+            Err(err) => panic!("{} got err: {}", P::NAME, err),
+        }
+        count += 1;
+    }
+
+    count
+}
+
+/// Normalized version of [`parse_all`].
+pub fn parse_all_normalized<P: ParserUnderTest>(
+    parser: &mut P,
+    input: &[u8],
+) -> Result<Vec<P::Output>, Error> {
+    let mut buf = input;
+    let mut v = Vec::new();
+
+    while !buf.is_empty() {
+        let (consumed, o) = parser.parse_normalized(buf)?;
+        if consumed == 0 {
+            return Err(Error(format!("{} consumed no input", P::NAME)));
+        }
+        v.push(o);
+        buf = &buf[consumed..];
+    }
+
+    Ok(v)
+}
+
+/// Returns `P::NAME` for a parser given as an expression, for the macros.
+pub fn name_of<P: ParserUnderTest>(_: &P) -> &'static str {
+    P::NAME
+}
+
+/// Returns `P::details()` for a parser given as an expression, for the
+/// macros.
+pub fn details_of<P: ParserUnderTest>(_: &P) -> Vec<(&'static str, String)> {
+    P::details()
+}
+
+/// Measures `parser` over the whole of `input`.
+pub fn bench<P: ParserUnderTest>(b: &mut Bencher, parser: &mut P, input: &[u8]) {
+    b.bytes = input.len() as u64;
+    b.iter(|| parse_all(parser, black_box(input)));
+}
+
+/// Defines the HTTP request benchmarks and `main` for a [`ParserUnderTest`],
+/// see [`runner`] for the command line. Installs the counting allocator of
+/// [`alloc`].
+///
+/// An optional second parser, for responses, is measured over
+/// [`fixtures::http::RESPONSES`]. Benchmark functions of the suite can be
+/// appended after a `;`.
+#[macro_export]
+macro_rules! http_benches {
+    ($parser:expr) => {
+        $crate::http_benches!($parser;);
+    };
+    // with a response parser, measured over the response fixture
+    ($parser:expr, $response:expr) => {
+        $crate::http_benches!($parser, $response;);
+    };
+    ($parser:expr, $response:expr; $($extra:ident),*) => {
+        fn responses_test(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $response, $crate::fixtures::http::RESPONSES)
+        }
+
+        $crate::http_benches!($parser; responses_test $(, $extra)*);
+    };
+    // extra benchmarks of the suite, defined by the caller
+    ($parser:expr; $($extra:ident),*) => {
+        $crate::count_allocations!();
+
+        fn one_test(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::http::ONE)
+        }
+
+        fn small_test(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::http::SMALL)
+        }
+
+        fn bigger_test(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::http::BIGGER)
+        }
+
+        fn httparse_example_test(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::http::HTTPARSE_EXAMPLE)
+        }
+
+        fn main() {
+            $crate::runner::main("http", $crate::name_of(&$parser), &$crate::details_of(&$parser), &[
+                $crate::runner::Benchmark { name: "one_test", run: one_test },
+                $crate::runner::Benchmark { name: "small_test", run: small_test },
+                $crate::runner::Benchmark { name: "bigger_test", run: bigger_test },
+                $crate::runner::Benchmark { name: "httparse_example_test", run: httparse_example_test },
+                $($crate::runner::Benchmark { name: stringify!($extra), run: $extra },)*
+            ]);
+        }
+    };
+}
+
+/// Defines the JSON benchmarks and `main` for a [`ParserUnderTest`], see
+/// [`runner`] for the command line. Installs the counting allocator of
+/// [`alloc`].
+#[macro_export]
+macro_rules! json_benches {
+    ($parser:expr) => {
+        $crate::count_allocations!();
+
+        fn basic(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::json::BASIC)
+        }
+
+        fn data(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::json::DATA)
+        }
+
+        fn apache(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::json::APACHE)
+        }
+
+        fn canada(b: &mut $crate::Bencher) {
+            $crate::bench(b, &mut $parser, $crate::fixtures::json::CANADA)
+        }
+
+        fn main() {
+            $crate::runner::main("json", $crate::name_of(&$parser), &$crate::details_of(&$parser), &[
+                $crate::runner::Benchmark { name: "basic", run: basic },
+                $crate::runner::Benchmark { name: "data", run: data },
+                $crate::runner::Benchmark { name: "apache", run: apache },
+                $crate::runner::Benchmark { name: "canada", run: canada },
+            ]);
+        }
+    };
+}
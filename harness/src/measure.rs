@@ -0,0 +1,267 @@
+//! Measurement loop of the suites, replacing bencher's.
+//!
+//! A benchmark function receives a [`Bencher`] and hands the code to measure
+//! to [`Bencher::iter`], as with bencher. The benchmark is first run for a
+//! warmup period, which also estimates how many iterations fit in a sample,
+//! then a fixed number of samples is taken. Samples outside the Tukey fences
+//! (1.5 times the interquartile range beyond the quartiles) are rejected as
+//! outliers, usually caused by interrupts or frequency changes, and a 95%
+//! confidence interval of the mean is computed from the remaining ones.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Handed to the benchmark functions, runs the measured code.
+pub struct Bencher {
+    /// Bytes processed by one iteration, to report a throughput.
+    pub bytes: u64,
+    iterations: u64,
+    elapsed: Duration,
+    skipped: Option<String>,
+}
+
+impl Bencher {
+    /// Marks the benchmark as not runnable here, for instance because the
+    /// CPU lacks the instructions it measures. It is then reported as
+    /// skipped instead of being measured.
+    pub fn skip(&mut self, reason: &str) {
+        self.skipped = Some(reason.to_string());
+    }
+
+    /// Runs `f` as many times as the current sample needs, and times it.
+    pub fn iter<T, F: FnMut() -> T>(&mut self, mut f: F) {
+        let start = Instant::now();
+        for _ in 0..self.iterations {
+            black_box(f());
+        }
+        self.elapsed = start.elapsed();
+    }
+}
+
+fn bencher(benchmark: fn(&mut Bencher), iterations: u64) -> Bencher {
+    let mut b = Bencher {
+        bytes: 0,
+        iterations,
+        elapsed: Duration::ZERO,
+        skipped: None,
+    };
+    benchmark(&mut b);
+    b
+}
+
+/// Runs `benchmark` once, with `iterations` iterations of its measured code.
+/// Returns the time taken by those iterations and the bytes of one.
+pub fn run_n(benchmark: fn(&mut Bencher), iterations: u64) -> (Duration, u64) {
+    let b = bencher(benchmark, iterations);
+    (b.elapsed, b.bytes)
+}
+
+/// Runs `benchmark` without iterating its measured code, and returns why it
+/// skipped itself, if it did.
+pub fn skipped(benchmark: fn(&mut Bencher)) -> Option<String> {
+    bencher(benchmark, 0).skipped
+}
+
+/// How long a benchmark is measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// Time spent running the benchmark before taking samples.
+    pub warmup: Duration,
+    /// Number of samples taken, at least 2.
+    pub samples: usize,
+    /// Approximate duration of one sample.
+    pub sample_time: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            warmup: Duration::from_millis(500),
+            samples: 100,
+            sample_time: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Statistics of the samples of one benchmark, in ns per iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub median: f64,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    /// 95% confidence interval of the mean.
+    pub low: f64,
+    pub high: f64,
+    /// Samples kept.
+    pub samples: usize,
+    /// Samples rejected as outliers.
+    pub outliers: usize,
+}
+
+/// Two-sided 97.5% quantiles of Student's t distribution for 1 to 10
+/// degrees of freedom.
+const T_975: [f64; 10] = [12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228];
+
+fn t_975(degrees: usize) -> f64 {
+    if degrees <= T_975.len() {
+        return T_975[degrees.max(1) - 1];
+    }
+    // Cornish-Fisher expansion around the normal quantile, within 1e-3 of
+    // the exact value from 10 degrees of freedom on
+    let z: f64 = 1.959964;
+    let n = degrees as f64;
+    z + (z.powi(3) + z) / (4.0 * n) + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * n * n)
+}
+
+/// Percentile `p` (between 0 and 1) of sorted `samples`, interpolated
+/// linearly.
+fn percentile(samples: &[f64], p: f64) -> f64 {
+    let rank = p * (samples.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    samples[lower] + (samples[upper] - samples[lower]) * (rank - lower as f64)
+}
+
+impl Summary {
+    /// Rejects the outliers of `samples` and summarizes the others.
+    pub fn new(samples: &[f64]) -> Summary {
+        assert!(samples.len() >= 2, "at least 2 samples are needed");
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let (q1, q3) = (percentile(&sorted, 0.25), percentile(&sorted, 0.75));
+        let fence = 1.5 * (q3 - q1);
+        let kept: Vec<f64> = sorted.iter().copied().filter(|s| *s >= q1 - fence && *s <= q3 + fence).collect();
+
+        let n = kept.len() as f64;
+        let mean = kept.iter().sum::<f64>() / n;
+        let std_dev = if kept.len() > 1 {
+            (kept.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let margin = t_975(kept.len().saturating_sub(1)) * std_dev / n.sqrt();
+
+        Summary {
+            median: percentile(&kept, 0.5),
+            mean,
+            min: kept[0],
+            max: kept[kept.len() - 1],
+            std_dev,
+            low: mean - margin,
+            high: mean + margin,
+            samples: kept.len(),
+            outliers: samples.len() - kept.len(),
+        }
+    }
+}
+
+/// Measures `benchmark` according to `config`. Returns its summary and the
+/// bytes of one iteration.
+pub fn run(config: &Config, benchmark: fn(&mut Bencher)) -> (Summary, u64) {
+    // doubles the iterations until the warmup time is spent, the last run
+    // gives the time of one iteration
+    let start = Instant::now();
+    let mut iterations = 1;
+    let mut per_iteration;
+    loop {
+        let (elapsed, _) = run_n(benchmark, iterations);
+        per_iteration = elapsed.as_nanos() as f64 / iterations as f64;
+        if start.elapsed() >= config.warmup {
+            break;
+        }
+        iterations *= 2;
+    }
+
+    let iterations = ((config.sample_time.as_nanos() as f64 / per_iteration.max(1.0)) as u64).max(1);
+    let mut bytes = 0;
+    let samples: Vec<f64> = (0..config.samples.max(2))
+        .map(|_| {
+            let (elapsed, b) = run_n(benchmark, iterations);
+            bytes = b;
+            elapsed.as_nanos() as f64 / iterations as f64
+        })
+        .collect();
+
+    (Summary::new(&samples), bytes)
+}
+
+/// Pins the current thread to `cpu`, so that samples are not taken on
+/// different cores.
+#[cfg(target_os = "linux")]
+pub fn pin_to_cpu(cpu: usize) -> Result<(), String> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(format!("no CPU {}", cpu));
+    }
+    // SAFETY: `set` is a plain bitmask, initialized by CPU_ZERO before use
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_cpu(_cpu: usize) -> Result<(), String> {
+    Err("CPU pinning is only supported on Linux".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outliers_and_interval() {
+        let mut samples: Vec<f64> = (0..20).map(|i| 100.0 + (i % 5) as f64).collect();
+        samples.push(1000.0);
+        samples.push(10.0);
+
+        let s = Summary::new(&samples);
+        assert_eq!(s.outliers, 2);
+        assert_eq!(s.samples, 20);
+        assert_eq!((s.min, s.max, s.median, s.mean), (100.0, 104.0, 102.0, 102.0));
+        // sd of 0..4 repeated 4 times is sqrt(40 / 19)
+        let margin = t_975(19) * (40.0f64 / 19.0).sqrt() / 20.0f64.sqrt();
+        assert!((s.high - s.mean - margin).abs() < 1e-9);
+        assert!((s.mean - s.low - margin).abs() < 1e-9);
+
+        assert!((t_975(19) - 2.093).abs() < 1e-3);
+        assert!((t_975(1000) - 1.962).abs() < 1e-3);
+    }
+
+    #[test]
+    fn runs_the_samples() {
+        fn benchmark(b: &mut Bencher) {
+            b.bytes = 3;
+            b.iter(|| black_box(1u64) + 1);
+        }
+
+        let config = Config {
+            warmup: Duration::from_millis(1),
+            samples: 5,
+            sample_time: Duration::from_micros(100),
+        };
+        let (s, bytes) = run(&config, benchmark);
+        assert_eq!(bytes, 3);
+        assert_eq!(s.samples + s.outliers, 5);
+        assert!(s.low <= s.mean && s.mean <= s.high);
+    }
+
+    #[test]
+    fn skips() {
+        fn runs(b: &mut Bencher) {
+            b.iter(|| black_box(1u64) + 1);
+        }
+        fn skips(b: &mut Bencher) {
+            b.skip("not supported");
+        }
+
+        assert_eq!(skipped(runs), None);
+        assert_eq!(skipped(skips).as_deref(), Some("not supported"));
+    }
+}
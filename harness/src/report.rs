@@ -0,0 +1,336 @@
+//! Renders recorded results as the tables found in the suites' READMEs, and
+//! as a standalone HTML page with bar charts.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::results::{Measurement, Results};
+use crate::runner;
+
+/// Column order of the README tables, unknown fixtures come last.
+const FIXTURES: &[(&str, &str)] = &[
+    ("one_test", "one_test"),
+    ("small_test", "small_test"),
+    ("bigger_test", "bigger_test"),
+    ("httparse_example_test", "httparse_example_test"),
+    ("responses_test", "responses_test"),
+    ("post_test", "post_test"),
+    ("basic", "basic"),
+    ("canada", "canada.json"),
+    ("apache", "apache_builds.json"),
+    ("data", "data.json"),
+    ("small_headers_test", "small.mp4 headers"),
+    ("small_index_test", "small.mp4 index"),
+    ("small_reader_index_test", "small.mp4 index, through Reader"),
+    ("small_write_test", "small.mp4 write"),
+    ("fragmented_test", "fragmented.mp4"),
+    ("fragmented_index_test", "fragmented.mp4 index"),
+    ("generated_64k_test", "generated 64 kB"),
+    ("generated_64k_index_test", "generated 64 kB index"),
+    ("generated_1m_test", "generated 1 MB"),
+    ("generated_1m_index_test", "generated 1 MB index"),
+    ("generated_16m_test", "generated 16 MB"),
+    ("generated_16m_index_test", "generated 16 MB index"),
+    ("fragmented_1m_test", "fragmented 1 MB"),
+    ("fragmented_1m_index_test", "fragmented 1 MB index"),
+    ("fragmented_16m_test", "fragmented 16 MB"),
+    ("fragmented_16m_index_test", "fragmented 16 MB index"),
+];
+
+/// Results of one suite, arranged as a table.
+pub struct Table<'a> {
+    pub suite: &'a str,
+    /// Fixtures, in README order, with their column title.
+    pub columns: Vec<(&'a str, &'a str)>,
+    /// Parsers sorted from slowest to fastest, with one cell per column.
+    pub rows: Vec<(&'a str, Vec<Option<&'a Measurement>>)>,
+}
+
+fn title(fixture: &str) -> &str {
+    FIXTURES.iter().find(|(f, _)| *f == fixture).map_or(fixture, |(_, t)| t)
+}
+
+/// A measurement as shown in the tables. The `+/-` is the half width of the
+/// 95% confidence interval of the mean when the measurement has one, as the
+/// spread of the samples mostly shows the noise of the machine.
+fn cell(m: &Measurement) -> String {
+    match m.statistics {
+        Some(s) => runner::format(&Measurement { deviation: (s.high - s.low) / 2.0, ..m.clone() }),
+        None => runner::format(m),
+    }
+}
+
+fn rank(fixture: &str) -> usize {
+    FIXTURES.iter().position(|(f, _)| *f == fixture).unwrap_or(FIXTURES.len())
+}
+
+/// Names of the suites present in `results`.
+pub fn suites(results: &Results) -> Vec<&str> {
+    let mut suites: Vec<_> = results.benchmarks.iter().map(|m| m.suite.as_str()).collect();
+    suites.dedup();
+    suites
+}
+
+impl<'a> Table<'a> {
+    pub fn new(results: &'a Results, suite: &'a str) -> Table<'a> {
+        let measurements: Vec<_> = results.benchmarks.iter().filter(|m| m.suite == suite).collect();
+
+        let mut fixtures: Vec<&str> = measurements.iter().map(|m| m.fixture.as_str()).collect();
+        fixtures.sort_by_key(|f| (rank(f), *f));
+        fixtures.dedup();
+
+        let mut by_parser: BTreeMap<&str, Vec<Option<&Measurement>>> = BTreeMap::new();
+        for m in &measurements {
+            let row = by_parser.entry(&m.parser).or_insert_with(|| vec![None; fixtures.len()]);
+            let column = fixtures.iter().position(|f| *f == m.fixture).unwrap();
+            row[column] = Some(*m);
+        }
+
+        // parsers are compared by their average slowdown relative to the
+        // fastest parser of each column, so that missing cells and fixtures
+        // of very different sizes do not skew the order
+        let fastest: Vec<f64> = (0..fixtures.len())
+            .map(|i| {
+                by_parser
+                    .values()
+                    .filter_map(|row| row[i].map(|m| m.ns_per_iter))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        let slowdown = |row: &[Option<&Measurement>]| {
+            let ratios: Vec<f64> = row
+                .iter()
+                .zip(&fastest)
+                .filter_map(|(m, fastest)| m.map(|m| (m.ns_per_iter / fastest).ln()))
+                .collect();
+            (ratios.iter().sum::<f64>() / ratios.len() as f64).exp()
+        };
+
+        let mut rows: Vec<_> = by_parser.into_iter().collect();
+        rows.sort_by(|a, b| slowdown(&b.1).total_cmp(&slowdown(&a.1)));
+
+        Table {
+            suite,
+            columns: fixtures.into_iter().map(|f| (f, title(f))).collect(),
+            rows,
+        }
+    }
+
+    fn is_fastest(&self, column: usize, m: &Measurement) -> bool {
+        self.rows.len() > 1
+            && self
+                .rows
+                .iter()
+                .filter_map(|(_, row)| row[column])
+                .all(|other| other.ns_per_iter >= m.ns_per_iter)
+    }
+
+    /// Markdown table in the format of http/README.md, fastest result of
+    /// each column in bold. A suite measuring a single parser, like mp4, has
+    /// one line per fixture instead.
+    pub fn markdown(&self) -> String {
+        let mut s = String::new();
+
+        if let [(parser, row)] = &self.rows[..] {
+            let width = self.columns.iter().map(|(_, title)| title.len()).max().unwrap_or(0);
+            writeln!(s, "| {:width$} | {} |", "", parser, width = width).unwrap();
+            writeln!(s, "| {} | {} |", "-".repeat(width), "-".repeat(parser.len())).unwrap();
+            for ((_, title), m) in self.columns.iter().zip(row) {
+                let cell = m.map_or(String::new(), cell);
+                writeln!(s, "| {:width$} | {} |", title, cell.trim_start(), width = width).unwrap();
+            }
+            return s;
+        }
+
+        let width = self.rows.iter().map(|(p, _)| p.len()).max().unwrap_or(0).max(10);
+        write!(s, "| {:width$} |", "", width = width).unwrap();
+        for (_, title) in &self.columns {
+            write!(s, " {} |", title).unwrap();
+        }
+        write!(s, "\n| {} |", "-".repeat(width)).unwrap();
+        for (_, title) in &self.columns {
+            write!(s, " {} |", "-".repeat(title.len())).unwrap();
+        }
+        s.push('\n');
+
+        for (parser, row) in &self.rows {
+            write!(s, "| {:width$} |", parser, width = width).unwrap();
+            for (i, m) in row.iter().enumerate() {
+                match m {
+                    None => s.push_str("  |"),
+                    Some(m) => {
+                        let cell = cell(m);
+                        if self.is_fastest(i, m) {
+                            write!(s, " **{}** |", cell.trim_start()).unwrap();
+                        } else {
+                            write!(s, " {} |", cell.trim_start()).unwrap();
+                        }
+                    }
+                }
+            }
+            s.push('\n');
+        }
+
+        s
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Standalone HTML page with the tables, and one bar chart per fixture
+/// showing the throughput of each parser, or its time per iteration when
+/// the benchmark does not set `b.bytes`.
+pub fn html(results: &Results) -> String {
+    let mut s = String::new();
+    s.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Parser benchmarks</title>\n<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+td, th { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: right; }
+td:first-child { text-align: left; }
+.chart { margin-bottom: 1.5em; }
+.bar { display: flex; align-items: center; margin: 2px 0; }
+.bar .name { width: 14em; }
+.bar .fill { background: #4a7ebb; height: 1.2em; margin-right: 0.5em; }
+.bar .fastest { background: #3a9a4a; }
+</style>\n</head>\n<body>\n<h1>Parser benchmarks</h1>\n",
+    );
+
+    let env = &results.environment;
+    writeln!(
+        s,
+        "<p>{}, revision {}, CPU features: {}</p>",
+        escape(&env.rustc),
+        escape(env.revision.as_deref().unwrap_or("unknown")),
+        escape(&env.cpu_features.join(", "))
+    )
+    .unwrap();
+
+    for suite in suites(results) {
+        let table = Table::new(results, suite);
+        writeln!(s, "<h2>{}</h2>\n<table>\n<tr><th></th>", escape(suite)).unwrap();
+        for (_, title) in &table.columns {
+            write!(s, "<th>{}</th>", escape(title)).unwrap();
+        }
+        s.push_str("</tr>\n");
+        for (parser, row) in &table.rows {
+            write!(s, "<tr><td>{}</td>", escape(parser)).unwrap();
+            for (i, m) in row.iter().enumerate() {
+                match m {
+                    None => s.push_str("<td></td>"),
+                    Some(m) if table.is_fastest(i, m) => {
+                        write!(s, "<td><b>{}</b></td>", escape(cell(m).trim_start())).unwrap()
+                    }
+                    Some(m) => write!(s, "<td>{}</td>", escape(cell(m).trim_start())).unwrap(),
+                }
+            }
+            s.push_str("</tr>\n");
+        }
+        s.push_str("</table>\n");
+
+        for (i, (_, title)) in table.columns.iter().enumerate() {
+            let cells: Vec<_> = table.rows.iter().filter_map(|(p, row)| row[i].map(|m| (p, m))).collect();
+            let throughput = cells.iter().all(|(_, m)| m.bytes != 0);
+            let value = |m: &Measurement| if throughput { m.mb_per_s } else { m.ns_per_iter };
+            let max = cells.iter().map(|(_, m)| value(m)).fold(0.0, f64::max);
+
+            writeln!(
+                s,
+                "<div class=\"chart\"><h3>{} ({})</h3>",
+                escape(title),
+                if throughput { "MB/s, longer is better" } else { "ns/iter, shorter is better" }
+            )
+            .unwrap();
+            for (parser, m) in cells {
+                writeln!(
+                    s,
+                    "<div class=\"bar\"><span class=\"name\">{}</span><span class=\"fill{}\" style=\"width: {:.1}%\"></span>{:.0}</div>",
+                    escape(parser),
+                    if table.is_fastest(i, m) { " fastest" } else { "" },
+                    value(m) / max * 60.0,
+                    value(m)
+                )
+                .unwrap();
+            }
+            s.push_str("</div>\n");
+        }
+    }
+
+    s.push_str("</body>\n</html>\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{Environment, Statistics};
+
+    fn measurement(parser: &str, fixture: &str, ns_per_iter: f64) -> Measurement {
+        Measurement {
+            suite: "http".to_string(),
+            parser: parser.to_string(),
+            fixture: fixture.to_string(),
+            bytes: 291,
+            ns_per_iter,
+            deviation: 10.0,
+            mb_per_s: 291.0 * 1000.0 / ns_per_iter,
+            details: BTreeMap::new(),
+            allocations: None,
+            statistics: None,
+        }
+    }
+
+    #[test]
+    fn slowest_to_fastest() {
+        let mut results = Results::default();
+        results.merge(
+            Environment::default(),
+            vec![
+                measurement("httparse", "one_test", 213.0),
+                measurement("httparse", "bigger_test", 78121.0),
+                measurement("nom", "bigger_test", 333344.0),
+                measurement("nom", "one_test", 889.0),
+                measurement("nom (optimized)", "one_test", 183.0),
+            ],
+        )
+        .unwrap();
+
+        let table = Table::new(&results, "http");
+        assert_eq!(table.columns, vec![("one_test", "one_test"), ("bigger_test", "bigger_test")]);
+        let parsers: Vec<_> = table.rows.iter().map(|(p, _)| *p).collect();
+        assert_eq!(parsers, vec!["nom", "httparse", "nom (optimized)"]);
+
+        let markdown = table.markdown();
+        assert!(markdown.contains(
+            "| nom (optimized) | **183 ns/iter (+/- 10) = 1590 MB/s** |  |"
+        ));
+        assert!(html(&results).contains("<h2>http</h2>"));
+    }
+
+    #[test]
+    fn single_parser() {
+        let mut results = Results::default();
+        results
+            .merge(Environment::default(), vec![measurement("nom", "one_test", 200.0), measurement("nom", "small_test", 1000.0)])
+            .unwrap();
+
+        let markdown = Table::new(&results, "http").markdown();
+        assert_eq!(
+            markdown,
+            "|            | nom |\n\
+             | ---------- | --- |\n\
+             | one_test   | 200 ns/iter (+/- 10) = 1455 MB/s |\n\
+             | small_test | 1,000 ns/iter (+/- 10) = 291 MB/s |\n"
+        );
+    }
+
+    #[test]
+    fn confidence_interval() {
+        let mut m = measurement("nom", "one_test", 200.0);
+        assert_eq!(cell(&m).trim_start(), "200 ns/iter (+/- 10) = 1455 MB/s");
+        m.statistics = Some(Statistics { mean: 201.0, std_dev: 4.0, low: 198.0, high: 204.0, samples: 100, outliers: 3 });
+        assert_eq!(cell(&m).trim_start(), "200 ns/iter (+/- 3) = 1455 MB/s");
+    }
+}
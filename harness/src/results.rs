@@ -0,0 +1,337 @@
+//! Machine readable benchmark results.
+//!
+//! The suites write their measurements to a JSON file when given
+//! `--json <path>`, and the `compare` binary reads two such files to find
+//! regressions.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Results of one or several suites, run on the same machine.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Results {
+    pub environment: Environment,
+    pub benchmarks: Vec<Measurement>,
+}
+
+/// Where the results come from, to tell apart runs made with different
+/// compilers, CPUs or revisions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    /// Output of `git rev-parse HEAD`, with `-dirty` appended when the tree
+    /// has local changes.
+    pub revision: Option<String>,
+    /// Version of the compiler that built the suite.
+    pub rustc: String,
+    /// CPU features detected at runtime.
+    pub cpu_features: Vec<String>,
+    /// CPU features enabled at compile time, through `-C target-feature` or
+    /// `-C target-cpu`.
+    pub target_features: Vec<String>,
+}
+
+/// One benchmark, as measured by [`crate::measure`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// `http`, `json` or `mp4`.
+    pub suite: String,
+    /// `ParserUnderTest::NAME`.
+    pub parser: String,
+    /// Name of the benchmark function, usually named after the fixture.
+    pub fixture: String,
+    /// Bytes parsed by each iteration.
+    pub bytes: u64,
+    /// Median time of one iteration.
+    pub ns_per_iter: f64,
+    /// Spread of the samples (max - min), outliers left out.
+    pub deviation: f64,
+    /// Throughput in MB/s, 0 when `bytes` is not set.
+    pub mb_per_s: f64,
+    /// Runtime choices of the parser, such as its SIMD code path.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+    /// Heap usage of one iteration, when the suite counts allocations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<Allocations>,
+    /// Distribution of the samples, missing from results recorded with
+    /// bencher.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statistics: Option<Statistics>,
+}
+
+/// Distribution of the time of one iteration, see [`crate::measure`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub mean: f64,
+    pub std_dev: f64,
+    /// 95% confidence interval of the mean.
+    pub low: f64,
+    pub high: f64,
+    /// Samples kept.
+    pub samples: usize,
+    /// Samples rejected as outliers.
+    pub outliers: usize,
+}
+
+/// Heap usage of one iteration of a benchmark, see [`crate::alloc`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Allocations {
+    /// Number of allocations and reallocations.
+    pub count: u64,
+    /// Bytes requested by those allocations.
+    pub bytes: u64,
+    /// Most bytes allocated at once during the iteration, on top of what was
+    /// allocated before it.
+    pub peak_bytes: u64,
+}
+
+impl Measurement {
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.suite, &self.parser, &self.fixture)
+    }
+}
+
+// `is_x86_feature_detected!` only takes literals
+macro_rules! detected {
+    ($($feature:tt),*) => {{
+        let mut features = Vec::new();
+        $(
+            if is_x86_feature_detected!($feature) {
+                features.push($feature.to_string());
+            }
+        )*
+        features
+    }};
+}
+
+macro_rules! enabled {
+    ($($feature:tt),*) => {{
+        let mut features = Vec::new();
+        $(
+            if cfg!(target_feature = $feature) {
+                features.push($feature.to_string());
+            }
+        )*
+        features
+    }};
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn cpu_features() -> Vec<String> {
+    detected!("sse2", "sse3", "ssse3", "sse4.1", "sse4.2", "popcnt", "avx", "avx2", "bmi1", "bmi2", "avx512f", "avx512bw")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn cpu_features() -> Vec<String> {
+    Vec::new()
+}
+
+fn target_features() -> Vec<String> {
+    enabled!("sse2", "sse3", "ssse3", "sse4.1", "sse4.2", "popcnt", "avx", "avx2", "bmi1", "bmi2", "avx512f", "avx512bw", "neon")
+}
+
+fn revision() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut revision = String::from_utf8(output.stdout).ok()?.trim().to_string();
+
+    let dirty = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .map(|o| !o.stdout.is_empty())
+        .unwrap_or(false);
+    if dirty {
+        revision.push_str("-dirty");
+    }
+
+    Some(revision)
+}
+
+impl Environment {
+    pub fn current() -> Environment {
+        Environment {
+            revision: revision(),
+            rustc: env!("HARNESS_RUSTC_VERSION").to_string(),
+            cpu_features: cpu_features(),
+            target_features: target_features(),
+        }
+    }
+}
+
+impl Results {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Results> {
+        let data = fs::read(path)?;
+        serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let data = serde_json::to_vec_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, data)
+    }
+
+    /// Adds `measurements`, replacing earlier measurements of the same
+    /// benchmark, so that all the suites can be recorded in one file. Fails
+    /// when measurements made in another environment would be kept, as the
+    /// file has a single environment.
+    pub fn merge(&mut self, environment: Environment, measurements: Vec<Measurement>) -> Result<(), Error> {
+        let replaced = |m: &Measurement| measurements.iter().any(|n| n.key() == m.key());
+        if environment != self.environment && !self.benchmarks.iter().all(replaced) {
+            return Err(Error(format!(
+                "results recorded at revision {}, with {}, cannot be merged with results of another environment",
+                self.environment.revision.as_deref().unwrap_or("unknown"),
+                self.environment.rustc
+            )));
+        }
+        self.environment = environment;
+        self.benchmarks.retain(|m| !replaced(m));
+        self.benchmarks.extend(measurements);
+        self.benchmarks.sort_by(|a, b| a.key().cmp(&b.key()));
+        Ok(())
+    }
+}
+
+/// How one benchmark evolved between two result files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    pub old: &'a Measurement,
+    pub new: &'a Measurement,
+    /// Relative change of `ns_per_iter`, in percent. Positive is slower.
+    pub percent: f64,
+}
+
+impl<'a> Change<'a> {
+    /// Whether the confidence intervals of both measurements are disjoint.
+    /// Always true when one of them has no statistics.
+    pub fn is_significant(&self) -> bool {
+        match (self.old.statistics, self.new.statistics) {
+            (Some(old), Some(new)) => old.high < new.low || new.high < old.low,
+            _ => true,
+        }
+    }
+
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        self.percent > threshold && self.is_significant()
+    }
+
+    pub fn is_improvement(&self, threshold: f64) -> bool {
+        self.percent < -threshold && self.is_significant()
+    }
+}
+
+/// Pairs the benchmarks present in both `old` and `new`.
+///
+/// Also returns the benchmarks only present in `old`, and those only
+/// present in `new`.
+pub fn compare<'a>(
+    old: &'a Results,
+    new: &'a Results,
+) -> (Vec<Change<'a>>, Vec<&'a Measurement>, Vec<&'a Measurement>) {
+    let old_by_key: BTreeMap<_, _> = old.benchmarks.iter().map(|m| (m.key(), m)).collect();
+    let new_by_key: BTreeMap<_, _> = new.benchmarks.iter().map(|m| (m.key(), m)).collect();
+
+    let changes = new_by_key
+        .iter()
+        .filter_map(|(key, new)| {
+            old_by_key.get(key).map(|old| Change {
+                old,
+                new,
+                percent: (new.ns_per_iter - old.ns_per_iter) / old.ns_per_iter * 100.0,
+            })
+        })
+        .collect();
+    let removed = old_by_key.iter().filter(|(key, _)| !new_by_key.contains_key(*key)).map(|(_, m)| *m).collect();
+    let added = new_by_key.iter().filter(|(key, _)| !old_by_key.contains_key(*key)).map(|(_, m)| *m).collect();
+
+    (changes, removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(parser: &str, fixture: &str, ns_per_iter: f64) -> Measurement {
+        Measurement {
+            suite: "http".to_string(),
+            parser: parser.to_string(),
+            fixture: fixture.to_string(),
+            bytes: 1000,
+            ns_per_iter,
+            deviation: 0.0,
+            mb_per_s: 1000.0 * 1000.0 / ns_per_iter,
+            details: BTreeMap::new(),
+            allocations: None,
+            statistics: None,
+        }
+    }
+
+    #[test]
+    fn merge_and_compare() {
+        let mut old = Results::default();
+        old.merge(Environment::default(), vec![measurement("nom", "one_test", 100.0), measurement("nom", "small_test", 1000.0)]).unwrap();
+        old.merge(Environment::default(), vec![measurement("nom", "one_test", 200.0)]).unwrap();
+        assert_eq!(old.benchmarks.len(), 2);
+
+        let mut new = Results::default();
+        new.merge(Environment::default(), vec![measurement("nom", "one_test", 220.0), measurement("nom", "bigger_test", 1.0)]).unwrap();
+
+        let data = serde_json::to_string(&new).unwrap();
+        assert_eq!(serde_json::from_str::<Results>(&data).unwrap(), new);
+
+        let (changes, removed, added) = compare(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert!((changes[0].percent - 10.0).abs() < 1e-9);
+        assert!(changes[0].is_regression(5.0));
+        assert!(!changes[0].is_regression(15.0));
+        assert_eq!(removed[0].fixture, "small_test");
+        assert_eq!(added[0].fixture, "bigger_test");
+    }
+
+    #[test]
+    fn merge_environments() {
+        let other = Environment { rustc: "rustc 1.0.0".to_string(), ..Environment::default() };
+        let mut results = Results::default();
+        results.merge(Environment::default(), vec![measurement("nom", "one_test", 100.0)]).unwrap();
+
+        // keeping the nom measurement would mix two environments
+        assert!(results.merge(other.clone(), vec![measurement("pest", "one_test", 100.0)]).is_err());
+        assert_eq!(results.benchmarks.len(), 1);
+
+        // replacing every measurement does not
+        results.merge(other.clone(), vec![measurement("nom", "one_test", 120.0)]).unwrap();
+        assert_eq!(results.environment, other);
+        assert_eq!(results.benchmarks[0].ns_per_iter, 120.0);
+    }
+
+    #[test]
+    fn overlapping_intervals() {
+        let statistics = |low, high| Statistics {
+            mean: (low + high) / 2.0,
+            std_dev: 1.0,
+            low,
+            high,
+            samples: 100,
+            outliers: 0,
+        };
+        let mut old = measurement("nom", "one_test", 200.0);
+        let mut new = measurement("nom", "one_test", 220.0);
+        old.statistics = Some(statistics(190.0, 215.0));
+        new.statistics = Some(statistics(210.0, 230.0));
+
+        let change = Change { old: &old, new: &new, percent: 10.0 };
+        assert!(!change.is_significant());
+        assert!(!change.is_regression(5.0));
+
+        new.statistics = Some(statistics(216.0, 230.0));
+        let change = Change { old: &old, new: &new, percent: 10.0 };
+        assert!(change.is_regression(5.0));
+    }
+}
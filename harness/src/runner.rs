@@ -0,0 +1,269 @@
+//! Replacement for bencher's `benchmark_main!`, which only prints its
+//! results.
+//!
+//! Accepts the same filter argument, and `--json <path>` to also record the
+//! results in `path`. An existing file is updated, so that running every
+//! suite with the same path gathers all the results in one file.
+//!
+//! The measurement, see [`measure`](crate::measure), is tuned with
+//! `--samples <count>` and `--warmup <ms>`, and `--pin <cpu>` runs the
+//! benchmarks on a single CPU.
+
+use std::env;
+use std::process;
+use std::time::Duration;
+
+use crate::alloc;
+use crate::measure::{self, Bencher, Config};
+use crate::results::{Allocations, Environment, Measurement, Results, Statistics};
+
+/// A benchmark function and the name it is reported under.
+pub struct Benchmark {
+    pub name: &'static str,
+    pub run: fn(&mut Bencher),
+}
+
+struct Options {
+    filter: Option<String>,
+    json: Option<String>,
+    config: Config,
+    pin: Option<usize>,
+}
+
+fn number<T: std::str::FromStr>(option: &str, value: Option<String>) -> T {
+    match value.and_then(|v| v.parse().ok()) {
+        Some(n) => n,
+        None => {
+            eprintln!("{} needs a number", option);
+            process::exit(2);
+        }
+    }
+}
+
+fn options() -> Options {
+    let mut options = Options {
+        filter: None,
+        json: None,
+        config: Config::default(),
+        pin: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // passed by `cargo bench`
+            "--bench" => {}
+            "--json" => match args.next() {
+                Some(path) => options.json = Some(path),
+                None => {
+                    eprintln!("--json needs a path");
+                    process::exit(2);
+                }
+            },
+            "--samples" => {
+                options.config.samples = number(&arg, args.next());
+                if options.config.samples < 2 {
+                    eprintln!("--samples needs at least 2 samples");
+                    process::exit(2);
+                }
+            }
+            "--warmup" => options.config.warmup = Duration::from_millis(number(&arg, args.next())),
+            "--pin" => options.pin = Some(number(&arg, args.next())),
+            _ => options.filter = Some(arg),
+        }
+    }
+
+    options
+}
+
+/// Iterations run by `allocations`, on top of a single one.
+const ALLOCATION_ITERATIONS: u64 = 10;
+
+/// Heap usage of one iteration of `benchmark`.
+///
+/// The benchmark is run with one iteration, then with
+/// `ALLOCATION_ITERATIONS + 1`, and the counts are taken from the difference
+/// so that its setup is left out. The peak does include the setup, which
+/// stays allocated while iterating. A first run initializes lazy state.
+fn allocations(benchmark: &Benchmark) -> Allocations {
+    let run = |n| measure::run_n(benchmark.run, n);
+
+    run(1);
+    let start = alloc::stats();
+    alloc::reset_peak();
+    run(1);
+    let one = alloc::stats();
+    run(ALLOCATION_ITERATIONS + 1);
+    let many = alloc::stats();
+
+    let per_iteration = |start: u64, one: u64, many: u64| {
+        ((many - one).saturating_sub(one - start) as f64 / ALLOCATION_ITERATIONS as f64).round() as u64
+    };
+    Allocations {
+        count: per_iteration(start.allocations, one.allocations, many.allocations),
+        bytes: per_iteration(start.bytes, one.bytes, many.bytes),
+        peak_bytes: one.peak.saturating_sub(start.live) as u64,
+    }
+}
+
+/// Measures one benchmark, and counts its allocations when the binary uses
+/// [`CountingAllocator`](crate::alloc::CountingAllocator).
+pub fn measure(
+    config: &Config,
+    suite: &str,
+    parser: &str,
+    details: &[(&str, String)],
+    benchmark: &Benchmark,
+) -> Measurement {
+    let (summary, bytes) = measure::run(config, benchmark.run);
+
+    let ns_per_iter = summary.median.max(1.0);
+    Measurement {
+        suite: suite.to_string(),
+        parser: parser.to_string(),
+        fixture: benchmark.name.to_string(),
+        bytes,
+        ns_per_iter,
+        deviation: summary.max - summary.min,
+        mb_per_s: bytes as f64 * 1000.0 / ns_per_iter,
+        details: details.iter().map(|(k, v)| (k.to_string(), v.clone())).collect(),
+        allocations: if alloc::is_installed() { Some(allocations(benchmark)) } else { None },
+        statistics: Some(Statistics {
+            mean: summary.mean,
+            std_dev: summary.std_dev,
+            low: summary.low,
+            high: summary.high,
+            samples: summary.samples,
+            outliers: summary.outliers,
+        }),
+    }
+}
+
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut s = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            s.push(',');
+        }
+        s.push(c);
+    }
+    s
+}
+
+/// Formats a measurement like bencher's console output.
+pub fn format(m: &Measurement) -> String {
+    let mut s = format!(
+        "{:>11} ns/iter (+/- {})",
+        thousands(m.ns_per_iter as u64),
+        thousands(m.deviation as u64)
+    );
+    if m.bytes != 0 {
+        s.push_str(&format!(" = {} MB/s", m.mb_per_s as u64));
+    }
+    s
+}
+
+/// Formats the allocations of a measurement, if they were counted.
+pub fn format_allocations(m: &Measurement) -> Option<String> {
+    m.allocations.map(|a| {
+        format!(
+            "{} allocs, {} bytes, peak {} bytes",
+            thousands(a.count),
+            thousands(a.bytes),
+            thousands(a.peak_bytes)
+        )
+    })
+}
+
+/// Formats the confidence interval of a measurement, if it has one.
+pub fn format_statistics(m: &Measurement) -> Option<String> {
+    m.statistics.map(|s| {
+        format!(
+            "95% CI {}..{} ns, {} outliers",
+            thousands(s.low.max(0.0) as u64),
+            thousands(s.high as u64),
+            s.outliers
+        )
+    })
+}
+
+/// Runs `benchmarks` of `parser`, prints the results and records them if
+/// asked to. `details` are the parser's runtime choices, see
+/// [`ParserUnderTest::details`](crate::ParserUnderTest::details).
+pub fn main(suite: &str, parser: &str, details: &[(&str, String)], benchmarks: &[Benchmark]) {
+    let options = options();
+
+    let benchmarks: Vec<_> = benchmarks
+        .iter()
+        .filter(|b| options.filter.as_ref().is_none_or(|f| b.name.contains(f.as_str())))
+        .collect();
+    let width = benchmarks.iter().map(|b| b.name.len()).max().unwrap_or(0);
+
+    if let Some(cpu) = options.pin {
+        match measure::pin_to_cpu(cpu) {
+            Ok(()) => println!("{}: pinned to CPU {}", parser, cpu),
+            Err(e) => {
+                eprintln!("could not pin to CPU {}: {}", cpu, e);
+                process::exit(1);
+            }
+        }
+    }
+    for (name, value) in details {
+        println!("{}: {} = {}", parser, name, value);
+    }
+    println!("\nrunning {} tests", benchmarks.len());
+    let mut measurements = Vec::new();
+    let mut ignored = 0;
+    for b in benchmarks {
+        if let Some(reason) = measure::skipped(b.run) {
+            println!("test {:<width$} ... skipped: {}", b.name, reason, width = width);
+            ignored += 1;
+            continue;
+        }
+        let m = measure(&options.config, suite, parser, details, b);
+        let notes: Vec<_> = format_statistics(&m).into_iter().chain(format_allocations(&m)).collect();
+        if notes.is_empty() {
+            println!("test {:<width$} ... bench: {}", b.name, format(&m), width = width);
+        } else {
+            println!("test {:<width$} ... bench: {} ({})", b.name, format(&m), notes.join("; "), width = width);
+        }
+        measurements.push(m);
+    }
+    println!(
+        "\ntest result: ok. 0 passed; 0 failed; {} ignored; {} measured\n",
+        ignored,
+        measurements.len()
+    );
+
+    if let Some(path) = options.json {
+        let mut results = match Results::load(&path) {
+            Ok(results) => results,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Results::default(),
+            Err(e) => {
+                eprintln!("could not read {}: {}", path, e);
+                process::exit(1);
+            }
+        };
+        if let Err(e) = results.merge(Environment::current(), measurements) {
+            eprintln!("could not record the results in {}: {}", path, e);
+            process::exit(1);
+        }
+        if let Err(e) = results.save(&path) {
+            eprintln!("could not write {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn thousands() {
+        assert_eq!(super::thousands(0), "0");
+        assert_eq!(super::thousands(999), "999");
+        assert_eq!(super::thousands(1000), "1,000");
+        assert_eq!(super::thousands(61519), "61,519");
+        assert_eq!(super::thousands(127775522), "127,775,522");
+    }
+}
@@ -0,0 +1,227 @@
+extern crate harness;
+#[macro_use]
+extern crate combine;
+
+use harness::ParserUnderTest;
+
+use combine::parser::combinator::no_partial;
+use combine::range::{range, take_while, take_while1};
+use combine::stream::FullRangeStream;
+use combine::parser::byte::digit;
+use combine::{many, one_of, token, ParseError, Parser, RangeStream};
+
+#[derive(Debug)]
+pub struct Request<'a> {
+    pub method: &'a [u8],
+    pub uri: &'a [u8],
+    pub version: u8,
+}
+
+#[derive(Debug)]
+pub struct Response<'a> {
+    pub version: u8,
+    pub code: u16,
+    pub reason: &'a [u8],
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Header<'a> {
+    pub name: &'a [u8],
+    pub value: &'a [u8],
+}
+
+fn is_token(c: u8) -> bool {
+    match c {
+        128..=255
+        | 0..=31
+        | b'('
+        | b')'
+        | b'<'
+        | b'>'
+        | b'@'
+        | b','
+        | b';'
+        | b':'
+        | b'\\'
+        | b'"'
+        | b'/'
+        | b'['
+        | b']'
+        | b'?'
+        | b'='
+        | b'{'
+        | b'}'
+        | b' ' => false,
+        _ => true,
+    }
+}
+
+fn is_header_value_token(c: u8) -> bool {
+    c == b'\t' || (c > 31 && c != 127)
+}
+
+fn is_url_token(c: u8) -> bool {
+    c > 0x20 && c < 0x7F
+}
+
+fn is_horizontal_space(c: u8) -> bool {
+    c == b' ' || c == b'\t'
+}
+
+// spaces and tabs ending a field value are not part of it
+fn trim_end(value: &[u8]) -> &[u8] {
+    &value[..value.iter().rposition(|&c| !is_horizontal_space(c)).map_or(0, |i| i + 1)]
+}
+
+fn end_of_line<'a, I>() -> impl Parser<Output = (), Input = I> + 'a
+where
+    I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    range(b"\r\n" as &[u8])
+        .or(range(b"\n" as &[u8]))
+        .map(|_| ())
+}
+
+fn message_header<'a, I>() -> impl Parser<Output = Header<'a>, Input = I>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let header_value = no_partial((
+        take_while1(is_horizontal_space),
+        take_while1(is_header_value_token),
+        end_of_line(),
+    )).map(|(_, line, _)| trim_end(line));
+
+    no_partial((take_while1(is_token), token(b':'), header_value))
+        .map(|(name, _, value)| Header { name, value })
+}
+
+fn http_version<'a, I>() -> impl Parser<Output = u8, Input = I> + 'a
+where
+    I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    range(&b"HTTP/1."[..]).with(one_of(b"01".iter().cloned()).map(|c| {
+        if c == b'0' {
+            0
+        } else {
+            1
+        }
+    }))
+}
+
+pub fn parse_http_request<'a, I>(input: I) -> Result<((Request<'a>, Vec<Header<'a>>), I), I::Error>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+
+    let request_line = no_partial(struct_parser!(Request {
+            method: take_while1(is_token),
+            _: token(b' '),
+            uri: take_while1(is_url_token),
+            _: token(b' '),
+            version: http_version(),
+        }));
+
+    let mut request = no_partial((
+        request_line,
+        end_of_line(),
+        many(message_header()),
+        end_of_line(),
+    )).map(|(request, _, headers, _)| (request, headers));
+
+    request.parse(input)
+}
+
+pub fn parse_http_response<'a, I>(input: I) -> Result<((Response<'a>, Vec<Header<'a>>), I), I::Error>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let status_code = (digit(), digit(), digit())
+        .map(|(a, b, c)| (a - b'0') as u16 * 100 + (b - b'0') as u16 * 10 + (c - b'0') as u16);
+
+    let status_line = no_partial(struct_parser!(Response {
+            version: http_version(),
+            _: token(b' '),
+            code: status_code,
+            _: token(b' '),
+            reason: take_while(is_header_value_token),
+        }));
+
+    let mut response = no_partial((
+        status_line,
+        end_of_line(),
+        many(message_header()),
+        end_of_line(),
+    )).map(|(response, _, headers, _)| (response, headers));
+
+    response.parse(input)
+}
+
+pub struct CombineHttp;
+
+impl ParserUnderTest for CombineHttp {
+    type Output = harness::http::Request;
+
+    const NAME: &'static str = "combine";
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+        match parse_http_request(input) {
+            Ok((_, i)) => Ok(input.len() - i.len()),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+        let ((request, headers), i) = parse_http_request(input).map_err(harness::Error::new)?;
+
+        Ok((
+            input.len() - i.len(),
+            harness::http::Request {
+                method: request.method.to_vec(),
+                uri: request.uri.to_vec(),
+                version: request.version,
+                headers: headers
+                    .iter()
+                    .map(|h| harness::http::Header::new(h.name, h.value))
+                    .collect(),
+            },
+        ))
+    }
+}
+
+pub struct CombineHttpResponse;
+
+impl ParserUnderTest for CombineHttpResponse {
+    type Output = harness::http::Response;
+
+    const NAME: &'static str = "combine";
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+        match parse_http_response(input) {
+            Ok((_, i)) => Ok(input.len() - i.len()),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+        let ((response, headers), i) = parse_http_response(input).map_err(harness::Error::new)?;
+
+        Ok((
+            input.len() - i.len(),
+            harness::http::Response {
+                version: response.version,
+                code: response.code,
+                reason: response.reason.to_vec(),
+                headers: headers
+                    .iter()
+                    .map(|h| harness::http::Header::new(h.name, h.value))
+                    .collect(),
+            },
+        ))
+    }
+}
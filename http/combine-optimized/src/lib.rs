@@ -0,0 +1,374 @@
+extern crate harness;
+extern crate combinators;
+#[macro_use]
+extern crate combine;
+
+use harness::ParserUnderTest;
+
+use combine::{token, one_of, optional, ParseError, Parser, RangeStream, skip_many};
+use combine::range::{range, take_while1};
+use combine::parser::byte::digit;
+use combine::stream::FullRangeStream;
+use combine::parser::combinator::no_partial;
+
+use combinators::http::{is_header_value_token, is_token, is_url_token, HEADER_VALUE_RANGES, URI_RANGES};
+use combinators::combine_adapter::take_while1_simd;
+
+#[derive(Debug)]
+pub struct Request<'a> {
+    pub method: &'a [u8],
+    pub uri: &'a [u8],
+    pub version: u8,
+}
+
+#[derive(Debug)]
+pub struct Response<'a> {
+    pub version: u8,
+    pub code: u16,
+    pub reason: &'a [u8],
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Header<'a> {
+    pub name: &'a [u8],
+    pub value: &'a [u8],
+}
+
+fn is_horizontal_space(c: u8) -> bool {
+    c == b' ' || c == b'\t'
+}
+
+// spaces and tabs ending a field value are not part of it
+fn trim_end(value: &[u8]) -> &[u8] {
+    &value[..value.iter().rposition(|&c| !is_horizontal_space(c)).map_or(0, |i| i + 1)]
+}
+
+fn end_of_line<'a, I>() -> impl Parser<Output = (), Input = I> + 'a
+where
+    I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    range(b"\r\n" as &[u8]).or(range(b"\n" as &[u8])).map(|_| ())
+}
+
+fn message_header<'a, I>() -> impl Parser<Output = Header<'a>, Input = I>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let header_value = no_partial((
+        take_while1(is_horizontal_space),
+        take_while1_simd(HEADER_VALUE_RANGES, is_header_value_token),
+        end_of_line(),
+    )).map(|(_, line, _)| trim_end(line));
+
+    no_partial((
+        take_while1(is_token),
+        token(b':'),
+        header_value,
+    )).map(|(name, _, value)| {
+        Header { name, value }
+    })
+}
+
+fn http_version<'a, I>() -> impl Parser<Output = u8, Input = I> + 'a
+where
+    I: RangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    range(&b"HTTP/1."[..]).with(one_of(b"01".iter().cloned()).map(|c| if c == b'0' { 0 } else { 1 }))
+}
+
+/// Error of `parse_http_request` and `parse_http_response`.
+#[derive(Debug, PartialEq)]
+pub enum Error<E> {
+    Parse(E),
+    /// The message has more headers than the slice given to store them, as
+    /// httparse's `Error::TooManyHeaders`.
+    TooManyHeaders,
+}
+
+fn request_head<'a, I, F>(input: I, request: &mut Request<'a>, header: F) -> Result<I, I::Error>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+    F: FnMut(Header<'a>),
+{
+
+    let request_line = no_partial(struct_parser!(Request {
+            method: take_while1(is_token),
+            _: token(b' '),
+            uri: take_while1_simd(URI_RANGES, is_url_token),
+            _: token(b' '),
+            version: http_version(),
+        }));
+
+    // Would have used an iterator here but unfortunately it does not optimize as well
+    let mut request = no_partial((
+        request_line,
+        end_of_line(),
+        skip_many(message_header().map(header)),
+        end_of_line(),
+    )).map(|(r, _, _, _)| *request = r);
+
+    request.parse(input).map(|((), rest)| rest)
+}
+
+fn response_head<'a, I, F>(input: I, response: &mut Response<'a>, header: F) -> Result<I, I::Error>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+    F: FnMut(Header<'a>),
+{
+    let status_code = (digit(), digit(), digit())
+        .map(|(a, b, c)| (a - b'0') as u16 * 100 + (b - b'0') as u16 * 10 + (c - b'0') as u16);
+
+    // the reason phrase may be empty
+    let status_line = no_partial(struct_parser!(Response {
+            version: http_version(),
+            _: token(b' '),
+            code: status_code,
+            _: token(b' '),
+            reason: optional(take_while1_simd(HEADER_VALUE_RANGES, is_header_value_token)).map(|r| r.unwrap_or(&[])),
+        }));
+
+    let mut parser = no_partial((
+        status_line,
+        end_of_line(),
+        skip_many(message_header().map(header)),
+        end_of_line(),
+    )).map(|(r, _, _, _)| *response = r);
+
+    parser.parse(input).map(|((), rest)| rest)
+}
+
+/// Parses a request into `request` and `headers`, returning the number of
+/// headers that were filled.
+pub fn parse_http_request<'a, I>(input: I, request: &mut Request<'a>, headers: &mut [Header<'a>]) -> Result<(usize, I), Error<I::Error>>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let mut i = 0;
+    let mut overflow = false;
+    let rest = request_head(input, request, |header| match headers.get_mut(i) {
+        Some(out) => {
+            *out = header;
+            i += 1;
+        }
+        None => overflow = true,
+    }).map_err(Error::Parse)?;
+
+    if overflow {
+        Err(Error::TooManyHeaders)
+    } else {
+        Ok((i, rest))
+    }
+}
+
+/// Parses a request into `request`, appending its headers to `headers`, for
+/// messages with an unknown number of headers.
+pub fn parse_http_request_vec<'a, I>(input: I, request: &mut Request<'a>, headers: &mut Vec<Header<'a>>) -> Result<I, I::Error>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    request_head(input, request, |header| headers.push(header))
+}
+
+/// Parses a response head into `response` and `headers`, returning the
+/// number of headers that were filled.
+pub fn parse_http_response<'a, I>(input: I, response: &mut Response<'a>, headers: &mut [Header<'a>]) -> Result<(usize, I), Error<I::Error>>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    let mut i = 0;
+    let mut overflow = false;
+    let rest = response_head(input, response, |header| match headers.get_mut(i) {
+        Some(out) => {
+            *out = header;
+            i += 1;
+        }
+        None => overflow = true,
+    }).map_err(Error::Parse)?;
+
+    if overflow {
+        Err(Error::TooManyHeaders)
+    } else {
+        Ok((i, rest))
+    }
+}
+
+/// Parses a response head into `response`, appending its headers to
+/// `headers`.
+pub fn parse_http_response_vec<'a, I>(input: I, response: &mut Response<'a>, headers: &mut Vec<Header<'a>>) -> Result<I, I::Error>
+where
+    I: FullRangeStream<Item = u8, Range = &'a [u8]> + 'a,
+    I::Error: ParseError<I::Item, I::Range, I::Position>,
+{
+    response_head(input, response, |header| headers.push(header))
+}
+
+pub struct CombineOptimizedHttp;
+
+impl ParserUnderTest for CombineOptimizedHttp {
+    type Output = harness::http::Request;
+
+    const NAME: &'static str = "combine (optimized)";
+
+    fn details() -> Vec<(&'static str, String)> {
+        vec![("take_while1_simd", combinators::simd_path().name().to_string())]
+    }
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+        let mut request = Request {
+            method: &[],
+            uri: &[],
+            version: 0,
+        };
+        let mut headers = [Header {
+            name: &[],
+            value: &[],
+        }; 16];
+
+        match parse_http_request(input, &mut request, &mut headers) {
+            Ok((_, i)) => Ok(input.len() - i.len()),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+        let mut request = Request {
+            method: &[],
+            uri: &[],
+            version: 0,
+        };
+        let mut headers = [Header {
+            name: &[],
+            value: &[],
+        }; 16];
+
+        let (count, i) = parse_http_request(input, &mut request, &mut headers)
+            .map_err(harness::Error::new)?;
+
+        Ok((
+            input.len() - i.len(),
+            harness::http::Request {
+                method: request.method.to_vec(),
+                uri: request.uri.to_vec(),
+                version: request.version,
+                headers: headers[..count]
+                    .iter()
+                    .map(|h| harness::http::Header::new(h.name, h.value))
+                    .collect(),
+            },
+        ))
+    }
+}
+
+pub struct CombineOptimizedHttpResponse;
+
+impl ParserUnderTest for CombineOptimizedHttpResponse {
+    type Output = harness::http::Response;
+
+    const NAME: &'static str = "combine (optimized)";
+
+    fn details() -> Vec<(&'static str, String)> {
+        vec![("take_while1_simd", combinators::simd_path().name().to_string())]
+    }
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+        let mut response = Response {
+            version: 0,
+            code: 0,
+            reason: &[],
+        };
+        let mut headers = [Header {
+            name: &[],
+            value: &[],
+        }; 16];
+
+        match parse_http_response(input, &mut response, &mut headers) {
+            Ok((_, i)) => Ok(input.len() - i.len()),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+        let mut response = Response {
+            version: 0,
+            code: 0,
+            reason: &[],
+        };
+        let mut headers = [Header {
+            name: &[],
+            value: &[],
+        }; 16];
+
+        let (count, i) = parse_http_response(input, &mut response, &mut headers)
+            .map_err(harness::Error::new)?;
+
+        Ok((
+            input.len() - i.len(),
+            harness::http::Response {
+                version: response.version,
+                code: response.code,
+                reason: response.reason.to_vec(),
+                headers: headers[..count]
+                    .iter()
+                    .map(|h| harness::http::Header::new(h.name, h.value))
+                    .collect(),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+fn many_headers(count: usize) -> Vec<u8> {
+    let mut data = b"GET / HTTP/1.1\r\n".to_vec();
+    for i in 0..count {
+        data.extend_from_slice(format!("X-Header-{}: {}\r\n", i, i).as_bytes());
+    }
+    data.extend_from_slice(b"\r\n");
+    data
+}
+
+#[test]
+fn too_many_headers_test() {
+    let (sixteen, seventeen) = (many_headers(16), many_headers(17));
+    let mut request = Request {
+        method: &[],
+        uri: &[],
+        version: 0,
+    };
+    let mut headers = [Header {
+        name: &[],
+        value: &[],
+    }; 16];
+
+    assert_eq!(
+        parse_http_request(&sixteen[..], &mut request, &mut headers).map(|(count, _)| count),
+        Ok(16)
+    );
+    assert_eq!(
+        parse_http_request(&seventeen[..], &mut request, &mut headers).map(|(count, _)| count),
+        Err(Error::TooManyHeaders)
+    );
+}
+
+#[test]
+fn request_vec_test() {
+    let data = many_headers(40);
+    let mut request = Request {
+        method: &[],
+        uri: &[],
+        version: 0,
+    };
+    let mut headers = Vec::new();
+
+    let rest = parse_http_request_vec(&data[..], &mut request, &mut headers).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(headers.len(), 40);
+    assert_eq!((headers[39].name, headers[39].value), (&b"X-Header-39"[..], &b"39"[..]));
+}
@@ -0,0 +1,146 @@
+//! Differential testing of the HTTP request parsers.
+//!
+//! Every request of a corpus is fed to all the Rust HTTP parsers, and their
+//! results are compared after normalization to `harness::http::Request`, or
+//! `harness::http::Response` for response corpora. httparse is the reference
+//! used to find message boundaries.
+//!
+//! Requests with bodies are only compared between the parsers that handle
+//! body framing, normalized to `harness::http::Message`. httparse parses the
+//! chunks and trailers, but its framing follows the rules of nom-optimized,
+//! so which header delimits the body is not independently verified.
+
+use harness::differential::{self, Contender, Divergence};
+use harness::http::{Message, Request, Response};
+
+pub fn contenders() -> Vec<Contender<Request>> {
+    vec![
+        Contender::new(httparse_test::Httparse),
+        Contender::new(picohttpparser::PicoHttpParser),
+        Contender::new(nom_http::NomHttp),
+        Contender::new(nom_optimized_http::NomOptimizedHttp),
+        Contender::new(combine_http::CombineHttp),
+        Contender::new(combine_optimized_http::CombineOptimizedHttp),
+    ]
+}
+
+pub fn response_contenders() -> Vec<Contender<Response>> {
+    vec![
+        Contender::new(httparse_test::HttparseResponse),
+        Contender::new(nom_http::NomHttpResponse),
+        Contender::new(nom_optimized_http::NomOptimizedHttpResponse),
+        Contender::new(combine_http::CombineHttpResponse),
+        Contender::new(combine_optimized_http::CombineOptimizedHttpResponse),
+    ]
+}
+
+pub fn message_contenders() -> Vec<Contender<Message>> {
+    vec![
+        Contender::new(httparse_test::HttparseMessage),
+        Contender::new(nom_optimized_http::NomOptimizedHttpMessage),
+    ]
+}
+
+/// Returns the requests of `corpus` on which the parsers disagree.
+pub fn check(corpus: &[u8]) -> Vec<Divergence<Request>> {
+    differential::run(corpus, &mut contenders())
+}
+
+/// Returns the responses of `corpus` on which the parsers disagree.
+pub fn check_responses(corpus: &[u8]) -> Vec<Divergence<Response>> {
+    differential::run(corpus, &mut response_contenders())
+}
+
+/// Returns the requests of `corpus`, bodies included, on which the parsers
+/// disagree.
+pub fn check_messages(corpus: &[u8]) -> Vec<Divergence<Message>> {
+    differential::run(corpus, &mut message_contenders())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harness::fixtures::http;
+
+    fn assert_agree(corpus: &[u8]) {
+        let divergences = check(corpus);
+        for d in &divergences {
+            println!("{}", d);
+        }
+        assert!(
+            divergences.is_empty(),
+            "{} divergent requests",
+            divergences.len()
+        );
+    }
+
+    #[test]
+    fn one() {
+        assert_agree(http::ONE);
+    }
+
+    #[test]
+    fn httparse_example() {
+        assert_agree(http::HTTPARSE_EXAMPLE);
+    }
+
+    #[test]
+    fn small() {
+        assert_agree(http::SMALL);
+    }
+
+    #[test]
+    fn bigger() {
+        assert_agree(http::BIGGER);
+    }
+
+    #[test]
+    fn responses() {
+        let divergences = check_responses(http::RESPONSES);
+        for d in &divergences {
+            println!("{}", d);
+        }
+        assert!(divergences.is_empty(), "{} divergent responses", divergences.len());
+    }
+
+    #[test]
+    fn post() {
+        let divergences = check_messages(http::POST);
+        for d in &divergences {
+            println!("{}", d);
+        }
+        assert!(divergences.is_empty(), "{} divergent requests", divergences.len());
+    }
+
+    #[test]
+    fn bodiless_messages() {
+        let divergences = check_messages(http::BIGGER);
+        assert!(divergences.is_empty(), "{} divergent requests", divergences.len());
+    }
+
+    #[test]
+    fn reports_divergence() {
+        // nom-http accepts several spaces between request line elements,
+        // the other parsers do not
+        let divergences = check(b"GET  / HTTP/1.1\r\nHost: a\r\n\r\n");
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].offset, 0);
+    }
+
+    #[test]
+    fn header_trailing_whitespace() {
+        // trailing whitespace is not part of a field value (RFC 7230 section
+        // 3.2), every parser has to drop it
+        assert_agree(b"GET / HTTP/1.1\r\nAccept: */* \t\r\nHost: a \r\n\r\n");
+        let responses = check_responses(b"HTTP/1.1 200 OK\r\nServer: a \r\nVary: b\t\r\n\r\n");
+        for d in &responses {
+            println!("{}", d);
+        }
+        assert!(responses.is_empty(), "{} divergent responses", responses.len());
+        let messages = check_messages(b"POST / HTTP/1.1\r\nContent-Length: 1 \r\n\r\nx");
+        for d in &messages {
+            println!("{}", d);
+        }
+        assert!(messages.is_empty(), "{} divergent requests", messages.len());
+    }
+}
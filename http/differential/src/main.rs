@@ -0,0 +1,66 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::process;
+
+use harness::differential::Divergence;
+use harness::fixtures::http;
+
+/// Prints the divergences found in corpus `name`, made of `kind`, and returns
+/// how many there are.
+fn report<T: fmt::Display>(name: &str, kind: &str, divergences: &[Divergence<T>]) -> usize {
+    println!("{}: {} divergent {}", name, divergences.len(), kind);
+    for d in divergences {
+        println!("{}", d);
+    }
+    divergences.len()
+}
+
+fn main() {
+    // `--bodies` compares request bodies too, with the parsers supporting them
+    let mut bodies = false;
+    let paths: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| {
+            bodies |= arg == "--bodies";
+            arg != "--bodies"
+        })
+        .collect();
+
+    let corpora: Vec<(String, Vec<u8>, bool)> = if !paths.is_empty() {
+        paths
+            .into_iter()
+            .map(|path| {
+                let data =
+                    fs::read(&path).unwrap_or_else(|e| panic!("could not read {}: {}", path, e));
+                (path, data, bodies)
+            })
+            .collect()
+    } else {
+        vec![
+            ("http-requests.txt".to_string(), http::SMALL.to_vec(), false),
+            ("bigger.txt".to_string(), http::BIGGER.to_vec(), false),
+            ("http-responses.txt".to_string(), http::RESPONSES.to_vec(), false),
+            ("http-post-requests.txt".to_string(), http::POST.to_vec(), true),
+        ]
+    };
+
+    let mut failed = false;
+    for (name, corpus, bodies) in &corpora {
+        // a status line starts with the version, a request line never does
+        let count = if corpus.starts_with(b"HTTP/") {
+            report(name, "responses", &http_differential::check_responses(corpus))
+        } else if *bodies {
+            let count = report(name, "requests", &http_differential::check_messages(corpus));
+            println!("{}: note: body framing is not independently verified", name);
+            count
+        } else {
+            report(name, "requests", &http_differential::check(corpus))
+        };
+        failed |= count > 0;
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
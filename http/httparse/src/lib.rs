@@ -0,0 +1,221 @@
+extern crate harness;
+extern crate httparse;
+
+use harness::ParserUnderTest;
+
+pub struct Httparse;
+
+impl ParserUnderTest for Httparse {
+  type Output = harness::http::Request;
+
+  const NAME: &'static str = "httparse";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut req = httparse::Request::new(&mut headers);
+
+    match req.parse(input) {
+      Ok(httparse::Status::Complete(sz)) => Ok(sz),
+      Ok(httparse::Status::Partial)      => Err(harness::Error::new("partial request")),
+      Err(err)                           => Err(harness::Error::new(err)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut req = httparse::Request::new(&mut headers);
+
+    let sz = match req.parse(input) {
+      Ok(httparse::Status::Complete(sz)) => sz,
+      Ok(httparse::Status::Partial)      => return Err(harness::Error::new("partial request")),
+      Err(err)                           => return Err(harness::Error::new(err)),
+    };
+
+    Ok((sz, harness::http::Request {
+      method:  req.method.unwrap_or("").as_bytes().to_vec(),
+      uri:     req.path.unwrap_or("").as_bytes().to_vec(),
+      version: req.version.unwrap_or(0),
+      headers: req.headers.iter().map(|h| harness::http::Header::new(h.name.as_bytes(), h.value)).collect(),
+    }))
+  }
+}
+
+pub struct HttparseResponse;
+
+impl ParserUnderTest for HttparseResponse {
+  type Output = harness::http::Response;
+
+  const NAME: &'static str = "httparse";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut resp = httparse::Response::new(&mut headers);
+
+    match resp.parse(input) {
+      Ok(httparse::Status::Complete(sz)) => Ok(sz),
+      Ok(httparse::Status::Partial)      => Err(harness::Error::new("partial response")),
+      Err(err)                           => Err(harness::Error::new(err)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut resp = httparse::Response::new(&mut headers);
+
+    let sz = match resp.parse(input) {
+      Ok(httparse::Status::Complete(sz)) => sz,
+      Ok(httparse::Status::Partial)      => return Err(harness::Error::new("partial response")),
+      Err(err)                           => return Err(harness::Error::new(err)),
+    };
+
+    Ok((sz, harness::http::Response {
+      version: resp.version.unwrap_or(0),
+      code:    resp.code.unwrap_or(0),
+      reason:  resp.reason.unwrap_or("").as_bytes().to_vec(),
+      headers: resp.headers.iter().map(|h| harness::http::Header::new(h.name.as_bytes(), h.value)).collect(),
+    }))
+  }
+}
+
+/// Body framing of a request, from its headers. httparse parses the head,
+/// chunk sizes and trailers, but does not pick the framing, so this follows
+/// the same rules as the nom-optimized `body` module: the choice between
+/// `Content-Length` and chunked is not independently verified.
+enum Framing {
+  Length(usize),
+  Chunked,
+}
+
+fn framing(headers: &[httparse::Header]) -> Result<Framing, harness::Error> {
+  let mut length = None;
+  let mut codings = Vec::new();
+
+  for h in headers {
+    if h.name.eq_ignore_ascii_case("content-length") {
+      let n = std::str::from_utf8(h.value).ok()
+        .map(|v| v.trim_matches(|c| c == ' ' || c == '\t'))
+        .filter(|v| !v.is_empty() && v.bytes().all(|c| c.is_ascii_digit()))
+        .and_then(|v| v.parse::<usize>().ok())
+        .ok_or_else(|| harness::Error::new("invalid content-length"))?;
+      if length.is_some() && length != Some(n) {
+        return Err(harness::Error::new("invalid content-length"));
+      }
+      length = Some(n);
+    } else if h.name.eq_ignore_ascii_case("transfer-encoding") {
+      codings.extend(h.value.split(|&c| c == b',')
+        .map(|c| std::str::from_utf8(c).unwrap_or("").trim_matches(|c| c == ' ' || c == '\t'))
+        .filter(|c| !c.is_empty()));
+    }
+  }
+
+  match (length, codings.is_empty()) {
+    (Some(_), false) => Err(harness::Error::new("content-length with transfer-encoding")),
+    (Some(n), true)  => Ok(Framing::Length(n)),
+    (None, true)     => Ok(Framing::Length(0)),
+    (None, false)    => {
+      let chunked = codings.iter().position(|c| c.eq_ignore_ascii_case("chunked"));
+      if chunked == Some(codings.len() - 1) {
+        Ok(Framing::Chunked)
+      } else {
+        Err(harness::Error::new("invalid transfer-encoding"))
+      }
+    },
+  }
+}
+
+/// Parses a chunked body, calling `chunk` on the data of each chunk, and
+/// returning its length along with the number of trailers.
+fn chunked<'a, F>(input: &'a [u8], trailers: &mut [httparse::Header<'a>], mut chunk: F) -> Result<(usize, usize), harness::Error>
+where
+  F: FnMut(&'a [u8]),
+{
+  let mut pos = 0;
+  loop {
+    let (len, size) = match httparse::parse_chunk_size(&input[pos..]) {
+      Ok(httparse::Status::Complete(x)) => x,
+      Ok(httparse::Status::Partial)      => return Err(harness::Error::new("partial chunk")),
+      Err(_)                             => return Err(harness::Error::new("invalid chunk size")),
+    };
+    pos += len;
+    if size == 0 {
+      break;
+    }
+    let left = (input.len() - pos) as u64;
+    if size > left || left - size < 2 {
+      return Err(harness::Error::new("partial chunk"));
+    }
+    let size = size as usize;
+    chunk(&input[pos..pos + size]);
+    pos += size;
+    if &input[pos..pos + 2] != b"\r\n" {
+      return Err(harness::Error::new("invalid chunk"));
+    }
+    pos += 2;
+  }
+
+  match httparse::parse_headers(&input[pos..], trailers) {
+    Ok(httparse::Status::Complete((len, trailers))) => Ok((pos + len, trailers.len())),
+    Ok(httparse::Status::Partial)                   => Err(harness::Error::new("partial trailers")),
+    Err(err)                                        => Err(harness::Error::new(err)),
+  }
+}
+
+/// Parses a request head, then its body as framed by its headers.
+pub struct HttparseMessage;
+
+impl ParserUnderTest for HttparseMessage {
+  type Output = harness::http::Message;
+
+  const NAME: &'static str = "httparse";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut trailers = [httparse::EMPTY_HEADER; 16];
+    let mut req = httparse::Request::new(&mut headers);
+
+    let sz = match req.parse(input) {
+      Ok(httparse::Status::Complete(sz)) => sz,
+      Ok(httparse::Status::Partial)      => return Err(harness::Error::new("partial request")),
+      Err(err)                           => return Err(harness::Error::new(err)),
+    };
+
+    match framing(req.headers)? {
+      Framing::Length(n) if input.len() - sz < n => Err(harness::Error::new("partial body")),
+      Framing::Length(n) => Ok(sz + n),
+      Framing::Chunked   => chunked(&input[sz..], &mut trailers, |_| ()).map(|(len, _)| sz + len),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 16];
+    let mut trailers = [httparse::EMPTY_HEADER; 16];
+    let mut req = httparse::Request::new(&mut headers);
+
+    let sz = match req.parse(input) {
+      Ok(httparse::Status::Complete(sz)) => sz,
+      Ok(httparse::Status::Partial)      => return Err(harness::Error::new("partial request")),
+      Err(err)                           => return Err(harness::Error::new(err)),
+    };
+
+    let mut body = Vec::new();
+    let (len, count) = match framing(req.headers)? {
+      Framing::Length(n) if input.len() - sz < n => return Err(harness::Error::new("partial body")),
+      Framing::Length(n) => {
+        body.extend_from_slice(&input[sz..sz + n]);
+        (n, 0)
+      },
+      Framing::Chunked   => chunked(&input[sz..], &mut trailers, |data| body.extend_from_slice(data))?,
+    };
+
+    Ok((sz + len, harness::http::Message {
+      request: harness::http::Request {
+        method:  req.method.unwrap_or("").as_bytes().to_vec(),
+        uri:     req.path.unwrap_or("").as_bytes().to_vec(),
+        version: req.version.unwrap_or(0),
+        headers: req.headers.iter().map(|h| harness::http::Header::new(h.name.as_bytes(), h.value)).collect(),
+      },
+      body,
+      trailers: trailers[..count].iter().map(|h| harness::http::Header::new(h.name.as_bytes(), h.value)).collect(),
+    }))
+  }
+}
@@ -0,0 +1,222 @@
+#[macro_use]
+extern crate nom;
+
+extern crate harness;
+
+use harness::ParserUnderTest;
+use nom::IResult;
+
+#[derive(Debug)]
+pub struct Request<'a> {
+    pub method:  &'a [u8],
+    pub uri:     &'a [u8],
+    pub version: &'a [u8],
+}
+
+#[derive(Debug)]
+pub struct Response<'a> {
+    pub version: &'a [u8],
+    pub code:    u16,
+    pub reason:  &'a [u8],
+}
+
+#[derive(Debug)]
+pub struct Header<'a> {
+    pub name:  &'a [u8],
+    pub value: Vec<&'a [u8]>,
+}
+
+fn is_token(c: u8) -> bool {
+    match c {
+        128..=255 => false,
+        0..=31    => false,
+        b'('      => false,
+        b')'      => false,
+        b'<'      => false,
+        b'>'      => false,
+        b'@'      => false,
+        b','      => false,
+        b';'      => false,
+        b':'      => false,
+        b'\\'     => false,
+        b'"'      => false,
+        b'/'      => false,
+        b'['      => false,
+        b']'      => false,
+        b'?'      => false,
+        b'='      => false,
+        b'{'      => false,
+        b'}'      => false,
+        b' '      => false,
+        _         => true,
+    }
+}
+
+fn not_line_ending(c: u8) -> bool {
+    c != b'\r' && c != b'\n'
+}
+
+fn is_space(c: u8) -> bool {
+    c == b' '
+}
+
+fn is_not_space(c: u8)        -> bool { c != b' ' }
+fn is_horizontal_space(c: u8) -> bool { c == b' ' || c == b'\t' }
+
+fn is_version(c: u8) -> bool {
+    c >= b'0' && c <= b'9' || c == b'.'
+}
+
+named!(line_ending, alt!(tag!("\r\n") | tag!("\n")));
+
+fn request_line<'a>(input: &'a [u8]) -> IResult<&'a[u8], Request<'a>> {
+  do_parse!(input,
+    method: take_while1!(is_token)     >>
+            take_while1!(is_space)     >>
+    url:    take_while1!(is_not_space) >>
+            take_while1!(is_space)     >>
+    version: http_version              >>
+    line_ending                        >>
+    ( Request {
+        method: method,
+        uri:    url,
+        version: version,
+    } )
+  )
+}
+
+fn is_digit(c: u8) -> bool {
+    c >= b'0' && c <= b'9'
+}
+
+fn status_code(digits: &[u8]) -> Option<u16> {
+    if digits.len() == 3 && digits.iter().all(|&c| is_digit(c)) {
+        Some(digits.iter().fold(0, |code, &c| code * 10 + (c - b'0') as u16))
+    } else {
+        None
+    }
+}
+
+fn status_line<'a>(input: &'a [u8]) -> IResult<&'a[u8], Response<'a>> {
+  do_parse!(input,
+    version: http_version                       >>
+             take_while1!(is_space)             >>
+    code:    map_opt!(take!(3), status_code)    >>
+             take_while1!(is_space)             >>
+    reason:  take_while!(not_line_ending)       >>
+    line_ending                                 >>
+    ( Response {
+        version: version,
+        code:    code,
+        reason:  reason,
+    } )
+  )
+}
+
+named!(http_version, preceded!(
+    tag!("HTTP/"),
+    take_while1!(is_version)
+));
+
+// the trailing whitespace of each folded line is not part of the value
+fn trim_end(value: &[u8]) -> &[u8] {
+  &value[..value.iter().rposition(|&c| !is_horizontal_space(c)).map_or(0, |i| i + 1)]
+}
+
+named!(message_header_value, map!(delimited!(
+    take_while1!(is_horizontal_space),
+    take_while1!(not_line_ending),
+    line_ending
+), trim_end));
+
+fn message_header<'a>(input: &'a [u8]) -> IResult<&'a[u8], Header<'a>> {
+  do_parse!(input,
+    name:   take_while1!(is_token)       >>
+            char!(':')                   >>
+    values: many1!(message_header_value) >>
+
+    ( Header {
+        name: name,
+        value: values,
+    } )
+  )
+}
+
+pub fn request<'a>(input: &'a [u8]) -> IResult<&'a[u8], (Request<'a>, Vec<Header<'a>>)> {
+  terminated!(input,
+    pair!(request_line, many1!(message_header)),
+    line_ending
+  )
+}
+
+pub fn response<'a>(input: &'a [u8]) -> IResult<&'a[u8], (Response<'a>, Vec<Header<'a>>)> {
+  terminated!(input,
+    pair!(status_line, many0!(message_header)),
+    line_ending
+  )
+}
+
+fn version(v: &[u8]) -> Result<u8, harness::Error> {
+  match v {
+    b"1.0" => Ok(0),
+    b"1.1" => Ok(1),
+    v      => Err(harness::Error::new(v)),
+  }
+}
+
+fn normalize_headers(headers: &[Header]) -> Vec<harness::http::Header> {
+  // folded lines are joined by a single space, as RFC 7230 asks for obs-fold
+  headers.iter().map(|h| harness::http::Header::new(h.name, &h.value.join(&b' '))).collect()
+}
+
+pub struct NomHttp;
+
+impl ParserUnderTest for NomHttp {
+  type Output = harness::http::Request;
+
+  const NAME: &'static str = "nom";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    match request(input) {
+      Ok((i, _)) => Ok(input.len() - i.len()),
+      Err(e)     => Err(harness::Error::new(e)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let (i, (req, headers)) = request(input).map_err(harness::Error::new)?;
+
+    Ok((input.len() - i.len(), harness::http::Request {
+      method:  req.method.to_vec(),
+      uri:     req.uri.to_vec(),
+      version: version(req.version)?,
+      headers: normalize_headers(&headers),
+    }))
+  }
+}
+
+pub struct NomHttpResponse;
+
+impl ParserUnderTest for NomHttpResponse {
+  type Output = harness::http::Response;
+
+  const NAME: &'static str = "nom";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    match response(input) {
+      Ok((i, _)) => Ok(input.len() - i.len()),
+      Err(e)     => Err(harness::Error::new(e)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let (i, (resp, headers)) = response(input).map_err(harness::Error::new)?;
+
+    Ok((input.len() - i.len(), harness::http::Response {
+      version: version(resp.version)?,
+      code:    resp.code,
+      reason:  resp.reason.to_vec(),
+      headers: normalize_headers(&headers),
+    }))
+  }
+}
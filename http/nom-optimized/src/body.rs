@@ -0,0 +1,357 @@
+//! Message body framing, following RFC 7230 section 3.3.3.
+//!
+//! `request` and `response` only parse the head of a message. Once the
+//! headers are known, `request_framing` or `response_framing` tells how the
+//! body is delimited and `body` consumes it, so that the next message of a
+//! pipelined buffer starts at the right place.
+
+use nom::{self, Context, ErrorKind, IResult, Needed};
+
+use combinators::http::{is_header_value_token, is_token};
+use super::{headers_iter, line_ending, Header};
+
+/// How the body of a message is delimited.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Framing {
+  /// `Content-Length` bytes, 0 when the message has no body.
+  Length(usize),
+  /// `Transfer-Encoding: chunked`.
+  Chunked,
+  /// The body runs until the connection is closed (responses only).
+  Close,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FramingError {
+  /// `Content-Length` is not a number, or several of them disagree.
+  InvalidContentLength,
+  /// A request transfer coding does not end with `chunked`, or `chunked` is
+  /// applied more than once.
+  InvalidTransferEncoding,
+  /// Both `Content-Length` and `Transfer-Encoding` are present, which is
+  /// rejected instead of guessed to avoid request smuggling.
+  ContentLengthWithTransferEncoding,
+}
+
+/// A parsed message body.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Body<'a> {
+  /// Body delimited by `Content-Length`, or by the end of the input.
+  Raw(&'a [u8]),
+  Chunked(Chunked<'a>),
+}
+
+/// A chunked body. The chunks are validated while parsing, and can then be
+/// iterated over without copying them.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Chunked<'a> {
+  /// From the first chunk-size line to the last chunk, trailers excluded.
+  raw:          &'a [u8],
+  /// Number of trailer fields filled by `body`.
+  pub trailers: usize,
+}
+
+impl<'a> Chunked<'a> {
+  /// The data of each chunk, in order.
+  pub fn chunks(&self) -> Chunks<'a> {
+    Chunks { input: self.raw }
+  }
+}
+
+pub struct Chunks<'a> {
+  input: &'a [u8],
+}
+
+impl<'a> Iterator for Chunks<'a> {
+  type Item = &'a [u8];
+
+  fn next(&mut self) -> Option<&'a [u8]> {
+    match chunk(self.input) {
+      Ok((i, data)) if !data.is_empty() => {
+        self.input = i;
+        Some(data)
+      },
+      _ => None,
+    }
+  }
+}
+
+fn is_hex_digit(c: u8) -> bool {
+  c.is_ascii_hexdigit()
+}
+
+fn content_length(value: &[u8]) -> Option<usize> {
+  if value.is_empty() || !value.iter().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  value.iter().try_fold(0usize, |n, &c| n.checked_mul(10)?.checked_add((c - b'0') as usize))
+}
+
+fn trim(s: &[u8]) -> &[u8] {
+  let start = s.iter().position(|&c| c != b' ' && c != b'\t').unwrap_or(s.len());
+  let end = s.iter().rposition(|&c| c != b' ' && c != b'\t').map_or(start, |e| e + 1);
+  &s[start..end]
+}
+
+/// Returns whether the transfer codings end with `chunked`, which must not
+/// appear anywhere else.
+fn is_chunked(headers: &[Header]) -> Result<bool, FramingError> {
+  let mut codings = headers.iter()
+    .filter(|h| h.name.eq_ignore_ascii_case(b"transfer-encoding"))
+    .flat_map(|h| h.value.split(|&c| c == b','))
+    .map(trim)
+    .filter(|c| !c.is_empty())
+    .peekable();
+
+  while let Some(coding) = codings.next() {
+    if coding.eq_ignore_ascii_case(b"chunked") {
+      return if codings.peek().is_none() { Ok(true) } else { Err(FramingError::InvalidTransferEncoding) };
+    }
+  }
+  Ok(false)
+}
+
+fn framing(headers: &[Header]) -> Result<Option<Framing>, FramingError> {
+  let mut length = None;
+  let mut transfer_encoding = false;
+
+  for h in headers {
+    if h.name.eq_ignore_ascii_case(b"content-length") {
+      let n = content_length(trim(h.value)).ok_or(FramingError::InvalidContentLength)?;
+      if length.is_some() && length != Some(n) {
+        return Err(FramingError::InvalidContentLength);
+      }
+      length = Some(n);
+    } else if h.name.eq_ignore_ascii_case(b"transfer-encoding") {
+      transfer_encoding = true;
+    }
+  }
+
+  match (length, transfer_encoding) {
+    (Some(_), true) => Err(FramingError::ContentLengthWithTransferEncoding),
+    (Some(n), false) => Ok(Some(Framing::Length(n))),
+    (None, true) => Ok(Some(if is_chunked(headers)? { Framing::Chunked } else { Framing::Close })),
+    (None, false) => Ok(None),
+  }
+}
+
+/// Framing of a request body: a request without `Content-Length` or
+/// `Transfer-Encoding` has no body.
+pub fn request_framing(headers: &[Header]) -> Result<Framing, FramingError> {
+  match framing(headers)? {
+    None => Ok(Framing::Length(0)),
+    Some(Framing::Close) => Err(FramingError::InvalidTransferEncoding),
+    Some(framing) => Ok(framing),
+  }
+}
+
+/// Framing of a response body. Responses to `HEAD` requests and `2xx`
+/// responses to `CONNECT` also have no body, but that depends on the
+/// request and is left to the caller.
+pub fn response_framing(code: u16, headers: &[Header]) -> Result<Framing, FramingError> {
+  if (100..200).contains(&code) || code == 204 || code == 304 {
+    return Ok(Framing::Length(0));
+  }
+  Ok(framing(headers)?.unwrap_or(Framing::Close))
+}
+
+named!(chunk_size<usize>, map_opt!(take_while1!(is_hex_digit), |digits: &[u8]| {
+  if digits.len() > 2 * std::mem::size_of::<usize>() {
+    return None;
+  }
+  Some(digits.iter().fold(0, |n, &c| n * 16 + (c as char).to_digit(16).unwrap() as usize))
+}));
+
+/// `ErrorKind::Custom` code for a malformed chunk extension.
+pub const INVALID_CHUNK_EXTENSION: u32 = 2;
+
+fn skip_whitespace(s: &[u8]) -> &[u8] {
+  &s[s.iter().position(|&c| c != b' ' && c != b'\t').unwrap_or(s.len())..]
+}
+
+fn token_len(s: &[u8]) -> usize {
+  s.iter().position(|&c| !is_token(c)).unwrap_or(s.len())
+}
+
+// length of the quoted-string starting `s`, with its quotes
+fn quoted_string_len(s: &[u8]) -> Option<usize> {
+  let mut i = 1;
+  loop {
+    match *s.get(i)? {
+      b'"'  => return Some(i + 1),
+      b'\\' => {
+        let c = *s.get(i + 1)?;
+        if c != b'\t' && c < b' ' || c == 0x7f {
+          return None;
+        }
+        i += 2;
+      },
+      c if c == b'\t' || c >= b' ' && c != 0x7f => i += 1,
+      _ => return None,
+    }
+  }
+}
+
+// `*( BWS ";" BWS name [ BWS "=" BWS ( token / quoted-string ) ] )`
+fn is_chunk_extensions(mut s: &[u8]) -> bool {
+  loop {
+    s = skip_whitespace(s);
+    if s.is_empty() {
+      return true;
+    }
+    if s[0] != b';' {
+      return false;
+    }
+    s = skip_whitespace(&s[1..]);
+    let name = token_len(s);
+    if name == 0 {
+      return false;
+    }
+    s = skip_whitespace(&s[name..]);
+    if s.first() == Some(&b'=') {
+      s = skip_whitespace(&s[1..]);
+      let value = if s.first() == Some(&b'"') { quoted_string_len(s) } else { Some(token_len(s)) };
+      match value {
+        Some(n) if n > 0 => s = &s[n..],
+        _ => return false,
+      }
+    }
+  }
+}
+
+// chunk extensions (`;name=value`) are validated but ignored
+fn chunk_extensions(input: &[u8]) -> IResult<&[u8], ()> {
+  let end = input.iter().position(|&c| !is_header_value_token(c)).unwrap_or(input.len());
+  if end == input.len() {
+    return Err(nom::Err::Incomplete(Needed::Unknown));
+  }
+  if !is_chunk_extensions(&input[..end]) {
+    return Err(nom::Err::Error(Context::Code(input, ErrorKind::Custom(INVALID_CHUNK_EXTENSION))));
+  }
+  Ok((&input[end..], ()))
+}
+
+/// One chunk, returning its data. The last chunk has no data.
+fn chunk(input: &[u8]) -> IResult<&[u8], &[u8]> {
+  do_parse!(input,
+    size: chunk_size       >>
+          chunk_extensions >>
+          line_ending      >>
+    data: take!(size)      >>
+          cond!(size > 0, line_ending) >>
+    (data)
+  )
+}
+
+fn chunked<'a, 'h>(input: &'a [u8], trailers: &'h mut [Header<'a>]) -> IResult<&'a [u8], Chunked<'a>> {
+  let mut i = input;
+  loop {
+    let (rest, data) = chunk(i)?;
+    i = rest;
+    if data.is_empty() {
+      break;
+    }
+  }
+  let raw = &input[..input.len() - i.len()];
+
+  do_parse!(i,
+    count: apply!(headers_iter, trailers) >>
+           line_ending                    >>
+    (Chunked { raw: raw, trailers: count })
+  )
+}
+
+/// Parses a body delimited by `framing`. Trailer fields of a chunked body are
+/// stored in `trailers`.
+pub fn body<'a, 'h>(input: &'a [u8], framing: Framing, trailers: &'h mut [Header<'a>]) -> IResult<&'a [u8], Body<'a>> {
+  match framing {
+    Framing::Length(n) => {
+      if input.len() < n {
+        Err(nom::Err::Incomplete(Needed::Size(n - input.len())))
+      } else {
+        Ok((&input[n..], Body::Raw(&input[..n])))
+      }
+    },
+    Framing::Chunked => map!(input, apply!(chunked, trailers), Body::Chunked),
+    Framing::Close => Ok((&input[input.len()..], Body::Raw(input))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn header<'a>(name: &'a str, value: &'a str) -> Header<'a> {
+    Header { name: name.as_bytes(), value: value.as_bytes() }
+  }
+
+  #[test]
+  fn framing() {
+    assert_eq!(request_framing(&[header("Host", "a")]), Ok(Framing::Length(0)));
+    assert_eq!(request_framing(&[header("content-length", " 12 ")]), Ok(Framing::Length(12)));
+    assert_eq!(request_framing(&[header("Content-Length", "1"), header("Content-Length", "1")]), Ok(Framing::Length(1)));
+    assert_eq!(request_framing(&[header("Content-Length", "1"), header("Content-Length", "2")]), Err(FramingError::InvalidContentLength));
+    assert_eq!(request_framing(&[header("Content-Length", "-1")]), Err(FramingError::InvalidContentLength));
+    assert_eq!(request_framing(&[header("Transfer-Encoding", "gzip, Chunked")]), Ok(Framing::Chunked));
+    assert_eq!(request_framing(&[header("Transfer-Encoding", "chunked, gzip")]), Err(FramingError::InvalidTransferEncoding));
+    assert_eq!(request_framing(&[header("Transfer-Encoding", "gzip")]), Err(FramingError::InvalidTransferEncoding));
+    assert_eq!(
+      request_framing(&[header("Transfer-Encoding", "chunked"), header("Content-Length", "3")]),
+      Err(FramingError::ContentLengthWithTransferEncoding)
+    );
+
+    assert_eq!(response_framing(304, &[header("Content-Length", "12")]), Ok(Framing::Length(0)));
+    assert_eq!(response_framing(200, &[header("Server", "nginx")]), Ok(Framing::Close));
+    assert_eq!(response_framing(200, &[header("Transfer-Encoding", "gzip")]), Ok(Framing::Close));
+  }
+
+  #[test]
+  fn chunked_body() {
+    let input = &b"4;name=value\r\nWiki\r\n5\r\npedia\r\nE ; a\r\n in\r\n\r\nchunks.\r\n0\r\nExpires: never\r\n\r\nGET"[..];
+    let mut trailers = [Header { name: &[], value: &[] }; 4];
+
+    let (rest, body) = body(input, Framing::Chunked, &mut trailers).unwrap();
+    assert_eq!(rest, &b"GET"[..]);
+    let chunked = match body {
+      Body::Chunked(chunked) => chunked,
+      _ => panic!("not chunked"),
+    };
+    let data: Vec<u8> = chunked.chunks().flat_map(|c| c.iter().cloned()).collect();
+    assert_eq!(&data[..], &b"Wikipedia in\r\n\r\nchunks."[..]);
+    assert_eq!(&trailers[..chunked.trailers], &[header("Expires", "never")]);
+
+    // every prefix is incomplete
+    for i in 0..input.len() - 3 {
+      match super::body(&input[..i], Framing::Chunked, &mut trailers) {
+        Err(nom::Err::Incomplete(_)) => {},
+        r => panic!("{:?} at {}", r, i),
+      }
+    }
+
+    assert!(super::body(&b"4\r\nWikiX\r\n0\r\n\r\n"[..], Framing::Chunked, &mut trailers).is_err());
+    assert!(super::body(&b"4 x\r\nWiki\r\n0\r\n\r\n"[..], Framing::Chunked, &mut trailers).is_err());
+  }
+
+  #[test]
+  fn chunk_extensions() {
+    let valid: &[&[u8]] = &[b"", b";a", b" ; a = b ;c", b";a=\"b; \\\"c\"", b";a=\"\";b=c"];
+    let invalid: &[&[u8]] = &[b";", b";=b", b";a=", b";a b", b";a=b c", b";a=\"b", b";a=\"b\"c", b"a", b";a;", b";a=b,c"];
+
+    for ext in valid {
+      let input = [&b"1"[..], ext, b"\r\nx\r\n0\r\n\r\n"].concat();
+      assert!(super::body(&input, Framing::Chunked, &mut []).is_ok(), "{:?}", String::from_utf8_lossy(ext));
+    }
+    for ext in invalid {
+      let input = [&b"1"[..], ext, b"\r\nx\r\n0\r\n\r\n"].concat();
+      assert!(super::body(&input, Framing::Chunked, &mut []).is_err(), "{:?}", String::from_utf8_lossy(ext));
+    }
+  }
+
+  #[test]
+  fn length_body() {
+    let mut trailers = [];
+    assert_eq!(body(&b"abcdef"[..], Framing::Length(4), &mut trailers), Ok((&b"ef"[..], Body::Raw(&b"abcd"[..]))));
+    assert_eq!(body(&b"abc"[..], Framing::Length(4), &mut trailers), Err(nom::Err::Incomplete(Needed::Size(1))));
+    assert_eq!(body(&b"abc"[..], Framing::Close, &mut trailers), Ok((&b""[..], Body::Raw(&b"abc"[..]))));
+  }
+}
@@ -0,0 +1,451 @@
+#[macro_use]
+extern crate nom;
+#[macro_use]
+extern crate combinators;
+
+extern crate harness;
+
+use harness::ParserUnderTest;
+use nom::{Context, ErrorKind, IResult};
+
+use combinators::http::is_token;
+
+pub use combinators::http::{is_header_value_token, is_url_token, HEADER_VALUE_RANGES, URI_RANGES};
+pub use combinators::{simd_path, take_while1_with, SimdPath};
+
+pub mod body;
+pub mod streaming;
+
+use body::Body;
+
+#[derive(PartialEq, Debug)]
+pub struct Request<'a> {
+    pub method:  &'a [u8],
+    pub uri:     &'a [u8],
+    pub version: u8,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Response<'a> {
+    pub version: u8,
+    pub code:    u16,
+    pub reason:  &'a [u8],
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Header<'a> {
+    pub name:  &'a [u8],
+    pub value: &'a [u8],
+}
+
+#[inline]
+fn is_header_name_token(b: u8) -> bool {
+  is_token(b)
+}
+
+fn is_horizontal_space(c: u8) -> bool { c == b' ' || c == b'\t' }
+
+named!(line_ending, alt!(tag!("\r\n") | tag!("\n")));
+
+// `take_while1_simd!` picks the SSE4.2, AVX2 or scalar implementation at
+// runtime, see `combinators::simd_path`
+fn request_line<'a,'r>(input: &'a [u8], req: &'r mut Request<'a>) -> IResult<&'a[u8], ()> {
+  do_parse!(input,
+    method: take_while1_unrolled!(is_token)     >>
+            char!(' ') >>
+    uri:    take_while1_simd!(is_url_token, URI_RANGES) >>
+            char!(' ') >>
+    version: http_version              >>
+    line_ending                        >>
+    ({
+      req.method  = method;
+      req.uri     = uri;
+      req.version = version;
+    })
+  )
+}
+
+named!(http_version<u8>, preceded!(
+    tag!("HTTP/1."),
+    map!(one_of!("01"), |n| if n == '0' { 0 } else { 1 })
+));
+
+fn status_code(digits: &[u8]) -> Option<u16> {
+  if digits.iter().all(|c| c.is_ascii_digit()) {
+    Some(digits.iter().fold(0, |code, &c| code * 10 + (c - b'0') as u16))
+  } else {
+    None
+  }
+}
+
+// the reason phrase may be empty, and uses the same characters as header values
+fn status_line<'a,'r>(input: &'a [u8], resp: &'r mut Response<'a>) -> IResult<&'a[u8], ()> {
+  do_parse!(input,
+    version: http_version                    >>
+             char!(' ')                      >>
+    code:    map_opt!(take!(3), status_code) >>
+             char!(' ')                      >>
+    reason:  reason_phrase                   >>
+    line_ending                              >>
+    ({
+      resp.version = version;
+      resp.code    = code;
+      resp.reason  = reason;
+    })
+  )
+}
+
+named!(reason_phrase, map!(
+    opt!(take_while1_simd!(is_header_value_token, HEADER_VALUE_RANGES)),
+    |r| r.unwrap_or(&[])
+));
+
+named!(header_value, delimited!(
+    take_while1_unrolled!(is_horizontal_space),
+    take_while1_simd!(is_header_value_token, HEADER_VALUE_RANGES),
+    line_ending
+));
+
+// trailing whitespace is not part of a field value (RFC 7230 section 3.2)
+fn trim_end(value: &[u8]) -> &[u8] {
+  &value[..value.iter().rposition(|&c| !is_horizontal_space(c)).map_or(0, |i| i + 1)]
+}
+
+fn header<'a,'h>(input: &'a [u8], header: &'h mut Header<'a>) -> IResult<&'a[u8], ()> {
+  let (input, name) = try_parse!(input, take_while1_unrolled!(is_header_name_token));
+  header.name = name;
+  let (input, _) = try_parse!(input, char!(':'));
+  let (input, value) = try_parse!(input, header_value);
+  header.value = trim_end(value);
+  Ok((input, ()))
+}
+
+/// `ErrorKind::Custom` code returned when a message has more headers than
+/// the slice given to store them, as httparse's `Error::TooManyHeaders`.
+pub const TOO_MANY_HEADERS: u32 = 1;
+
+fn headers_iter<'a,'h>(input: &'a [u8], headers: &'h mut [Header<'a>]) -> IResult<&'a[u8], usize> {
+  let mut input = input;
+  let mut count = 0;
+
+  for h in headers.iter_mut() {
+    match header(input, h) {
+      Ok((i, _)) => input = i,
+      Err(nom::Err::Error(_)) => return Ok((input, count)),
+      Err(e) => return Err(e),
+    }
+    count += 1;
+  }
+
+  // every slot is used, there must not be another header
+  let mut h = Header { name: &[], value: &[] };
+  match header(input, &mut h) {
+    Ok(_) => Err(nom::Err::Failure(Context::Code(input, ErrorKind::Custom(TOO_MANY_HEADERS)))),
+    Err(nom::Err::Error(_)) => Ok((input, count)),
+    Err(e) => Err(e),
+  }
+}
+
+fn headers_vec<'a,'h>(input: &'a [u8], headers: &'h mut Vec<Header<'a>>) -> IResult<&'a[u8], ()> {
+  let mut input = input;
+
+  loop {
+    let mut h = Header { name: &[], value: &[] };
+    match header(input, &mut h) {
+      Ok((i, _)) => input = i,
+      Err(nom::Err::Error(_)) => return Ok((input, ())),
+      Err(e) => return Err(e),
+    }
+    headers.push(h);
+  }
+}
+
+/// Parses a request into `req` and `headers`, returning the number of
+/// headers that were filled.
+pub fn request<'a,'r, 'h>(input: &'a [u8], req: &'r mut Request<'a>, headers: &'h mut [Header<'a>]) -> IResult<&'a[u8], usize> {
+  do_parse!(input,
+           apply!(request_line, req)     >>
+    count: apply!(headers_iter, headers) >>
+           line_ending                   >>
+    (count)
+  )
+}
+
+/// Parses a request into `req`, appending its headers to `headers`, for
+/// messages with an unknown number of headers.
+pub fn request_vec<'a,'r, 'h>(input: &'a [u8], req: &'r mut Request<'a>, headers: &'h mut Vec<Header<'a>>) -> IResult<&'a[u8], ()> {
+  do_parse!(input,
+    apply!(request_line, req)    >>
+    apply!(headers_vec, headers) >>
+    line_ending                  >>
+    ()
+  )
+}
+
+/// Parses a response head into `resp` and `headers`, returning the number of
+/// headers that were filled.
+pub fn response<'a,'r, 'h>(input: &'a [u8], resp: &'r mut Response<'a>, headers: &'h mut [Header<'a>]) -> IResult<&'a[u8], usize> {
+  do_parse!(input,
+           apply!(status_line, resp)     >>
+    count: apply!(headers_iter, headers) >>
+           line_ending                   >>
+    (count)
+  )
+}
+
+/// Parses a response head into `resp`, appending its headers to `headers`.
+pub fn response_vec<'a,'r, 'h>(input: &'a [u8], resp: &'r mut Response<'a>, headers: &'h mut Vec<Header<'a>>) -> IResult<&'a[u8], ()> {
+  do_parse!(input,
+    apply!(status_line, resp)    >>
+    apply!(headers_vec, headers) >>
+    line_ending                  >>
+    ()
+  )
+}
+
+pub struct NomOptimizedHttp;
+
+impl ParserUnderTest for NomOptimizedHttp {
+  type Output = harness::http::Request;
+
+  const NAME: &'static str = "nom (optimized)";
+
+  fn details() -> Vec<(&'static str, String)> {
+    vec![("take_while1_simd", simd_path().name().to_string())]
+  }
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut req = Request {
+      method: &[],
+      uri:    &[],
+      version: 0
+    };
+    let mut headers = [Header{ name: &[], value: &[] }; 16];
+
+    match request(input, &mut req, &mut headers) {
+      Ok((i, _)) => Ok(input.len() - i.len()),
+      Err(e)     => Err(harness::Error::new(e)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut req = Request {
+      method: &[],
+      uri:    &[],
+      version: 0
+    };
+    let mut headers = [Header{ name: &[], value: &[] }; 16];
+
+    let (i, count) = request(input, &mut req, &mut headers).map_err(harness::Error::new)?;
+
+    Ok((input.len() - i.len(), harness::http::Request {
+      method:  req.method.to_vec(),
+      uri:     req.uri.to_vec(),
+      version: req.version,
+      headers: headers[..count].iter().map(|h| harness::http::Header::new(h.name, h.value)).collect(),
+    }))
+  }
+}
+
+pub struct NomOptimizedHttpResponse;
+
+impl ParserUnderTest for NomOptimizedHttpResponse {
+  type Output = harness::http::Response;
+
+  const NAME: &'static str = "nom (optimized)";
+
+  fn details() -> Vec<(&'static str, String)> {
+    vec![("take_while1_simd", simd_path().name().to_string())]
+  }
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut resp = Response {
+      version: 0,
+      code:    0,
+      reason:  &[],
+    };
+    let mut headers = [Header{ name: &[], value: &[] }; 16];
+
+    match response(input, &mut resp, &mut headers) {
+      Ok((i, _)) => Ok(input.len() - i.len()),
+      Err(e)     => Err(harness::Error::new(e)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut resp = Response {
+      version: 0,
+      code:    0,
+      reason:  &[],
+    };
+    let mut headers = [Header{ name: &[], value: &[] }; 16];
+
+    let (i, count) = response(input, &mut resp, &mut headers).map_err(harness::Error::new)?;
+
+    Ok((input.len() - i.len(), harness::http::Response {
+      version: resp.version,
+      code:    resp.code,
+      reason:  resp.reason.to_vec(),
+      headers: headers[..count].iter().map(|h| harness::http::Header::new(h.name, h.value)).collect(),
+    }))
+  }
+}
+
+/// Parses a request head, then its body as framed by its headers.
+pub struct NomOptimizedHttpMessage;
+
+impl ParserUnderTest for NomOptimizedHttpMessage {
+  type Output = harness::http::Message;
+
+  const NAME: &'static str = "nom (optimized)";
+
+  fn details() -> Vec<(&'static str, String)> {
+    vec![("take_while1_simd", simd_path().name().to_string())]
+  }
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut req = Request {
+      method: &[],
+      uri:    &[],
+      version: 0
+    };
+    let mut headers  = [Header{ name: &[], value: &[] }; 16];
+    let mut trailers = [Header{ name: &[], value: &[] }; 16];
+
+    let (i, count) = request(input, &mut req, &mut headers).map_err(harness::Error::new)?;
+    let framing = body::request_framing(&headers[..count]).map_err(harness::Error::new)?;
+    match body::body(i, framing, &mut trailers) {
+      Ok((i, _)) => Ok(input.len() - i.len()),
+      Err(e)     => Err(harness::Error::new(e)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut req = Request {
+      method: &[],
+      uri:    &[],
+      version: 0
+    };
+    let mut headers  = [Header{ name: &[], value: &[] }; 16];
+    let mut trailers = [Header{ name: &[], value: &[] }; 16];
+
+    let (i, count) = request(input, &mut req, &mut headers).map_err(harness::Error::new)?;
+    let framing = body::request_framing(&headers[..count]).map_err(harness::Error::new)?;
+    let (i, body) = body::body(i, framing, &mut trailers).map_err(harness::Error::new)?;
+
+    let (data, trailers) = match body {
+      Body::Raw(data)        => (data.to_vec(), &trailers[..0]),
+      Body::Chunked(chunked) => (chunked.chunks().flat_map(|c| c.iter().cloned()).collect(), &trailers[..chunked.trailers]),
+    };
+
+    Ok((input.len() - i.len(), harness::http::Message {
+      request: harness::http::Request {
+        method:  req.method.to_vec(),
+        uri:     req.uri.to_vec(),
+        version: req.version,
+        headers: headers[..count].iter().map(|h| harness::http::Header::new(h.name, h.value)).collect(),
+      },
+      body:     data,
+      trailers: trailers.iter().map(|h| harness::http::Header::new(h.name, h.value)).collect(),
+    }))
+  }
+}
+
+#[test]
+fn response_test() {
+  let mut resp = Response {
+    version: 0,
+    code:    0,
+    reason:  &[],
+  };
+  let mut headers = [Header{ name: &[], value: &[] }; 16];
+
+  let data = &b"HTTP/1.1 404 Not Found\r\nServer: nginx\r\n\r\n"[..];
+  assert_eq!(response(data, &mut resp, &mut headers), Ok((&b""[..], 1)));
+  assert_eq!((resp.version, resp.code, resp.reason), (1, 404, &b"Not Found"[..]));
+
+  let data = &b"HTTP/1.0 200 \r\n\r\n"[..];
+  assert_eq!(response(data, &mut resp, &mut headers), Ok((&b""[..], 0)));
+  assert_eq!((resp.version, resp.code, resp.reason), (0, 200, &b""[..]));
+
+  assert!(response(&b"HTTP/1.1 20x OK\r\n\r\n"[..], &mut resp, &mut headers).is_err());
+}
+
+#[cfg(test)]
+fn many_headers(count: usize) -> Vec<u8> {
+  let mut data = b"GET / HTTP/1.1\r\n".to_vec();
+  for i in 0..count {
+    data.extend_from_slice(format!("X-Header-{}: {}\r\n", i, i).as_bytes());
+  }
+  data.extend_from_slice(b"\r\n");
+  data
+}
+
+#[test]
+fn too_many_headers_test() {
+  let (sixteen, seventeen, forty) = (many_headers(16), many_headers(17), many_headers(40));
+  let mut req = Request {
+    method: &[],
+    uri:    &[],
+    version: 0
+  };
+  let mut headers = [Header{ name: &[], value: &[] }; 16];
+
+  assert_eq!(request(&sixteen, &mut req, &mut headers), Ok((&b""[..], 16)));
+
+  for data in &[&seventeen, &forty] {
+    match request(data, &mut req, &mut headers) {
+      Err(nom::Err::Failure(Context::Code(i, ErrorKind::Custom(TOO_MANY_HEADERS)))) => {
+        assert!(i.starts_with(b"X-Header-16:"));
+      },
+      r => panic!("expected too many headers, got {:?}", r),
+    }
+  }
+
+  // the last header may still be incomplete
+  assert!(request(&seventeen[..seventeen.len() - 10], &mut req, &mut headers).unwrap_err().is_incomplete());
+}
+
+#[test]
+fn request_vec_test() {
+  let mut req = Request {
+    method: &[],
+    uri:    &[],
+    version: 0
+  };
+  let data = many_headers(40);
+  let mut headers = Vec::new();
+
+  assert_eq!(request_vec(&data, &mut req, &mut headers), Ok((&b""[..], ())));
+  assert_eq!(headers.len(), 40);
+  assert_eq!((headers[39].name, headers[39].value), (&b"X-Header-39"[..], &b"39"[..]));
+
+  let mut resp = Response {
+    version: 0,
+    code:    0,
+    reason:  &[],
+  };
+  let mut headers = Vec::new();
+  let data = &b"HTTP/1.1 200 OK\r\nA: 1\r\nB: 2\r\n\r\n"[..];
+  assert_eq!(response_vec(data, &mut resp, &mut headers), Ok((&b""[..], ())));
+  assert_eq!(headers.len(), 2);
+}
+
+#[test]
+fn httparse_test() {
+  use std::str;
+  let data = harness::fixtures::http::HTTPARSE_EXAMPLE;
+
+  let mut req = Request {
+    method: &[],
+    uri:    &[],
+    version: 0
+  };
+  let mut headers = [Header{ name: &[], value: &[] }; 16];
+  let res = request(data, &mut req, &mut headers);
+  println!("res:{:?}\nreq:\n{:?}\nheaders:\n{:?}", res, req, headers);
+  if let Err(nom::Err::Error(nom::Context::Code(i, ref e))) = res {
+    println!("got error {:?} at input:\n{}", e, str::from_utf8(i).unwrap());
+  }
+  res.unwrap();
+}
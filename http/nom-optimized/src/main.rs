@@ -1,275 +1,150 @@
-#![feature(const_fn)]
-#![feature(cfg_target_feature, target_feature, stdsimd)]
-
 #[macro_use]
-extern crate bencher;
-
+extern crate combinators;
 #[macro_use]
+extern crate harness;
 extern crate nom;
+extern crate nom_optimized_http;
 
-use bencher::{black_box, Bencher};
-
+use harness::{black_box, Bencher};
 use nom::IResult;
-
-#[macro_use]
-mod combinators;
-
-use combinators::{is_header_value_token, is_token};
-
-#[derive(Debug)]
-struct Request<'a> {
-    method:  &'a [u8],
-    uri:     &'a [u8],
-    version: u8,
-}
-
-#[derive(Copy, Clone, PartialEq, Debug)]
-struct Header<'a> {
-    name:  &'a [u8],
-    value: &'a [u8],
-}
-
-fn is_url_token(c: u8) -> bool {
-  c > 0x20 && c < 0x7F
-}
-
-#[inline]
-fn is_header_name_token(b: u8) -> bool {
-  is_token(b)
-}
-
-fn not_line_ending(c: u8) -> bool {
-    c != b'\r' && c != b'\n'
-}
-
-fn is_space(c: u8) -> bool {
-    c == b' '
+use nom_optimized_http::streaming::{RequestParser, Status};
+use nom_optimized_http::{Header, Request, SimdPath};
+
+// feeds `bigger.txt` to the streaming parser `chunk` bytes at a time, as a
+// socket would
+fn chunked(b: &mut Bencher, chunk: usize) {
+  let input = harness::fixtures::http::BIGGER;
+  let mut buf = Vec::with_capacity(4096);
+  let mut parser = RequestParser::new();
+
+  b.bytes = input.len() as u64;
+  b.iter(|| {
+    let mut count = 0;
+    buf.clear();
+    parser.reset();
+    for data in black_box(input).chunks(chunk) {
+      buf.extend_from_slice(data);
+      while let Status::Complete(len) = parser.parse(&buf).unwrap() {
+        buf.drain(..len);
+        parser.reset();
+        count += 1;
+      }
+    }
+    count
+  });
 }
 
-fn is_not_space(c: u8)        -> bool { c != b' ' }
-fn is_horizontal_space(c: u8) -> bool { c == b' ' || c == b'\t' }
-
-fn is_version(c: u8) -> bool {
-    c >= b'0' && c <= b'9' || c == b'.'
+fn post_test(b: &mut Bencher) {
+  harness::bench(b, &mut nom_optimized_http::NomOptimizedHttpMessage, harness::fixtures::http::POST)
 }
 
-named!(line_ending, alt!(tag!("\r\n") | tag!("\n")));
-
-#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"),
-      target_feature = "sse2"))]
-fn request_line<'a,'r>(input: &'a [u8], req: &'r mut Request<'a>) -> IResult<&'a[u8], ()> {
-  let range = b"\0 \x7F\x7F";
-  do_parse!(input,
-    method: take_while1_unrolled!(is_token)     >>
-            char!(' ') >>
-    uri:    take_while1_simd!(is_url_token, range) >>
-            char!(' ') >>
-    version: http_version              >>
-    line_ending                        >>
-    ({
-      req.method  = method;
-      req.uri     = uri;
-      req.version = version;
-    })
-  )
+fn bigger_chunked_16_test(b: &mut Bencher) {
+  chunked(b, 16)
 }
 
-#[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"),
-      target_feature = "sse2")))]
-fn request_line<'a,'r>(input: &'a [u8], req: &'r mut Request<'a>) -> IResult<&'a[u8], ()> {
-  do_parse!(input,
-    method: take_while1_unrolled!(is_token)     >>
-            char!(' ') >>
-    uri:    take_while1_unrolled!(is_url_token) >>
-            char!(' ') >>
-    version: http_version              >>
-    line_ending                        >>
-    ({
-      req.method  = method;
-      req.uri     = uri;
-      req.version = version;
-    })
-  )
+fn bigger_chunked_1460_test(b: &mut Bencher) {
+  chunked(b, 1460)
 }
 
-named!(http_version<u8>, preceded!(
-    tag!("HTTP/1."),
-    map!(one_of!("01"), |n| if n == '0' { 0 } else { 1 })
-));
-
-//const header_value_range:&[u8] = &[0, 010, 012, 037, 177, 177];
-const header_value_range:&[u8] = b"\0\x08\x0A\x1F\x7F\x7F";
-
-#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"),
-      target_feature = "sse2"))]
-named!(header_value, delimited!(
-    take_while1_unrolled!(is_horizontal_space),
-    take_while1_simd!(is_header_value_token, header_value_range),
-    line_ending
-));
-
-#[cfg(not(all(any(target_arch = "x86", target_arch = "x86_64"),
-      target_feature = "sse2")))]
-named!(header_value, delimited!(
-    take_while1_unrolled!(is_horizontal_space),
-    take_while1_unrolled!(is_header_value_token),
-    line_ending
-));
-
-fn header<'a,'h>(input: &'a [u8], header: &'h mut Header<'a>) -> IResult<&'a[u8], ()> {
-  let (input, name) = try_parse!(input, take_while1_unrolled!(is_header_name_token));
-  header.name = name;
-  let (input, _) = try_parse!(input, char!(':'));
-  let (input, value) = try_parse!(input, header_value);
-  header.value = value;
-  Ok((input, ()))
-}
-
-fn headers_iter<'a,'h>(input: &'a [u8], headers: &'h mut [Header<'a>]) -> IResult<&'a[u8], ()> {
-  let mut iter = headers.iter_mut();
-  let mut input = input;
-
-  loop {
-    let h = match iter.next() {
-      Some(header) => header,
-      None => break
-    };
-
-    match header(input, h) {
-      Ok((i, _)) => input = i,
-      Err(nom::Err::Error(_)) => break,
-      e => return e,
+// the URIs (or header values) of `bigger.txt`, each followed by the byte that
+// ends it, to measure the scanners alone
+fn fields(uris: bool) -> Vec<u8> {
+  let mut buf = harness::fixtures::http::BIGGER;
+  let mut fields = Vec::new();
+  while !buf.is_empty() {
+    let mut req = Request { method: &[], uri: &[], version: 0 };
+    let mut headers = [Header { name: &[], value: &[] }; 16];
+    let (rest, count) = nom_optimized_http::request(buf, &mut req, &mut headers).unwrap();
+    if uris {
+      fields.extend_from_slice(req.uri);
+      fields.push(b' ');
+    } else {
+      for h in &headers[..count] {
+        fields.extend_from_slice(h.value);
+        fields.extend_from_slice(b"\r\n");
+      }
     }
+    buf = rest;
   }
-
-  Ok((input, ()))
+  fields
 }
 
-fn request<'a,'r, 'h>(input: &'a [u8], req: &'r mut Request<'a>, headers: &'h mut [Header<'a>]) -> IResult<&'a[u8], ()> {
-  terminated!(input,
-    apply!(request_line, req),
-    pair!(
-      apply!(headers_iter, headers),
-      line_ending
-    )
-  )
+// take_while1_with falls back to scalar code when the CPU lacks the path,
+// which would record scalar timings under the SIMD names
+fn scan<F: Fn(u8) -> bool + Copy>(b: &mut Bencher, uris: bool, path: SimdPath, predicate: F, ranges: &[u8]) {
+  if !path.is_supported() {
+    b.skip(&format!("{} is not supported by this CPU", path.name()));
+    return;
+  }
+  let input = fields(uris);
+
+  b.bytes = input.len() as u64;
+  b.iter(|| {
+    let mut i = black_box(&input[..]);
+    let mut count = 0;
+    while !i.is_empty() {
+      i = match nom_optimized_http::take_while1_with(path, i, predicate, ranges) {
+        Ok((rest, _)) => { count += 1; rest }
+        Err(())      => &i[1..],
+      };
+    }
+    count
+  });
+}
+
+// the same fields through `take_while1_unrolled!`, the macro the parser uses
+// for methods and header names, rather than the scalar fallback of
+// take_while1_with
+fn scan_unrolled<F: Fn(u8) -> bool + Copy>(b: &mut Bencher, uris: bool, predicate: F) {
+  let input = fields(uris);
+
+  b.bytes = input.len() as u64;
+  b.iter(|| {
+    let mut i = black_box(&input[..]);
+    let mut count = 0;
+    while !i.is_empty() {
+      let result: IResult<&[u8], &[u8]> = take_while1_unrolled!(i, predicate);
+      i = match result {
+        Ok((rest, _)) => { count += 1; rest }
+        Err(_)        => &i[1..],
+      };
+    }
+    count
+  });
 }
 
-
-fn small_test(b: &mut Bencher) {
-  let data = include_bytes!("../../http-requests.txt");
-  b.bytes = data.len() as u64;
-  parse(b, data)
+fn uri_scan_unrolled_test(b: &mut Bencher) {
+  scan_unrolled(b, true, nom_optimized_http::is_url_token)
 }
 
-fn bigger_test(b: &mut Bencher) {
-  let data = include_bytes!("../../bigger.txt");
-  b.bytes = data.len() as u64;
-  parse(b, data)
+fn header_value_scan_unrolled_test(b: &mut Bencher) {
+  scan_unrolled(b, false, nom_optimized_http::is_header_value_token)
 }
 
-fn one_test(b: &mut Bencher) {
-  let data = &b"GET / HTTP/1.1
-Host: www.reddit.com
-User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10.8; rv:15.0) Gecko/20100101 Firefox/15.0.1
-Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8
-Accept-Language: en-us,en;q=0.5
-Accept-Encoding: gzip, deflate
-Connection: keep-alive
-
-"[..];
-  b.bytes = data.len() as u64;
-  parse(b, data)
+fn uri_scan_scalar_test(b: &mut Bencher) {
+  scan(b, true, SimdPath::Scalar, nom_optimized_http::is_url_token, nom_optimized_http::URI_RANGES)
 }
 
-fn httparse_example_test(b: &mut Bencher) {
-  let data = &b"GET /wp-content/uploads/2010/03/hello-kitty-darth-vader-pink.jpg HTTP/1.1\r\n\
-Host: www.kittyhell.com\r\n\
-User-Agent: Mozilla/5.0 (Macintosh; U; Intel Mac OS X 10.6; ja-JP-mac; rv:1.9.2.3) Gecko/20100401 Firefox/3.6.3 Pathtraq/0.9\r\n\
-Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
-Accept-Language: ja,en-us;q=0.7,en;q=0.3\r\n\
-Accept-Encoding: gzip,deflate\r\n\
-Accept-Charset: Shift_JIS,utf-8;q=0.7,*;q=0.7\r\n\
-Keep-Alive: 115\r\n\
-Connection: keep-alive\r\n\
-Cookie: wp_ozh_wsa_visits=2; wp_ozh_wsa_visit_lasttime=xxxxxxxxxx; __utma=xxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.x; __utmz=xxxxxxxxx.xxxxxxxxxx.x.x.utmccn=(referral)|utmcsr=reader.livedoor.com|utmcct=/reader/|utmcmd=referral\r\n\r\n"[..];
-
-  b.bytes = data.len() as u64;
-  parse(b, data)
+fn uri_scan_sse42_test(b: &mut Bencher) {
+  scan(b, true, SimdPath::Sse42, nom_optimized_http::is_url_token, nom_optimized_http::URI_RANGES)
 }
 
-#[test]
-fn httparse_test() {
-  use std::str;
-  let data = &b"GET /wp-content/uploads/2010/03/hello-kitty-darth-vader-pink.jpg HTTP/1.1\r\n\
-Host: www.kittyhell.com\r\n\
-User-Agent: Mozilla/5.0 (Macintosh; U; Intel Mac OS X 10.6; ja-JP-mac; rv:1.9.2.3) Gecko/20100401 Firefox/3.6.3 Pathtraq/0.9\r\n\
-Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
-Accept-Language: ja,en-us;q=0.7,en;q=0.3\r\n\
-Accept-Encoding: gzip,deflate\r\n\
-Accept-Charset: Shift_JIS,utf-8;q=0.7,*;q=0.7\r\n\
-Keep-Alive: 115\r\n\
-Connection: keep-alive\r\n\
-Cookie: wp_ozh_wsa_visits=2; wp_ozh_wsa_visit_lasttime=xxxxxxxxxx; __utma=xxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.xxxxxxxxxx.x; __utmz=xxxxxxxxx.xxxxxxxxxx.x.x.utmccn=(referral)|utmcsr=reader.livedoor.com|utmcct=/reader/|utmcmd=referral\r\n\r\n"[..];
-
-  let mut req = Request {
-    method: &[],
-    uri:    &[],
-    version: 0
-  };
-  let mut headers = [Header{ name: &[], value: &[] }; 16];
-  let res = request(data, &mut req, &mut headers);
-  println!("res:{:?}\nreq:\n{:?}\nheaders:\n{:?}", res, req, headers);
-  if let Err(nom::Err::Error(nom::Context::Code(ref i, ref e))) = res {
-    println!("got error {:?} at input:\n{}", e, str::from_utf8(i).unwrap());
-  }
-  res.unwrap();
+fn uri_scan_avx2_test(b: &mut Bencher) {
+  scan(b, true, SimdPath::Avx2, nom_optimized_http::is_url_token, nom_optimized_http::URI_RANGES)
 }
 
-fn parse(b: &mut Bencher, buffer: &[u8]) {
-    let mut req = Request {
-      method: &[],
-      uri:    &[],
-      version: 0
-    };
-    let mut headers = [Header{ name: &[], value: &[] }; 16];
-    b.iter(|| {
-        let mut buf = black_box(buffer);
-        let mut v = Vec::new();
-
-        while !buf.is_empty() {
-            match request(buf, &mut req, &mut headers) {
-                Ok((i, o)) => {
-                    v.push(o);
-
-                    buf = i
-                }
-                Err(err) => panic!("got err: {:?}", err),
-            }
-        }
-
-        v
-    });
+fn header_value_scan_scalar_test(b: &mut Bencher) {
+  scan(b, false, SimdPath::Scalar, nom_optimized_http::is_header_value_token, nom_optimized_http::HEADER_VALUE_RANGES)
 }
 
-benchmark_group!(http, one_test, small_test, bigger_test, httparse_example_test);
-benchmark_main!(http);
-
-/*
-fn main() {
-    let mut contents: Vec<u8> = Vec::new();
-
-    {
-        use std::io::Read;
-
-        let mut file = File::open(env::args().nth(1).expect("File to read")).ok().expect("Failed to open file");
-
-        let _ = file.read_to_end(&mut contents).unwrap();
-    }
-
-    let mut buf = &contents[..];
-    loop { parse(buf); }
+fn header_value_scan_sse42_test(b: &mut Bencher) {
+  scan(b, false, SimdPath::Sse42, nom_optimized_http::is_header_value_token, nom_optimized_http::HEADER_VALUE_RANGES)
 }
-*/
 
+fn header_value_scan_avx2_test(b: &mut Bencher) {
+  scan(b, false, SimdPath::Avx2, nom_optimized_http::is_header_value_token, nom_optimized_http::HEADER_VALUE_RANGES)
+}
 
+http_benches!(nom_optimized_http::NomOptimizedHttp, nom_optimized_http::NomOptimizedHttpResponse; post_test, bigger_chunked_16_test, bigger_chunked_1460_test,
+  uri_scan_unrolled_test, uri_scan_scalar_test, uri_scan_sse42_test, uri_scan_avx2_test,
+  header_value_scan_unrolled_test, header_value_scan_scalar_test, header_value_scan_sse42_test, header_value_scan_avx2_test);
@@ -0,0 +1,255 @@
+//! Incremental request parsing, for data arriving in chunks.
+//!
+//! The caller keeps appending what it receives to its own buffer and calls
+//! `RequestParser::parse` with the whole buffer each time. The parser keeps
+//! the offsets of what it already parsed and resumes at the first incomplete
+//! line, so a request delivered one byte at a time is not parsed over and
+//! over again.
+//!
+//! Only the request head is parsed, the body that may follow is handled by
+//! the `body` module.
+
+use std::ops::Range;
+
+use nom::{self, ErrorKind};
+
+use super::{header, line_ending, request_line, Header, Request};
+
+/// Result of a parse attempt, as in httparse.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Status<T> {
+  /// The whole request was parsed, holds its length in bytes.
+  Complete(T),
+  /// More data is needed.
+  Partial,
+}
+
+impl<T> Status<T> {
+  pub fn is_complete(&self) -> bool {
+    match *self {
+      Status::Complete(_) => true,
+      Status::Partial     => false,
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Error {
+  /// The request is malformed, `offset` is the start of the offending line.
+  Invalid { offset: usize, kind: ErrorKind },
+}
+
+fn kind(err: nom::Err<&[u8]>) -> ErrorKind {
+  match err {
+    nom::Err::Error(ctx) | nom::Err::Failure(ctx) => ctx.into_error_kind(),
+    nom::Err::Incomplete(_)                       => unreachable!(),
+  }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum State {
+  RequestLine,
+  Headers,
+  Done(usize),
+}
+
+/// Resumable request parser, see the module documentation.
+#[derive(Debug)]
+pub struct RequestParser {
+  state:   State,
+  /// Where parsing resumes: the start of the first line not parsed yet.
+  pos:     usize,
+  method:  Range<usize>,
+  uri:     Range<usize>,
+  version: u8,
+  headers: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl Default for RequestParser {
+  fn default() -> RequestParser {
+    RequestParser::new()
+  }
+}
+
+impl RequestParser {
+  pub fn new() -> RequestParser {
+    RequestParser {
+      state:   State::RequestLine,
+      pos:     0,
+      method:  0..0,
+      uri:     0..0,
+      version: 0,
+      headers: Vec::new(),
+    }
+  }
+
+  /// Forgets the current request, to parse the next one from a new buffer.
+  pub fn reset(&mut self) {
+    self.state = State::RequestLine;
+    self.pos = 0;
+    self.headers.clear();
+  }
+
+  /// Parses the request received so far.
+  ///
+  /// `buf` must start with the bytes given to the previous calls since the
+  /// last `reset`. Once the request is complete, it is available through
+  /// `request` and `headers`, and further calls keep returning `Complete`.
+  pub fn parse(&mut self, buf: &[u8]) -> Result<Status<usize>, Error> {
+    loop {
+      let input = &buf[self.pos..];
+      let offset = |s: &[u8]| s.as_ptr() as usize - buf.as_ptr() as usize;
+
+      match self.state {
+        State::RequestLine => {
+          let mut req = Request { method: &[], uri: &[], version: 0 };
+          match request_line(input, &mut req) {
+            Ok((i, _)) => {
+              self.method  = offset(req.method)..offset(req.method) + req.method.len();
+              self.uri     = offset(req.uri)..offset(req.uri) + req.uri.len();
+              self.version = req.version;
+              self.pos     = offset(i);
+              self.state   = State::Headers;
+            },
+            Err(nom::Err::Incomplete(_)) => return Ok(Status::Partial),
+            Err(e) => return Err(Error::Invalid { offset: self.pos, kind: kind(e) }),
+          }
+        },
+        State::Headers => {
+          let mut h = Header { name: &[], value: &[] };
+          match header(input, &mut h) {
+            Ok((i, _)) => {
+              self.headers.push((
+                offset(h.name)..offset(h.name) + h.name.len(),
+                offset(h.value)..offset(h.value) + h.value.len(),
+              ));
+              self.pos = offset(i);
+            },
+            Err(nom::Err::Incomplete(_)) => return Ok(Status::Partial),
+            // not a header, this should be the empty line ending the request
+            Err(nom::Err::Error(_)) => match line_ending(input) {
+              Ok((i, _)) => self.state = State::Done(offset(i)),
+              Err(nom::Err::Incomplete(_)) => return Ok(Status::Partial),
+              Err(e) => return Err(Error::Invalid { offset: self.pos, kind: kind(e) }),
+            },
+            Err(e) => return Err(Error::Invalid { offset: self.pos, kind: kind(e) }),
+          }
+        },
+        State::Done(len) => return Ok(Status::Complete(len)),
+      }
+    }
+  }
+
+  /// The request line, once `parse` returned `Complete` for `buf`.
+  pub fn request<'a>(&self, buf: &'a [u8]) -> Request<'a> {
+    Request {
+      method:  &buf[self.method.clone()],
+      uri:     &buf[self.uri.clone()],
+      version: self.version,
+    }
+  }
+
+  /// The headers parsed so far from `buf`.
+  pub fn headers<'a, 'p>(&'p self, buf: &'a [u8]) -> impl Iterator<Item = Header<'a>> + 'p
+  where
+    'a: 'p,
+  {
+    self.headers.iter().map(move |(name, value)| Header {
+      name:  &buf[name.clone()],
+      value: &buf[value.clone()],
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use harness::fixtures::http::BIGGER;
+
+  fn complete(buf: &[u8]) -> (usize, harness::http::Request) {
+    let mut parser = RequestParser::new();
+    let len = match parser.parse(buf).unwrap() {
+      Status::Complete(len) => len,
+      Status::Partial       => panic!("partial request"),
+    };
+    (len, normalize(&parser, buf))
+  }
+
+  fn normalize(parser: &RequestParser, buf: &[u8]) -> harness::http::Request {
+    let req = parser.request(buf);
+    harness::http::Request {
+      method:  req.method.to_vec(),
+      uri:     req.uri.to_vec(),
+      version: req.version,
+      headers: parser.headers(buf).map(|h| harness::http::Header::new(h.name, h.value)).collect(),
+    }
+  }
+
+  fn requests() -> Vec<&'static [u8]> {
+    let mut requests = Vec::new();
+    let mut buf = BIGGER;
+    while !buf.is_empty() {
+      let (len, _) = complete(buf);
+      requests.push(&buf[..len]);
+      buf = &buf[len..];
+    }
+    requests
+  }
+
+  #[test]
+  fn same_as_request() {
+    use harness::ParserUnderTest;
+
+    let mut buf = BIGGER;
+    while !buf.is_empty() {
+      let expected = ::NomOptimizedHttp.parse_normalized(buf).unwrap();
+      assert_eq!(complete(buf), expected);
+      buf = &buf[expected.0..];
+    }
+  }
+
+  #[test]
+  fn split_at_every_byte() {
+    for request in requests() {
+      let (len, expected) = complete(request);
+      for split in 0..request.len() {
+        let mut parser = RequestParser::new();
+        assert_eq!(parser.parse(&request[..split]), Ok(Status::Partial), "split at {}", split);
+        assert_eq!(parser.parse(request), Ok(Status::Complete(len)), "split at {}", split);
+        assert_eq!(normalize(&parser, request), expected, "split at {}", split);
+      }
+    }
+  }
+
+  #[test]
+  fn byte_by_byte() {
+    let requests = requests();
+    let mut parser = RequestParser::new();
+    let mut buf = Vec::new();
+    let mut parsed = 0;
+
+    for &b in BIGGER {
+      buf.push(b);
+      if let Status::Complete(len) = parser.parse(&buf).unwrap() {
+        assert_eq!(&buf[..len], requests[parsed]);
+        assert_eq!(normalize(&parser, &buf), complete(requests[parsed]).1);
+        parsed += 1;
+        buf.drain(..len);
+        parser.reset();
+      }
+    }
+
+    assert_eq!(parsed, requests.len());
+    assert!(buf.is_empty());
+  }
+
+  #[test]
+  fn invalid() {
+    let mut parser = RequestParser::new();
+    assert_eq!(parser.parse(b"GET / HTTP/1.1\r\nHost: a\r\n"), Ok(Status::Partial));
+    assert_eq!(
+      parser.parse(b"GET / HTTP/1.1\r\nHost: a\r\n:\r\n\r\n"),
+      Err(Error::Invalid { offset: 25, kind: ErrorKind::Alt })
+    );
+  }
+}
@@ -0,0 +1,114 @@
+extern crate harness;
+extern crate pico_sys as pico;
+
+use std::{ptr, slice};
+
+use harness::ParserUnderTest;
+
+/// Same layout as `phr_header`, whose fields are private in pico-sys.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Header {
+  name:      *const u8,
+  name_len:  usize,
+  value:     *const u8,
+  value_len: usize,
+}
+
+const EMPTY_HEADER: Header = Header { name: ptr::null(), name_len: 0, value: ptr::null(), value_len: 0 };
+
+/// A slice pointing into the parsed buffer. pico leaves the name of a folded
+/// header line null, which `slice::from_raw_parts` does not accept.
+unsafe fn bytes<'a>(p: *const u8, len: usize) -> &'a [u8] {
+  if p.is_null() { &[] } else { slice::from_raw_parts(p, len) }
+}
+
+// this version of picohttpparser keeps the trailing whitespace of field
+// values, which is not part of them (RFC 7230 section 3.2)
+fn trim_end(value: &[u8]) -> &[u8] {
+  &value[..value.iter().rposition(|&c| c != b' ' && c != b'\t').map_or(0, |i| i + 1)]
+}
+
+struct Request<'a> {
+  len:         usize,
+  method:      &'a [u8],
+  path:        &'a [u8],
+  version:     u8,
+  num_headers: usize,
+}
+
+fn parse_request<'a>(buf: &'a [u8], headers: &mut [Header]) -> Result<Request<'a>, i32> {
+  let mut method = ptr::null();
+  let mut method_len = 0;
+  let mut path = ptr::null();
+  let mut path_len = 0;
+  let mut minor_version = 0;
+  let mut num_headers = headers.len();
+
+  let ret = unsafe {
+    pico::ffi::phr_parse_request(
+      buf.as_ptr() as *const _,
+      buf.len(),
+      &mut method,
+      &mut method_len,
+      &mut path,
+      &mut path_len,
+      &mut minor_version,
+      headers.as_mut_ptr() as *mut pico::ffi::phr_header,
+      &mut num_headers,
+      0
+    )
+  };
+
+  if ret < 0 {
+    return Err(ret);
+  }
+
+  // on success, pico points method and path into `buf`
+  unsafe {
+    Ok(Request {
+      len:         ret as usize,
+      method:      slice::from_raw_parts(method as *const u8, method_len),
+      path:        slice::from_raw_parts(path as *const u8, path_len),
+      version:     minor_version as u8,
+      num_headers,
+    })
+  }
+}
+
+pub struct PicoHttpParser;
+
+impl ParserUnderTest for PicoHttpParser {
+  type Output = harness::http::Request;
+
+  const NAME: &'static str = "picohttpparser";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let mut headers = [EMPTY_HEADER; 16];
+
+    match parse_request(input, &mut headers) {
+      Ok(req)  => Ok(req.len),
+      Err(ret) => Err(harness::Error(format!("error: ret = {}", ret))),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let mut headers = [EMPTY_HEADER; 16];
+
+    let req = match parse_request(input, &mut headers) {
+      Ok(req)  => req,
+      Err(ret) => return Err(harness::Error(format!("error: ret = {}", ret))),
+    };
+
+    let headers = headers[..req.num_headers].iter().map(|h| unsafe {
+      harness::http::Header::new(bytes(h.name, h.name_len), trim_end(bytes(h.value, h.value_len)))
+    }).collect();
+
+    Ok((req.len, harness::http::Request {
+      method:  req.method.to_vec(),
+      uri:     req.path.to_vec(),
+      version: req.version,
+      headers,
+    }))
+  }
+}
@@ -0,0 +1,206 @@
+//! Differential testing of the JSON parsers.
+//!
+//! Every document is parsed by all the JSON parsers, converted to
+//! `harness::json::Value`, and compared to what `serde_json::Value` makes of
+//! it. The oracle is plain serde_json rather than the `bench-serde-json`
+//! suite, so that the suite is checked like the others.
+
+use std::fmt::Write;
+
+use harness::differential::{self, Contender, Divergence};
+use harness::json::Value;
+use harness::{Error, ParserUnderTest};
+
+/// `serde_json::Value`, used as the reference.
+pub struct Oracle;
+
+impl ParserUnderTest for Oracle {
+    type Output = Value;
+
+    const NAME: &'static str = "serde_json::Value";
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, Error> {
+        self.parse_normalized(input).map(|(consumed, _)| consumed)
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Value), Error> {
+        match serde_json::from_slice(input) {
+            Ok(value) => Ok((input.len(), normalize(value))),
+            Err(err) => Err(Error::new(err)),
+        }
+    }
+}
+
+fn normalize(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap()),
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(v) => Value::Array(v.into_iter().map(normalize).collect()),
+        serde_json::Value::Object(m) => Value::object(m.into_iter().map(|(k, v)| (k, normalize(v)))),
+    }
+}
+
+pub fn contenders() -> Vec<Contender<Value>> {
+    vec![
+        Contender::new(Oracle),
+        Contender::new(bench_serde_json::SerdeJson),
+        Contender::new(nom_json::NomJson),
+        Contender::new(peg_json::PegJson),
+        Contender::new(combine_json::CombineJson),
+        Contender::new(pest_json::PestJson),
+    ]
+}
+
+/// Returns the documents on which a parser disagrees with the oracle.
+///
+/// `document` must hold a single JSON document.
+pub fn check(document: &[u8]) -> Option<Divergence<Value>> {
+    differential::run(document, &mut contenders()).pop()
+}
+
+/// Describes how each parser differs from the oracle. Documents are too
+/// large to be printed whole, so values are located by JSON pointer.
+pub fn report(divergence: &Divergence<Value>) -> String {
+    let mut s = String::new();
+    let mut outcomes = divergence.outcomes.iter();
+    let (oracle_name, oracle) = outcomes.next().unwrap();
+
+    let expected = match oracle {
+        Ok((_, value)) => value,
+        Err(e) => {
+            writeln!(s, "  {} failed: {}", oracle_name, e).unwrap();
+            for (name, outcome) in outcomes {
+                if let Ok((consumed, _)) = outcome {
+                    writeln!(s, "  {} accepted it, consuming {} bytes", name, consumed).unwrap();
+                }
+            }
+            return s;
+        }
+    };
+
+    for (name, outcome) in outcomes {
+        match outcome {
+            Err(e) => writeln!(s, "  {} failed: {}", name, e).unwrap(),
+            Ok((consumed, value)) => {
+                if *consumed != divergence.input.len() {
+                    writeln!(s, "  {} consumed {} of {} bytes", name, consumed, divergence.input.len()).unwrap();
+                }
+                if let Some(path) = expected.first_difference(value) {
+                    writeln!(
+                        s,
+                        "  {} differs at \"{}\": expected {}, got {}",
+                        name,
+                        path,
+                        excerpt(pointer(expected, &path)),
+                        excerpt(pointer(value, &path))
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    s
+}
+
+/// Follows a pointer built by `Value::first_difference`.
+fn pointer<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('/').skip(1).try_fold(value, |value, token| match value {
+        Value::Array(v) => token.parse().ok().and_then(|i: usize| v.get(i)),
+        Value::Object(m) => m.iter().find(|(k, _)| k == token).map(|(_, v)| v),
+        _ => None,
+    })
+}
+
+fn excerpt(value: Option<&Value>) -> String {
+    const MAX: usize = 80;
+
+    match value {
+        None => "nothing".to_string(),
+        Some(value) => {
+            let s = value.to_string();
+            match s.char_indices().nth(MAX) {
+                Some((i, _)) => format!("{}...", &s[..i]),
+                None => s,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use harness::fixtures::json;
+
+    fn assert_agree(document: &[u8]) {
+        if let Some(d) = check(document) {
+            panic!("parsers disagree:\n{}", report(&d));
+        }
+    }
+
+    #[test]
+    fn basic() {
+        assert_agree(json::BASIC);
+    }
+
+    #[test]
+    fn data() {
+        assert_agree(json::DATA);
+    }
+
+    #[test]
+    fn apache() {
+        assert_agree(json::APACHE);
+    }
+
+    #[test]
+    fn canada() {
+        assert_agree(json::CANADA);
+    }
+
+    #[test]
+    fn test() {
+        assert_agree(json::TEST);
+    }
+
+    fn failing(document: &[u8]) -> Vec<&'static str> {
+        let d = check(document).expect("no divergence");
+        d.outcomes.iter().filter(|(_, o)| o.is_err()).map(|(name, _)| *name).collect()
+    }
+
+    // known gaps in the parsers, reported instead of being silently
+    // benchmarked as if they were complete
+
+    #[test]
+    fn null() {
+        assert_eq!(failing(b"[null]"), vec!["nom", "peg"]);
+    }
+
+    #[test]
+    fn multi_digit_exponent() {
+        assert_agree(b"[1e10]");
+        assert_agree(b"[-2.5E+12, 3e-07]");
+    }
+
+    #[test]
+    fn malformed_exponent() {
+        // peg used to accept a `+` among the exponent digits, and panic on
+        // the number it could not convert
+        for document in [&b"[1e++]"[..], b"[1e-+]"] {
+            assert_eq!(failing(document), vec!["serde_json::Value", "serde_json", "nom", "peg", "combine", "pest"]);
+        }
+    }
+
+    #[test]
+    fn escaped_key() {
+        // bench-serde-json borrows object keys from the input
+        assert_eq!(failing(br#"{"a\"b": 1}"#), vec!["serde_json"]);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert_agree(br#"{"a": 1, "b": [], "a": 2}"#);
+    }
+}
@@ -0,0 +1,39 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use harness::fixtures::json;
+
+fn main() {
+    let documents: Vec<(String, Vec<u8>)> = if env::args().len() > 1 {
+        env::args()
+            .skip(1)
+            .map(|path| {
+                let data = fs::read(&path).unwrap_or_else(|e| panic!("could not read {}: {}", path, e));
+                (path, data)
+            })
+            .collect()
+    } else {
+        vec![
+            ("data.json".to_string(), json::DATA.to_vec()),
+            ("canada.json".to_string(), json::CANADA.to_vec()),
+            ("apache_builds.json".to_string(), json::APACHE.to_vec()),
+            ("test.json".to_string(), json::TEST.to_vec()),
+        ]
+    };
+
+    let mut failed = false;
+    for (name, document) in &documents {
+        match json_differential::check(document) {
+            None => println!("{}: ok", name),
+            Some(d) => {
+                println!("{}: parsers disagree\n{}", name, json_differential::report(&d));
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
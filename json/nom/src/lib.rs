@@ -0,0 +1,191 @@
+#[macro_use]
+extern crate nom;
+
+extern crate fnv;
+extern crate harness;
+
+use fnv::FnvHashMap as HashMap;
+use harness::ParserUnderTest;
+use nom::{HexDisplay, recognize_float};
+
+use std::str;
+
+pub fn is_string_character(c: u8) -> bool {
+  //FIXME: should validate unicode character
+  c != b'"' && c != b'\\'
+}
+
+pub fn is_space(c: u8) -> bool {
+  c == b' ' || c == b'\t' || c == b'\r' || c == b'\n'
+}
+
+named!(sp, take_while!(is_space));
+
+#[derive(Debug, PartialEq)]
+pub enum JsonValue<'a> {
+  Str(&'a str),
+  Boolean(bool),
+  Num(f64),
+  Array(Vec<JsonValue<'a>>),
+  Object(HashMap<&'a str, JsonValue<'a>>),
+}
+
+named!(float<f64>, flat_map!(recognize_float, parse_to!(f64)));
+
+//FIXME: handle the cases like \u1234
+named!(
+  string<&str>,
+  delimited!(
+    char!('\"'),
+    map_res!(
+      escaped!(take_while1!(is_string_character), '\\', one_of!("\"bfnrt\\")),
+      str::from_utf8
+    ),
+    char!('\"')
+  )
+);
+
+named!(
+  boolean<bool>,
+  alt!(value!(false, tag!("false")) | value!(true, tag!("true")))
+);
+
+named!(
+  array<Vec<JsonValue>>,
+  delimited!(
+    char!('['),
+    return_error!(separated_list!(preceded!(sp, char!(',')), value)),
+    preceded!(sp, char!(']'))
+  )
+);
+
+named!(
+  key_value<(&str, JsonValue)>,
+  separated_pair!(ws!(string), char!(':'), value)
+);
+
+/*
+named!(
+  hash<HashMap<&str, JsonValue>>,
+  map!(
+    delimited!(
+      char!('{'),
+      return_error!(separated_list!(preceded!(sp, char!(',')), key_value)),
+      preceded!(sp, char!('}'))
+    ),
+    |tuple_vec| tuple_vec
+      .into_iter()
+      .collect()
+  )
+);
+*/
+
+fn hash_internal(input: &[u8]) -> nom::IResult<&[u8], HashMap<&str, JsonValue<'_>>> {
+  match key_value(input) {
+    Err(nom::Err::Error(_)) => Ok((input, HashMap::default())),
+    Err(e) => Err(e),
+    Ok((i, (key, value))) => {
+      let mut map = HashMap::default();
+      map.insert(key, value);
+
+      let mut input = i;
+      loop {
+        match do_parse!(input, sp >> char!(',') >> kv: key_value >> (kv)) {
+          Err(nom::Err::Error(_)) => break Ok((input, map)),
+          Err(e) => break Err(e),
+          Ok((i, (key, value))) => {
+            map.insert(key, value);
+            input = i;
+          }
+        }
+      }
+    }
+  }
+
+}
+
+named!(
+  hash<HashMap<&str, JsonValue>>,
+    delimited!(
+      char!('{'),
+      return_error!(
+        hash_internal
+      ),
+      preceded!(sp, char!('}'))
+    )
+);
+
+named!(
+  value<JsonValue>,
+  preceded!(sp, alt!(
+    map!(string, JsonValue::Str)  |
+    map!(float, JsonValue::Num)   |
+    map!(array, JsonValue::Array) |
+    map!(hash, JsonValue::Object) |
+    map!(boolean, JsonValue::Boolean)
+  ))
+);
+
+named!(
+  pub root<JsonValue>,
+  delimited!(
+    call!(sp),
+    alt!(
+      map!(hash, JsonValue::Object) |
+      map!(array, JsonValue::Array)
+    ),
+    not!(complete!(sp))
+  )
+);
+
+fn normalize(value: JsonValue) -> harness::json::Value {
+  match value {
+    JsonValue::Str(s)     => harness::json::Value::String(harness::json::unescape(s)),
+    JsonValue::Boolean(b) => harness::json::Value::Bool(b),
+    JsonValue::Num(n)     => harness::json::Value::Number(n),
+    JsonValue::Array(v)   => harness::json::Value::Array(v.into_iter().map(normalize).collect()),
+    JsonValue::Object(m)  => harness::json::Value::object(m.into_iter().map(|(k, v)| (harness::json::unescape(k), normalize(v)))),
+  }
+}
+
+fn error(err: nom::Err<&[u8]>) -> harness::Error {
+  if let nom::Err::Error(nom::Context::Code(i, ref e)) = err {
+    harness::Error(format!("got err {:?} at:\n{}", e, i.to_hex(16)))
+  } else {
+    harness::Error(format!("got err: {:?}", err))
+  }
+}
+
+pub struct NomJson;
+
+impl ParserUnderTest for NomJson {
+  type Output = harness::json::Value;
+
+  const NAME: &'static str = "nom";
+
+  // `root` only succeeds when nothing but whitespace follows the document,
+  // so a successful parse consumes the whole input
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    match root(input) {
+      Ok(_)    => Ok(input.len()),
+      Err(err) => Err(error(err)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    match root(input) {
+      Ok((_, o)) => Ok((input.len(), normalize(o))),
+      Err(err)   => Err(error(err)),
+    }
+  }
+}
+
+#[test]
+fn test() {
+  let data = harness::fixtures::json::TEST;
+  let (_, o) = NomJson.parse_normalized(data).unwrap();
+  println!("test: {:?}", o);
+  assert_eq!(o, harness::json::Value::object(vec![
+    ("assignedLabels".to_string(), harness::json::Value::Array(vec![harness::json::Value::object(vec![])])),
+  ]));
+}
@@ -0,0 +1,58 @@
+extern crate fnv;
+extern crate harness;
+
+use std::str;
+
+use fnv::FnvHashMap as HashMap;
+use harness::ParserUnderTest;
+
+// generated by rust-peg 0.5, which still emits `...` range patterns
+#[allow(ellipsis_inclusive_range_patterns, clippy::all)]
+mod peg_json {
+    include!(concat!(env!("OUT_DIR"), "/json.rs"));
+}
+
+pub use peg_json::root;
+
+#[derive(Debug, PartialEq)]
+pub enum JsonValue<'a> {
+  Str(&'a str),
+  Num(f64),
+  Boolean(bool),
+  Array(Vec<JsonValue<'a>>),
+  Object(HashMap<&'a str, JsonValue<'a>>),
+}
+
+fn normalize(value: JsonValue) -> harness::json::Value {
+  match value {
+    JsonValue::Str(s)     => harness::json::Value::String(harness::json::unescape(s)),
+    JsonValue::Num(n)     => harness::json::Value::Number(n),
+    JsonValue::Boolean(b) => harness::json::Value::Bool(b),
+    JsonValue::Array(v)   => harness::json::Value::Array(v.into_iter().map(normalize).collect()),
+    JsonValue::Object(m)  => harness::json::Value::object(m.into_iter().map(|(k, v)| (harness::json::unescape(k), normalize(v)))),
+  }
+}
+
+pub struct PegJson;
+
+impl ParserUnderTest for PegJson {
+  type Output = harness::json::Value;
+
+  const NAME: &'static str = "peg";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    let input = str::from_utf8(input).map_err(harness::Error::new)?;
+    match root(input) {
+      Ok(_)    => Ok(input.len()),
+      Err(err) => Err(harness::Error::new(err)),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    let input = str::from_utf8(input).map_err(harness::Error::new)?;
+    match root(input) {
+      Ok(o)    => Ok((input.len(), normalize(o))),
+      Err(err) => Err(harness::Error::new(err)),
+    }
+  }
+}
@@ -0,0 +1,105 @@
+extern crate fnv;
+extern crate harness;
+extern crate pest;
+extern crate pest_grammars;
+
+use std::str;
+
+use harness::ParserUnderTest;
+
+use pest::Parser;
+use pest::Span;
+use pest::iterators::Pair;
+
+use pest_grammars::json::*;
+
+use fnv::FnvHashMap as HashMap;
+
+pub enum Json<'i> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(Span<'i>),
+    Array(Vec<Json<'i>>),
+    Object(HashMap<Span<'i>, Json<'i>>)
+}
+
+pub fn consume(pair: Pair<Rule>) -> Json {
+    fn value(pair: Pair<Rule>) -> Json {
+        let pair = pair.into_inner().next().unwrap();
+
+        match pair.as_rule() {
+            Rule::null => Json::Null,
+            Rule::bool => match pair.as_str() {
+                "false" => Json::Bool(false),
+                "true" => Json::Bool(true),
+                _ => unreachable!()
+            },
+            Rule::number => Json::Number(pair.as_str().parse().unwrap()),
+            Rule::string => Json::String(pair.into_span()),
+            Rule::array => Json::Array(pair.into_inner().map(value).collect()),
+            Rule::object => {
+                let pairs = pair.into_inner().map(|pos| {
+                    let mut pair = pos.into_inner();
+
+                    let key = pair.next().unwrap().into_span();
+                    let value = value(pair.next().unwrap());
+
+                    (key, value)
+                });
+
+                Json::Object(pairs.collect())
+            }
+            _ => unreachable!()
+        }
+    }
+
+    value(pair)
+}
+
+fn normalize(json: Json) -> harness::json::Value {
+    // string spans include the surrounding quotes and escape sequences
+    fn string(span: Span) -> String {
+        let s = span.as_str();
+        harness::json::unescape(&s[1..s.len() - 1])
+    }
+
+    match json {
+        Json::Null => harness::json::Value::Null,
+        Json::Bool(b) => harness::json::Value::Bool(b),
+        Json::Number(n) => harness::json::Value::Number(n),
+        Json::String(s) => harness::json::Value::String(string(s)),
+        Json::Array(v) => harness::json::Value::Array(v.into_iter().map(normalize).collect()),
+        Json::Object(m) => {
+            // spans of the same key at different positions are different
+            // keys of the map, pass them in document order
+            let mut members: Vec<_> = m.into_iter().collect();
+            members.sort_by_key(|(k, _)| k.start());
+            harness::json::Value::object(members.into_iter().map(|(k, v)| (string(k), normalize(v))))
+        }
+    }
+}
+
+pub struct PestJson;
+
+impl ParserUnderTest for PestJson {
+    type Output = harness::json::Value;
+
+    const NAME: &'static str = "pest";
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+        let input = str::from_utf8(input).map_err(harness::Error::new)?;
+        match JsonParser::parse(Rule::json, input) {
+            Ok(_) => Ok(input.len()),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+        let input = str::from_utf8(input).map_err(harness::Error::new)?;
+        match JsonParser::parse(Rule::json, input) {
+            Ok(mut pairs) => Ok((input.len(), normalize(consume(pairs.next().unwrap())))),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+extern crate fnv;
+extern crate harness;
+extern crate serde;
+extern crate serde_json;
+
+use std::borrow::Cow;
+use std::fmt;
+
+use fnv::FnvHashMap as HashMap;
+use harness::ParserUnderTest;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+#[derive(Debug, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Str(Cow<'a, str>),
+    Num(f64),
+    Boolean(bool),
+    Array(Vec<Value<'a>>),
+    Object(HashMap<&'a str, Value<'a>>),
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any JSON value")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Value::Str(Cow::Owned(v.to_owned())))
+            }
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(Value::Str(Cow::Borrowed(v)))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(Value::Num(v as f64))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(Value::Num(v as f64))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(Value::Num(v))
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(Value::Boolean(v))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut array = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    array.push(value);
+                }
+                Ok(Value::Array(array))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut object = HashMap::default();
+                while let Some((key, value)) = map.next_entry()? {
+                    object.insert(key, value);
+                }
+                Ok(Value::Object(object))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+fn normalize(value: Value) -> harness::json::Value {
+    match value {
+        Value::Null => harness::json::Value::Null,
+        Value::Str(s) => harness::json::Value::String(s.into_owned()),
+        Value::Num(n) => harness::json::Value::Number(n),
+        Value::Boolean(b) => harness::json::Value::Bool(b),
+        Value::Array(v) => harness::json::Value::Array(v.into_iter().map(normalize).collect()),
+        Value::Object(m) => harness::json::Value::object(
+            m.into_iter().map(|(k, v)| (k.to_string(), normalize(v))),
+        ),
+    }
+}
+
+pub struct SerdeJson;
+
+impl ParserUnderTest for SerdeJson {
+    type Output = harness::json::Value;
+
+    const NAME: &'static str = "serde_json";
+
+    fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+        match serde_json::from_slice::<Value>(input) {
+            Ok(_) => Ok(input.len()),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+
+    fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+        match serde_json::from_slice::<Value>(input) {
+            Ok(value) => Ok((input.len(), normalize(value))),
+            Err(err) => Err(harness::Error::new(err)),
+        }
+    }
+}
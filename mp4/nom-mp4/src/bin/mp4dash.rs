@@ -0,0 +1,26 @@
+//! Writes the `fragmented.mp4` fixture, as built by `mp4::generate::dash`.
+//! The tests check that the committed file has the same bytes.
+//!
+//! Usage: `mp4dash <file>`
+
+extern crate mp4;
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+  let mut args = env::args().skip(1);
+  let path = match (args.next(), args.next()) {
+    (Some(path), None) => path,
+    _ => {
+      eprintln!("usage: mp4dash <file>");
+      process::exit(2);
+    },
+  };
+
+  if let Err(e) = fs::write(&path, mp4::generate::dash()) {
+    eprintln!("could not write {}: {}", path, e);
+    process::exit(2);
+  }
+}
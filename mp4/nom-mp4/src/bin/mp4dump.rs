@@ -0,0 +1,66 @@
+//! Prints the box tree of an MP4 file, as seen by the parser the suite
+//! benchmarks: the offset, size and type of every box, the decoded fields
+//! of the boxes it knows and the first bytes of the others.
+//!
+//! Usage: `mp4dump [--json] [--validate] <file>`
+//!
+//! `--json` prints the tree as a JSON object instead. `--validate` also
+//! looks for structural violations, such as missing, repeated or misplaced
+//! boxes, or samples outside of the file. Exits with status 1 when the file
+//! does not parse or has violations.
+
+extern crate mp4;
+extern crate serde_json;
+
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+use mp4::dump::Dump;
+
+fn usage() -> ! {
+  eprintln!("usage: mp4dump [--json] [--validate] <file>");
+  process::exit(2);
+}
+
+fn main() {
+  let mut json = false;
+  let mut validate = false;
+  let mut path = None;
+
+  for arg in env::args().skip(1) {
+    match arg.as_str() {
+      "--json"     => json = true,
+      "--validate" => validate = true,
+      _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+      _ => usage(),
+    }
+  }
+  let path = path.unwrap_or_else(|| usage());
+
+  let dump = File::open(&path).and_then(|f| Dump::read(BufReader::new(f))).unwrap_or_else(|e| {
+    eprintln!("could not read {}: {}", path, e);
+    process::exit(2);
+  });
+  let mut tree = dump.parse();
+  if validate {
+    tree.validate();
+  }
+
+  if json {
+    println!("{}", serde_json::to_string_pretty(&tree.json()).unwrap());
+  } else {
+    print!("{}", tree.text());
+    for violation in &tree.violations {
+      println!("error: {}", violation);
+    }
+    if validate && tree.violations.is_empty() {
+      println!("no violations");
+    }
+  }
+
+  if !tree.violations.is_empty() {
+    process::exit(1);
+  }
+}
@@ -0,0 +1,639 @@
+//! Describing the boxes of a file, for the `mp4dump` binary.
+//!
+//! [`Dump::read`] loads the top level boxes of a file through a
+//! [`Reader`], except the `mdat`, and [`Dump::parse`] parses each of them on
+//! its own, so that a box that fails to parse is reported and kept in the
+//! tree undecoded instead of hiding the boxes after it. [`Tree::validate`]
+//! adds the structural violations the parser does not look for: missing,
+//! repeated or misplaced boxes, and samples outside of the file.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use serde_json::{Map, Value};
+
+use super::fragment;
+use super::reader::{Entry, LoadedBox, ReadError, Reader};
+use super::sample;
+use super::{BoxError, FourCC, MP4Box, Node};
+
+/// Bytes of the payload shown for the boxes that are not decoded.
+pub const PREVIEW: usize = 16;
+
+/// Something wrong with the file, found at `offset`.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Violation {
+  pub offset:  usize,
+  pub message: String,
+}
+
+impl Violation {
+  fn new(offset: usize, message: String) -> Violation {
+    Violation { offset, message }
+  }
+}
+
+impl From<BoxError> for Violation {
+  fn from(e: BoxError) -> Violation {
+    Violation::new(e.offset(), e.to_string())
+  }
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.message.fmt(f)
+  }
+}
+
+/// A top level box, loaded unless it is an `mdat`.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TopBox {
+  pub entry:  Entry,
+  pub loaded: Option<LoadedBox>,
+}
+
+/// The top level boxes of a file.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Dump {
+  pub length: u64,
+  pub boxes:  Vec<TopBox>,
+  /// Why the boxes after the last one could not be read.
+  pub error:  Option<BoxError>,
+}
+
+impl Dump {
+  /// Reads the top level boxes of `source` up to the end of the file, or
+  /// up to the first one whose header is invalid.
+  pub fn read<R: Read + Seek>(source: R) -> io::Result<Dump> {
+    let mut reader = Reader::new(source)?;
+    let mut boxes = Vec::new();
+    let error = loop {
+      let entry = match reader.next_box() {
+        Ok(Some(entry)) => entry,
+        Ok(None)        => break None,
+        Err(e)          => match e {
+          ReadError::Io(e)  => return Err(e),
+          ReadError::Box(e) => break Some(e),
+        },
+      };
+      let loaded = if entry.header.fourcc.0 == *b"mdat" {
+        None
+      } else {
+        // `next_box` checked the size, only reading can fail
+        Some(reader.load(&entry).map_err(|e| match e {
+          ReadError::Io(e)  => e,
+          ReadError::Box(e) => io::Error::new(io::ErrorKind::InvalidData, e),
+        })?)
+      };
+      boxes.push(TopBox { entry, loaded });
+    };
+    let length = reader.into_inner().seek(SeekFrom::End(0))?;
+    Ok(Dump { length, boxes, error })
+  }
+
+  /// Parses the boxes read. A box that does not parse is kept in the tree
+  /// as an unknown box, with a violation.
+  pub fn parse(&self) -> Tree<'_> {
+    let mut boxes = Vec::new();
+    let mut violations = Vec::new();
+    for top in &self.boxes {
+      let entry = &top.entry;
+      let node = match top.loaded {
+        None => Node {
+          uuid: entry.header.uuid,
+          offset: entry.offset as usize,
+          size: entry.size as usize,
+          ..Node::new(entry.header.fourcc, MP4Box::Mdat, &[], Vec::new())
+        },
+        Some(ref loaded) => loaded.node().unwrap_or_else(|e| {
+          violations.push(e.into());
+          Node {
+            uuid: entry.header.uuid,
+            offset: entry.offset as usize,
+            size: entry.size as usize,
+            ..Node::new(entry.header.fourcc, MP4Box::Unknown(entry.header.fourcc), &loaded.data[entry.header.length..], Vec::new())
+          }
+        }),
+      };
+      boxes.push(node);
+    }
+    if let Some(e) = self.error {
+      violations.push(e.into());
+    }
+    Tree { length: self.length, boxes, violations }
+  }
+}
+
+/// The parsed boxes of a file, and what is wrong with them.
+#[derive(PartialEq,Eq,Debug)]
+pub struct Tree<'a> {
+  pub length:     u64,
+  pub boxes:      Vec<Node<'a>>,
+  pub violations: Vec<Violation>,
+}
+
+/// Boxes that must be in a container, with the alternatives allowed.
+const REQUIRED: &[(&[u8; 4], &[&[&[u8; 4]]])] = &[
+  (b"moov", &[&[b"mvhd"]]),
+  (b"trak", &[&[b"tkhd"], &[b"mdia"]]),
+  (b"mdia", &[&[b"mdhd"], &[b"hdlr"], &[b"minf"]]),
+  (b"minf", &[&[b"stbl"]]),
+  (b"stbl", &[&[b"stsd"], &[b"stts"], &[b"stsc"], &[b"stsz", b"stz2"], &[b"stco", b"co64"]]),
+  (b"mvex", &[&[b"trex"]]),
+  (b"moof", &[&[b"mfhd"]]),
+  (b"traf", &[&[b"tfhd"]]),
+  (b"mfra", &[&[b"mfro"]]),
+];
+
+/// Boxes that can only be found once in their container.
+const UNIQUE: &[&[u8; 4]] = &[
+  b"ftyp", b"moov", b"mvhd", b"mvex", b"tkhd", b"mdia", b"mdhd", b"hdlr", b"minf",
+  b"stbl", b"stsd", b"stts", b"ctts", b"stsc", b"stsz", b"stz2", b"stco", b"co64",
+  b"stss", b"mfhd", b"tfhd", b"tfdt", b"mfra", b"mfro",
+];
+
+/// Where the decoded boxes belong, `None` for the top level. `hdlr` is left
+/// out, QuickTime also writes it in `minf`.
+const PARENTS: &[(&[u8; 4], Option<&[u8; 4]>)] = &[
+  (b"ftyp", None), (b"moov", None), (b"moof", None), (b"mfra", None),
+  (b"sidx", None), (b"mdat", None),
+  (b"mvhd", Some(b"moov")), (b"trak", Some(b"moov")), (b"mvex", Some(b"moov")),
+  (b"tkhd", Some(b"trak")), (b"mdia", Some(b"trak")),
+  (b"mdhd", Some(b"mdia")), (b"minf", Some(b"mdia")),
+  (b"stbl", Some(b"minf")),
+  (b"stsd", Some(b"stbl")), (b"stts", Some(b"stbl")), (b"ctts", Some(b"stbl")),
+  (b"stsc", Some(b"stbl")), (b"stsz", Some(b"stbl")), (b"stz2", Some(b"stbl")),
+  (b"stco", Some(b"stbl")), (b"co64", Some(b"stbl")), (b"stss", Some(b"stbl")),
+  (b"trex", Some(b"mvex")),
+  (b"mfhd", Some(b"moof")), (b"traf", Some(b"moof")),
+  (b"tfhd", Some(b"traf")), (b"tfdt", Some(b"traf")), (b"trun", Some(b"traf")),
+  (b"tfra", Some(b"mfra")), (b"mfro", Some(b"mfra")),
+];
+
+impl<'a> Tree<'a> {
+  /// Adds the structural violations of the tree. The sample tables and
+  /// fragments are only resolved when nothing else is wrong, so that they
+  /// find the boxes they need.
+  pub fn validate(&mut self) {
+    let mut violations = Vec::new();
+    siblings(&self.boxes, None, &mut violations);
+    for top in &self.boxes {
+      for (_, node) in top.walk() {
+        children(node, &mut violations);
+      }
+    }
+    order(&self.boxes, &mut violations);
+    self.violations.extend(violations);
+
+    if self.violations.is_empty() {
+      self.samples();
+    }
+  }
+
+  /// Checks that the samples can be resolved and are in the file.
+  fn samples(&mut self) {
+    let mut tracks = Vec::new();
+    if let Some(moov) = self.boxes.iter().find(|b| b.content == MP4Box::Moov) {
+      match sample::index(&self.boxes) {
+        Ok(index) => tracks.extend(index.into_iter().map(|t| (t.track_id, t.samples, moov.offset))),
+        Err(e)    => self.violations.push(e.into()),
+      }
+    }
+    match fragment::fragments(&self.boxes) {
+      Ok(fragments) => for f in fragments {
+        let offset = f.offset;
+        tracks.extend(f.tracks.into_iter().map(|t| (t.track_id, t.samples, offset)));
+      },
+      Err(e) => self.violations.push(e.into()),
+    }
+
+    for (track_id, samples, offset) in tracks {
+      // reported once per track and box
+      if let Some((n, s)) = samples.iter().enumerate().find(|&(_, s)| s.offset + s.size as u64 > self.length) {
+        self.violations.push(Violation::new(offset, format!(
+          "sample {} of track {} described at {} is at {}, past the end of the file ({} bytes)",
+          n + 1, track_id, offset, s.offset, self.length)));
+      }
+    }
+  }
+
+  /// The tree as indented lines: offset, size, type and decoded fields.
+  pub fn text(&self) -> String {
+    let mut out = String::new();
+    for top in &self.boxes {
+      for (depth, node) in top.walk() {
+        out.push_str(&format!("{:>10} {:>10} {}{}", node.offset, node.size, "  ".repeat(depth), node.fourcc));
+        for (name, value) in fields(node) {
+          out.push_str(&format!(" {}={}", name, text(&value)));
+        }
+        out.push('\n');
+      }
+    }
+    out
+  }
+
+  /// The tree and its violations as a JSON object.
+  pub fn json(&self) -> Value {
+    let mut tree = Map::new();
+    tree.insert("length".into(), self.length.into());
+    tree.insert("boxes".into(), Value::Array(self.boxes.iter().map(json).collect()));
+    tree.insert("violations".into(), Value::Array(self.violations.iter().map(|v| {
+      let mut violation = Map::new();
+      violation.insert("offset".into(), v.offset.into());
+      violation.insert("message".into(), v.message.clone().into());
+      Value::Object(violation)
+    }).collect()));
+    Value::Object(tree)
+  }
+}
+
+fn json(node: &Node) -> Value {
+  let mut object = Map::new();
+  object.insert("offset".into(), node.offset.into());
+  object.insert("size".into(), node.size.into());
+  object.insert("type".into(), node.fourcc.to_string().into());
+  object.insert("fields".into(), Value::Object(fields(node).into_iter().map(|(name, value)| (name.to_string(), value)).collect()));
+  if !node.children.is_empty() {
+    object.insert("children".into(), Value::Array(node.children.iter().map(json).collect()));
+  }
+  Value::Object(object)
+}
+
+fn text(value: &Value) -> String {
+  match *value {
+    Value::String(ref s) => s.clone(),
+    Value::Array(ref a)  => a.iter().map(text).collect::<Vec<_>>().join(","),
+    ref v                => v.to_string(),
+  }
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Checks that the boxes of a container, or the top level ones, are not
+/// repeated or misplaced.
+fn siblings(nodes: &[Node], parent: Option<&Node>, violations: &mut Vec<Violation>) {
+  let where_ = || match parent {
+    Some(p) => format!("the {} box at {}", p.fourcc, p.offset),
+    None    => "the file".to_string(),
+  };
+  for (i, node) in nodes.iter().enumerate() {
+    let fourcc = &node.fourcc.0;
+    if UNIQUE.contains(&fourcc) && nodes[..i].iter().any(|n| n.fourcc == node.fourcc) {
+      violations.push(Violation::new(node.offset, format!(
+        "{} box at {} is repeated in {}", node.fourcc, node.offset, where_())));
+    }
+    if let Some(&(_, expected)) = PARENTS.iter().find(|&&(child, _)| child == fourcc) {
+      if expected != parent.map(|p| &p.fourcc.0) {
+        let expected = match expected {
+          Some(e) => format!("in a {} box", FourCC(*e)),
+          None    => "at the top level".to_string(),
+        };
+        violations.push(Violation::new(node.offset, format!(
+          "{} box at {} should be {}, not in {}", node.fourcc, node.offset, expected, where_())));
+      }
+    }
+  }
+}
+
+/// Checks the children of a container.
+fn children(node: &Node, violations: &mut Vec<Violation>) {
+  // a box that failed to parse is unknown, whatever its type
+  if !node.content.is_container() {
+    return;
+  }
+  siblings(&node.children, Some(node), violations);
+  if let Some(&(_, required)) = REQUIRED.iter().find(|&&(parent, _)| node.fourcc.0 == *parent) {
+    for alternatives in required {
+      if !node.children.iter().any(|c| alternatives.contains(&&c.fourcc.0)) {
+        violations.push(BoxError::MissingBox { offset: node.offset, fourcc: FourCC(*alternatives[0]) }.into());
+      }
+    }
+  }
+}
+
+/// Checks the top level boxes against each other.
+fn order(boxes: &[Node], violations: &mut Vec<Violation>) {
+  let first = |fourcc: &[u8; 4]| boxes.iter().find(|b| b.fourcc.0 == *fourcc);
+
+  if let Some(ftyp) = first(b"ftyp") {
+    if let Some(b) = boxes.iter().find(|b| b.offset < ftyp.offset && b.fourcc.0 != *b"free" && b.fourcc.0 != *b"skip") {
+      violations.push(Violation::new(ftyp.offset, format!("ftyp box at {} comes after the {} box at {}", ftyp.offset, b.fourcc, b.offset)));
+    }
+  }
+
+  let moof = first(b"moof");
+  match first(b"moov") {
+    None => if moof.is_some() || first(b"mdat").is_some() {
+      violations.push(Violation::new(0, "no moov box in the file".to_string()));
+    },
+    Some(moov) => if let Some(moof) = moof {
+      if moov.offset > moof.offset {
+        violations.push(Violation::new(moof.offset, format!("moof box at {} comes before the moov box at {}", moof.offset, moov.offset)));
+      }
+      if !moov.children.iter().any(|c| c.fourcc.0 == *b"mvex") {
+        violations.push(BoxError::MissingBox { offset: moov.offset, fourcc: FourCC(*b"mvex") }.into());
+      }
+    },
+  }
+
+  let mut sequence = None;
+  for moof in boxes.iter().filter(|b| b.content == MP4Box::Moof) {
+    if let Some(&MP4Box::Mfhd(n)) = moof.children.iter().map(|c| &c.content).find(|c| matches!(c, MP4Box::Mfhd(_))) {
+      if sequence.is_some_and(|s| n <= s) {
+        violations.push(Violation::new(moof.offset, format!(
+          "moof box at {} has sequence number {}, not above the previous {}", moof.offset, n, sequence.unwrap())));
+      }
+      sequence = Some(n);
+    }
+  }
+
+  if let Some(mfra) = boxes.last().filter(|b| b.content == MP4Box::Mfra) {
+    if let Some(&MP4Box::Mfro(size)) = mfra.children.last().map(|c| &c.content) {
+      if size as usize != mfra.size {
+        violations.push(Violation::new(mfra.offset, format!("mfra box at {} has size {}, but its mfro gives {}", mfra.offset, mfra.size, size)));
+      }
+    }
+  }
+}
+
+/// Decoded fields of a box, and a preview of the payload of the boxes that
+/// are not decoded.
+pub fn fields(node: &Node) -> Vec<(&'static str, Value)> {
+  let mut fields: Vec<(&'static str, Value)> = Vec::new();
+  if let Some(uuid) = node.uuid {
+    fields.push(("uuid", hex(&uuid).into()));
+  }
+  match node.content {
+    MP4Box::Ftyp(ref f) => {
+      fields.push(("major_brand", f.major_brand.into()));
+      fields.push(("minor_version", hex(f.major_brand_version).into()));
+      fields.push(("compatible_brands", f.compatible_brands.clone().into()));
+    }
+    MP4Box::Mvhd(ref h) => {
+      fields.push(("version", h.version.into()));
+      fields.push(("creation_time", h.creation_time.into()));
+      fields.push(("modification_time", h.modification_time.into()));
+      fields.push(("timescale", h.timescale.into()));
+      fields.push(("duration", h.duration.into()));
+      fields.push(("rate", h.rate.to_f64().into()));
+      fields.push(("volume", h.volume.to_f64().into()));
+      fields.push(("next_track_id", h.next_track_id.into()));
+    }
+    MP4Box::Tkhd(ref h) => {
+      fields.push(("version", h.version.into()));
+      fields.push(("flags", h.flags.into()));
+      fields.push(("creation_time", h.creation_time.into()));
+      fields.push(("modification_time", h.modification_time.into()));
+      fields.push(("track_id", h.track_id.into()));
+      fields.push(("duration", h.duration.into()));
+      fields.push(("layer", h.layer.into()));
+      fields.push(("alternate_group", h.alternate_group.into()));
+      fields.push(("volume", h.volume.to_f64().into()));
+      fields.push(("width", h.width.to_f64().into()));
+      fields.push(("height", h.height.to_f64().into()));
+    }
+    MP4Box::Mdhd(ref h) => {
+      fields.push(("version", h.version.into()));
+      fields.push(("creation_time", h.creation_time.into()));
+      fields.push(("modification_time", h.modification_time.into()));
+      fields.push(("timescale", h.timescale.into()));
+      fields.push(("duration", h.duration.into()));
+      fields.push(("language", h.language().into()));
+    }
+    MP4Box::Hdlr(ref h) => {
+      fields.push(("handler_type", h.handler_type.to_string().into()));
+      fields.push(("name", String::from_utf8_lossy(h.name).into_owned().into()));
+    }
+    MP4Box::Stsd(ref entries) => {
+      fields.push(("entries", entries.len().into()));
+      fields.push(("formats", entries.iter().map(|e| e.format.to_string()).collect::<Vec<_>>().into()));
+    }
+    MP4Box::Stts(ref entries) => {
+      fields.push(("entries", entries.len().into()));
+      fields.push(("samples", entries.iter().map(|e| e.count as u64).sum::<u64>().into()));
+    }
+    MP4Box::Ctts(ref entries) => {
+      fields.push(("entries", entries.len().into()));
+      fields.push(("samples", entries.iter().map(|e| e.count as u64).sum::<u64>().into()));
+    }
+    MP4Box::Stsc(ref entries) => fields.push(("entries", entries.len().into())),
+    MP4Box::Stsz(ref s) | MP4Box::Stz2(ref s) => {
+      fields.push(("sample_size", s.constant.into()));
+      fields.push(("sample_count", s.count.into()));
+    }
+    MP4Box::Stco(ref offsets) | MP4Box::Co64(ref offsets) => fields.push(("entries", offsets.len().into())),
+    MP4Box::Stss(ref samples) => fields.push(("entries", samples.len().into())),
+    MP4Box::Trex(ref t) => {
+      fields.push(("track_id", t.track_id.into()));
+      fields.push(("sample_description_index", t.sample_description_index.into()));
+      fields.push(("sample_duration", t.sample_duration.into()));
+      fields.push(("sample_size", t.sample_size.into()));
+      fields.push(("sample_flags", t.sample_flags.into()));
+    }
+    MP4Box::Mfhd(n) => fields.push(("sequence_number", n.into())),
+    MP4Box::Tfhd(ref t) => {
+      fields.push(("track_id", t.track_id.into()));
+      let optional = [
+        ("base_data_offset", t.base_data_offset),
+        ("sample_description_index", t.sample_description_index.map(u64::from)),
+        ("default_sample_duration", t.default_sample_duration.map(u64::from)),
+        ("default_sample_size", t.default_sample_size.map(u64::from)),
+        ("default_sample_flags", t.default_sample_flags.map(u64::from)),
+      ];
+      fields.extend(optional.iter().filter_map(|&(name, v)| v.map(|v| (name, v.into()))));
+      fields.push(("duration_is_empty", t.duration_is_empty.into()));
+      fields.push(("default_base_is_moof", t.default_base_is_moof.into()));
+    }
+    MP4Box::Tfdt(t) => {
+      fields.push(("version", t.version.into()));
+      fields.push(("base_media_decode_time", t.time.into()));
+    }
+    MP4Box::Trun(ref t) => {
+      if let Some(offset) = t.data_offset {
+        fields.push(("data_offset", offset.into()));
+      }
+      if let Some(flags) = t.first_sample_flags {
+        fields.push(("first_sample_flags", flags.into()));
+      }
+      fields.push(("sample_count", t.sample_count.into()));
+    }
+    MP4Box::Sidx(ref s) => {
+      fields.push(("version", s.version.into()));
+      fields.push(("reference_id", s.reference_id.into()));
+      fields.push(("timescale", s.timescale.into()));
+      fields.push(("earliest_presentation_time", s.earliest_presentation_time.into()));
+      fields.push(("first_offset", s.first_offset.into()));
+      fields.push(("references", s.references.len().into()));
+    }
+    MP4Box::Tfra(ref t) => {
+      fields.push(("version", t.version.into()));
+      fields.push(("track_id", t.track_id.into()));
+      fields.push(("entries", t.entries.len().into()));
+    }
+    MP4Box::Mfro(size) => fields.push(("size", size.into())),
+    MP4Box::Unknown(_) => {
+      let preview = &node.payload[..node.payload.len().min(PREVIEW)];
+      let more = if node.payload.len() > PREVIEW { ".." } else { "" };
+      fields.push(("preview", format!("{}{}", hex(preview), more).into()));
+    }
+    _ => {}
+  }
+  fields
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::generate::{generate, Config};
+  use std::io::Cursor;
+
+  fn violations(input: &[u8]) -> Vec<String> {
+    let dump = Dump::read(Cursor::new(input)).unwrap();
+    let mut tree = dump.parse();
+    tree.validate();
+    tree.violations.iter().map(|v| v.to_string()).collect()
+  }
+
+  fn leaf(fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut b = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    b.extend_from_slice(fourcc);
+    b.extend_from_slice(payload);
+    b
+  }
+
+  fn container(fourcc: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+    leaf(fourcc, &children.concat())
+  }
+
+  #[test]
+  fn fixtures() {
+    let fixtures: [&[u8]; 4] = [
+      harness::fixtures::mp4::SMALL,
+      harness::fixtures::mp4::FRAGMENTED,
+      &generate(&Config::progressive(1 << 16)),
+      &generate(&Config::fragmented(1 << 16, 4)),
+    ];
+    for input in fixtures.iter() {
+      assert_eq!(violations(input), Vec::<String>::new());
+    }
+
+    let dump = Dump::read(Cursor::new(harness::fixtures::mp4::SMALL)).unwrap();
+    assert_eq!(dump.length, harness::fixtures::mp4::SMALL.len() as u64);
+    // the mdat is not read
+    assert!(dump.boxes.iter().all(|b| b.loaded.is_none() == (b.entry.header.fourcc.0 == *b"mdat")));
+    let tree = dump.parse();
+    let text = tree.text();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), tree.boxes.iter().map(|b| b.walk().len()).sum::<usize>());
+    assert!(lines[0].trim_start().starts_with("0         28 ftyp major_brand=mp42 minor_version=00000000 compatible_brands=mp42,isom,avc1"), "{}", lines[0]);
+    assert!(text.contains("    380040       3459 moov\n"));
+    assert!(text.contains("    380320         33       hdlr handler_type=vide name=\n"));
+    assert!(text.contains(" timescale=90000 duration=501120 rate=1.0 volume=1.0 next_track_id=3\n"));
+
+    let json = tree.json();
+    let boxes = json["boxes"].as_array().unwrap();
+    assert_eq!(boxes.len(), tree.boxes.len());
+    assert_eq!((&boxes[0]["type"], &boxes[0]["offset"], &boxes[0]["size"]), (&"ftyp".into(), &0.into(), &28.into()));
+    assert_eq!(boxes[0]["fields"]["compatible_brands"][1], "isom");
+    let moov = boxes.iter().find(|b| b["type"] == "moov").unwrap();
+    assert_eq!(moov["children"][0]["fields"]["timescale"], 90000);
+    assert_eq!(json["violations"], Value::Array(Vec::new()));
+  }
+
+  #[test]
+  fn unknown() {
+    let mut input = leaf(b"abcd", &(0..20).collect::<Vec<u8>>());
+    input.extend(leaf(b"efgh", b"\x01\x02"));
+    let dump = Dump::read(Cursor::new(&input[..])).unwrap();
+    assert_eq!(dump.parse().text(), concat!(
+      "         0         28 abcd preview=000102030405060708090a0b0c0d0e0f..\n",
+      "        28         10 efgh preview=0102\n",
+    ));
+
+    let mut uuid = 24u32.to_be_bytes().to_vec();
+    uuid.extend_from_slice(b"uuid");
+    uuid.extend(0..16);
+    let dump = Dump::read(Cursor::new(&uuid[..])).unwrap();
+    assert_eq!(fields(&dump.parse().boxes[0]), vec![
+      ("uuid", "000102030405060708090a0b0c0d0e0f".into()),
+      ("preview", "".into()),
+    ]);
+  }
+
+  #[test]
+  fn invalid() {
+    // a box that does not parse is kept, and the next ones are still read
+    let mut input = leaf(b"ftyp", b"\xff\xff\xff\xff");
+    input.extend(leaf(b"free", b""));
+    input.extend_from_slice(b"\0\0\0\x20moov");
+    let dump = Dump::read(Cursor::new(&input[..])).unwrap();
+    let tree = dump.parse();
+    assert_eq!(tree.boxes.iter().map(|b| b.fourcc.to_string()).collect::<Vec<_>>(), vec!["ftyp", "free"]);
+    assert_eq!(tree.boxes[0].content, MP4Box::Unknown(FourCC(*b"ftyp")));
+    assert_eq!(tree.violations, vec![
+      BoxError::InvalidPayload { offset: 0, fourcc: FourCC(*b"ftyp") }.into(),
+      BoxError::Truncated { offset: 20, size: 32, available: 8 }.into(),
+    ]);
+    assert_eq!(tree.json()["violations"][1]["offset"], 20);
+  }
+
+  #[test]
+  fn structure() {
+    let input = generate(&Config::progressive(1 << 16));
+    let boxes = super::super::full_data_interpreter(&input).unwrap();
+    let moov = boxes.iter().position(|b| b.fourcc.0 == *b"moov").unwrap();
+    let moov_offset = boxes[moov].offset;
+    let mvhd = boxes[moov].children[0].offset - moov_offset;
+    let moov_data = &input[moov_offset..moov_offset + boxes[moov].size];
+
+    // without its mvhd
+    let mvhd_size = boxes[moov].children[0].size;
+    let mut without = moov_data[..mvhd].to_vec();
+    without.extend_from_slice(&moov_data[mvhd + mvhd_size..]);
+    let size = without.len() as u32;
+    without[..4].copy_from_slice(&size.to_be_bytes());
+    let mut file = input[..moov_offset].to_vec();
+    file.extend(without);
+    assert_eq!(violations(&file), vec![format!("no mvhd box in the box at {}", moov_offset)]);
+
+    // repeated, and with an stts at the top level
+    let mut file = input.clone();
+    file.extend_from_slice(moov_data);
+    file.extend(leaf(b"stts", b"\0\0\0\0\0\0\0\0"));
+    assert_eq!(violations(&file), vec![
+      format!("moov box at {} is repeated in the file", input.len()),
+      format!("stts box at {} should be in a stbl box, not in the file", input.len() + moov_data.len()),
+    ]);
+
+    // samples past the end of the file, in the last mdat of a fragmented file
+    let input = generate(&Config::fragmented(1 << 16, 4));
+    let boxes = super::super::full_data_interpreter(&input).unwrap();
+    let mdat = boxes.last().unwrap();
+    assert_eq!(mdat.fourcc, FourCC(*b"mdat"));
+    let mut file = input[..mdat.offset].to_vec();
+    file.extend(leaf(b"mdat", &mdat.payload[..mdat.payload.len() - 100]));
+    let moof = boxes[boxes.len() - 2].offset;
+    let found = violations(&file);
+    assert!(!found.is_empty(), "{:?}", found);
+    for v in found {
+      assert!(v.contains(&format!("described at {} ", moof)) && v.ends_with(&format!("past the end of the file ({} bytes)", file.len())), "{}", v);
+    }
+  }
+
+  #[test]
+  fn fragments() {
+    let mfhd = |n: u32| leaf(b"mfhd", &[&[0, 0, 0, 0][..], &n.to_be_bytes()].concat());
+    let tfhd = || leaf(b"tfhd", &[0, 0x02, 0, 0, 0, 0, 0, 1]);
+    let moof = |n: u32| container(b"moof", &[mfhd(n), container(b"traf", &[tfhd()])]);
+    let mut file = [moof(2), moof(2)].concat();
+    file.extend(container(b"mfra", &[leaf(b"mfro", &[0, 0, 0, 0, 0, 0, 0, 99])]));
+    let second = moof(2).len();
+    assert_eq!(violations(&file), vec![
+      "no moov box in the file".to_string(),
+      format!("moof box at {} has sequence number 2, not above the previous 2", second),
+      format!("mfra box at {} has size 24, but its mfro gives 99", 2 * second),
+    ]);
+  }
+}
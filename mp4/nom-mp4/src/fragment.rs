@@ -0,0 +1,570 @@
+//! Fragmented files, ISO/IEC 14496-12 sections 8.8 and 8.16.3.
+//!
+//! Streaming formats such as DASH and CMAF leave the sample tables of `moov`
+//! empty and describe the samples in movie fragments instead: each `moof`
+//! holds a `traf` per track, whose `trun` boxes list the samples stored in
+//! the following `mdat`. Fields left out of a `trun` take the defaults of
+//! the `tfhd`, then those of the `trex` of the track in `moov/mvex`.
+//! [`fragments`] resolves them into one [`Sample`] per sample.
+
+use nom::{IResult,be_i32,be_u16,be_u32,be_u64};
+
+use super::header::full_box;
+use super::sample::{Sample, MAX_SAMPLES_PER_BYTE};
+use super::{BoxError, MP4Box, Node};
+
+/// Defaults of the samples of a track, from `trex`.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct TrackExtends {
+  pub track_id:                 u32,
+  pub sample_description_index: u32,
+  pub sample_duration:          u32,
+  pub sample_size:              u32,
+  pub sample_flags:             u32,
+}
+
+/// `tfhd`, the optional fields are present when their flag is set.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct TrackFragmentHeader {
+  pub track_id:                 u32,
+  pub base_data_offset:         Option<u64>,
+  pub sample_description_index: Option<u32>,
+  pub default_sample_duration:  Option<u32>,
+  pub default_sample_size:      Option<u32>,
+  pub default_sample_flags:     Option<u32>,
+  /// The fragment has no samples for this track.
+  pub duration_is_empty:        bool,
+  /// Data offsets are relative to the start of the `moof`.
+  pub default_base_is_moof:     bool,
+}
+
+/// A sample of a `trun`, fields left out take their default value.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct RunSample {
+  pub duration:           Option<u32>,
+  pub size:               Option<u32>,
+  pub flags:              Option<u32>,
+  pub composition_offset: Option<i64>,
+}
+
+/// `trun`, a run of contiguous samples.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TrackRun {
+  /// Position of the first sample, from the base data offset.
+  pub data_offset:        Option<i32>,
+  /// Flags of the first sample, replacing the others.
+  pub first_sample_flags: Option<u32>,
+  pub sample_count:       u32,
+  /// The fields of each sample, empty when the run has no per sample field
+  /// and every sample takes the defaults.
+  pub samples:            Vec<RunSample>,
+}
+
+/// `tfdt`, the decode time of the first sample of a track fragment.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct DecodeTime {
+  /// 1 for a 64 bit time.
+  pub version: u8,
+  pub time:    u64,
+}
+
+/// Reference of a `sidx` to a subsegment or to another `sidx`.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct SegmentReference {
+  /// The reference is to a `sidx` rather than to media.
+  pub is_index:            bool,
+  pub size:                u32,
+  pub subsegment_duration: u32,
+  pub starts_with_sap:     bool,
+  pub sap_type:            u8,
+  pub sap_delta_time:      u32,
+}
+
+/// `sidx`, the index of the subsegments of a segment.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct SegmentIndex {
+  /// 1 for 64 bit times and offsets.
+  pub version:                    u8,
+  pub reference_id:               u32,
+  pub timescale:                  u32,
+  pub earliest_presentation_time: u64,
+  /// Distance from the end of the `sidx` to the first subsegment.
+  pub first_offset:               u64,
+  pub references:                 Vec<SegmentReference>,
+}
+
+/// Entry of `tfra`, a sync sample to seek to.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct RandomAccessPoint {
+  pub time:        u64,
+  /// Position of the `moof` holding the sample.
+  pub moof_offset: u64,
+  /// Numbers of the `traf`, `trun` and sample, from 1.
+  pub traf_number: u32,
+  pub trun_number: u32,
+  pub sample:      u32,
+}
+
+/// `tfra`, the random access points of a track.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TrackFragmentRandomAccess {
+  /// 1 for 64 bit times and offsets.
+  pub version:  u8,
+  pub track_id: u32,
+  pub entries:  Vec<RandomAccessPoint>,
+}
+
+/// Checks that `count` entries of at least `size` bytes can be in `input`,
+/// before reserving memory for them.
+fn check_count(input: &[u8], count: u32, size: usize) -> IResult<&[u8], ()> {
+  if count as u64 * size as u64 > input.len() as u64 {
+    Err(nom::Err::Error(nom::Context::Code(input, nom::ErrorKind::Count)))
+  } else {
+    Ok((input, ()))
+  }
+}
+
+fn optional<'a, T, F>(input: &'a [u8], present: bool, f: F) -> IResult<&'a [u8], Option<T>>
+  where F: Fn(&'a [u8]) -> IResult<&'a [u8], T> {
+  if present {
+    let (i, v) = f(input)?;
+    Ok((i, Some(v)))
+  } else {
+    Ok((input, None))
+  }
+}
+
+pub fn track_extends(input:&[u8]) -> IResult<&[u8], TrackExtends> {
+  do_parse!(input,
+                              full_box >>
+    track_id:                 be_u32   >>
+    sample_description_index: be_u32   >>
+    sample_duration:          be_u32   >>
+    sample_size:              be_u32   >>
+    sample_flags:             be_u32   >>
+    (TrackExtends { track_id, sample_description_index, sample_duration, sample_size, sample_flags })
+  )
+}
+
+/// Sequence number of the fragment, from `mfhd`.
+pub fn movie_fragment_header(input:&[u8]) -> IResult<&[u8], u32> {
+  do_parse!(input, full_box >> n: be_u32 >> (n))
+}
+
+pub fn track_fragment_header(input:&[u8]) -> IResult<&[u8], TrackFragmentHeader> {
+  let (i, (_, flags)) = full_box(input)?;
+  let (i, track_id) = be_u32(i)?;
+  let (i, base_data_offset) = optional(i, flags & 0x01 != 0, be_u64)?;
+  let (i, sample_description_index) = optional(i, flags & 0x02 != 0, be_u32)?;
+  let (i, default_sample_duration) = optional(i, flags & 0x08 != 0, be_u32)?;
+  let (i, default_sample_size) = optional(i, flags & 0x10 != 0, be_u32)?;
+  let (i, default_sample_flags) = optional(i, flags & 0x20 != 0, be_u32)?;
+  Ok((i, TrackFragmentHeader {
+    track_id, base_data_offset, sample_description_index,
+    default_sample_duration, default_sample_size, default_sample_flags,
+    duration_is_empty:    flags & 0x010000 != 0,
+    default_base_is_moof: flags & 0x020000 != 0,
+  }))
+}
+
+/// Decode time of the first sample of the fragment, from `tfdt`.
+pub fn track_fragment_decode_time(input:&[u8]) -> IResult<&[u8], DecodeTime> {
+  let (i, (version, _)) = full_box(input)?;
+  let (i, time) = if version == 1 { be_u64(i)? } else { map!(i, be_u32, |t| t as u64)? };
+  Ok((i, DecodeTime { version, time }))
+}
+
+pub fn track_run(input:&[u8]) -> IResult<&[u8], TrackRun> {
+  let (i, (version, flags)) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  let (i, data_offset) = optional(i, flags & 0x01 != 0, be_i32)?;
+  let (i, first_sample_flags) = optional(i, flags & 0x04 != 0, be_u32)?;
+
+  let (duration, size, sample_flags, composition) =
+    (flags & 0x100 != 0, flags & 0x200 != 0, flags & 0x400 != 0, flags & 0x800 != 0);
+  let field_count = [duration, size, sample_flags, composition].iter().filter(|f| **f).count();
+  // without any field, the samples take no room and are not expanded
+  if field_count == 0 {
+    return Ok((i, TrackRun { data_offset, first_sample_flags, sample_count: count, samples: Vec::new() }));
+  }
+  let (i, _) = check_count(i, count, 4 * field_count)?;
+
+  let mut samples = Vec::with_capacity(count as usize);
+  let mut i = i;
+  for _ in 0..count {
+    let (rest, duration) = optional(i, duration, be_u32)?;
+    let (rest, size) = optional(rest, size, be_u32)?;
+    let (rest, flags) = optional(rest, sample_flags, be_u32)?;
+    let (rest, composition_offset) = if version == 0 {
+      optional(rest, composition, |i| map!(i, be_u32, |o| o as i64))?
+    } else {
+      optional(rest, composition, |i| map!(i, be_i32, |o| o as i64))?
+    };
+    samples.push(RunSample { duration, size, flags, composition_offset });
+    i = rest;
+  }
+  Ok((i, TrackRun { data_offset, first_sample_flags, sample_count: count, samples }))
+}
+
+pub fn segment_index(input:&[u8]) -> IResult<&[u8], SegmentIndex> {
+  let (i, (version, _)) = full_box(input)?;
+  let (i, reference_id) = be_u32(i)?;
+  let (i, timescale) = be_u32(i)?;
+  let (i, (earliest_presentation_time, first_offset)) = if version == 0 {
+    do_parse!(i, t: be_u32 >> o: be_u32 >> (t as u64, o as u64))?
+  } else {
+    do_parse!(i, t: be_u64 >> o: be_u64 >> (t, o))?
+  };
+  let (i, _) = be_u16(i)?;
+  let (i, count) = be_u16(i)?;
+  let (i, _) = check_count(i, count as u32, 12)?;
+  let (i, references) = count!(i, do_parse!(
+    size:     be_u32 >>
+    duration: be_u32 >>
+    sap:      be_u32 >>
+    (SegmentReference {
+      is_index:            size >> 31 != 0,
+      size:                size & 0x7fff_ffff,
+      subsegment_duration: duration,
+      starts_with_sap:     sap >> 31 != 0,
+      sap_type:            (sap >> 28 & 0x7) as u8,
+      sap_delta_time:      sap & 0x0fff_ffff,
+    })
+  ), count as usize)?;
+  Ok((i, SegmentIndex { version, reference_id, timescale, earliest_presentation_time, first_offset, references }))
+}
+
+/// Unsigned number of `length` bytes, from 1 to 4.
+fn sized(input:&[u8], length: usize) -> IResult<&[u8], u32> {
+  let (i, bytes) = take!(input, length)?;
+  Ok((i, bytes.iter().fold(0, |n, b| n << 8 | *b as u32)))
+}
+
+pub fn track_fragment_random_access(input:&[u8]) -> IResult<&[u8], TrackFragmentRandomAccess> {
+  let (i, (version, _)) = full_box(input)?;
+  let (i, track_id) = be_u32(i)?;
+  let (i, lengths) = be_u32(i)?;
+  let (traf, trun, sample) = ((lengths >> 4 & 3) as usize + 1, (lengths >> 2 & 3) as usize + 1, (lengths & 3) as usize + 1);
+  let (i, count) = be_u32(i)?;
+  let (mut i, _) = check_count(i, count, if version == 1 { 16 } else { 8 } + traf + trun + sample)?;
+
+  let mut entries = Vec::with_capacity(count as usize);
+  for _ in 0..count {
+    let (rest, (time, moof_offset)) = if version == 1 {
+      do_parse!(i, t: be_u64 >> o: be_u64 >> (t, o))?
+    } else {
+      do_parse!(i, t: be_u32 >> o: be_u32 >> (t as u64, o as u64))?
+    };
+    let (rest, traf_number) = sized(rest, traf)?;
+    let (rest, trun_number) = sized(rest, trun)?;
+    let (rest, sample) = sized(rest, sample)?;
+    entries.push(RandomAccessPoint { time, moof_offset, traf_number, trun_number, sample });
+    i = rest;
+  }
+  Ok((i, TrackFragmentRandomAccess { version, track_id, entries }))
+}
+
+/// Size of the enclosing `mfra`, from `mfro`, to find it from the end of
+/// the file.
+pub fn movie_fragment_random_access_offset(input:&[u8]) -> IResult<&[u8], u32> {
+  do_parse!(input, full_box >> size: be_u32 >> (size))
+}
+
+/// Whether the sample flags mark a sync sample: `sample_is_non_sync_sample`
+/// is not set.
+pub fn is_sync(flags: u32) -> bool {
+  flags & 0x0001_0000 == 0
+}
+
+/// Samples of a track in a fragment.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TrackFragment {
+  pub track_id: u32,
+  pub samples:  Vec<Sample>,
+}
+
+/// A movie fragment, the samples it describes.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Fragment {
+  pub sequence_number: u32,
+  /// Position of the `moof` box.
+  pub offset:          usize,
+  pub tracks:          Vec<TrackFragment>,
+}
+
+fn invalid(node: &Node, reason: &'static str) -> BoxError {
+  BoxError::InvalidFragment { offset: node.offset, reason }
+}
+
+// `box_content` decodes every box of these types, or fails
+macro_rules! decoded (
+  ($node:expr, $variant:path) => (
+    match $node.content {
+      $variant(ref h) => h,
+      _               => unreachable!(),
+    }
+  );
+);
+
+/// A sample of a run without per sample fields.
+const NO_FIELDS: RunSample = RunSample { duration: None, size: None, flags: None, composition_offset: None };
+
+/// Resolves the samples of a `traf` of the `moof` at `moof_offset`. `base`
+/// is where the data of the previous `traf` ends, `decode_time` where the
+/// previous fragment of the track ends. Returns the samples, and where their
+/// data and their time end.
+fn track_fragment(traf: &Node, moof_offset: u64, base: u64, trex: &[&TrackExtends], decode_time: u64)
+  -> Result<(TrackFragment, u64, u64), BoxError> {
+  let tfhd = decoded!(traf.child(b"tfhd")?, MP4Box::Tfhd);
+  let trex = trex.iter().find(|t| t.track_id == tfhd.track_id);
+
+  let base = match tfhd.base_data_offset {
+    Some(offset)                      => offset,
+    None if tfhd.default_base_is_moof => moof_offset,
+    None                              => base,
+  };
+  let mut time = match traf.child(b"tfdt") {
+    Ok(tfdt) => decoded!(tfdt, MP4Box::Tfdt).time,
+    Err(_)   => decode_time,
+  };
+  let description_index = tfhd.sample_description_index.or(trex.map(|t| t.sample_description_index)).unwrap_or(1);
+
+  let mut samples = Vec::new();
+  let mut offset = base;
+  for trun in traf.children.iter().filter(|c| c.fourcc.0 == *b"trun") {
+    let run = decoded!(trun, MP4Box::Trun);
+    // without a data offset, the run follows the previous one
+    if let Some(data_offset) = run.data_offset {
+      offset = base.checked_add_signed(data_offset as i64)
+        .ok_or_else(|| invalid(trun, "data offset before the start of the file"))?;
+    }
+    for n in 0..run.sample_count as usize {
+      let s = run.samples.get(n).unwrap_or(&NO_FIELDS);
+      let duration = s.duration.or(tfhd.default_sample_duration).or(trex.map(|t| t.sample_duration))
+        .ok_or_else(|| invalid(trun, "no sample duration, nor default"))?;
+      let size = s.size.or(tfhd.default_sample_size).or(trex.map(|t| t.sample_size))
+        .ok_or_else(|| invalid(trun, "no sample size, nor default"))?;
+      let first = if n == 0 { run.first_sample_flags } else { None };
+      let flags = first.or(s.flags).or(tfhd.default_sample_flags).or(trex.map(|t| t.sample_flags))
+        .ok_or_else(|| invalid(trun, "no sample flags, nor default"))?;
+
+      samples.push(Sample {
+        offset,
+        size,
+        decode_time: time,
+        composition_offset: s.composition_offset.unwrap_or(0),
+        keyframe: is_sync(flags),
+        description_index,
+      });
+      offset = offset.checked_add(size as u64).ok_or_else(|| invalid(trun, "sample offset overflow"))?;
+      time = time.checked_add(duration as u64).ok_or_else(|| invalid(trun, "decode time overflow"))?;
+    }
+  }
+
+  Ok((TrackFragment { track_id: tfhd.track_id, samples }, offset, time))
+}
+
+/// Resolves the samples of every top level `moof` among `boxes`, with the
+/// defaults of `moov/mvex` when there is one.
+pub fn fragments(boxes: &[Node]) -> Result<Vec<Fragment>, BoxError> {
+  let trex: Vec<&TrackExtends> = boxes.iter()
+    .filter(|b| b.content == MP4Box::Moov)
+    .flat_map(|moov| moov.children.iter().filter(|c| c.content == MP4Box::Mvex))
+    .flat_map(|mvex| mvex.children.iter())
+    .filter_map(|c| match c.content { MP4Box::Trex(ref t) => Some(t), _ => None })
+    .collect();
+
+  // where the last fragment of each track ends, for the fragments without
+  // `tfdt`
+  let mut decode_times: Vec<(u32, u64)> = Vec::new();
+  let mut fragments = Vec::new();
+  for moof in boxes.iter().filter(|b| b.content == MP4Box::Moof) {
+    let sequence_number = *decoded!(moof.child(b"mfhd")?, MP4Box::Mfhd);
+    // a run taking every field from the defaults gives any count in a few
+    // bytes
+    let count: u64 = moof.children.iter()
+      .filter(|c| c.content == MP4Box::Traf)
+      .flat_map(|traf| traf.children.iter().filter(|c| c.fourcc.0 == *b"trun"))
+      .map(|trun| decoded!(trun, MP4Box::Trun).sample_count as u64)
+      .sum();
+    if count > MAX_SAMPLES_PER_BYTE * moof.size as u64 {
+      return Err(invalid(moof, "more samples than the size of the fragment allows"));
+    }
+    let mut base = moof.offset as u64;
+    let mut tracks = Vec::new();
+    for traf in moof.children.iter().filter(|c| c.content == MP4Box::Traf) {
+      let track_id = decoded!(traf.child(b"tfhd")?, MP4Box::Tfhd).track_id;
+      let last = decode_times.iter().position(|t| t.0 == track_id);
+      let decode_time = last.map_or(0, |n| decode_times[n].1);
+
+      let (fragment, end, time) = track_fragment(traf, moof.offset as u64, base, &trex, decode_time)?;
+      base = end;
+      match last {
+        Some(n) => decode_times[n].1 = time,
+        None    => decode_times.push((track_id, time)),
+      }
+      tracks.push(fragment);
+    }
+    fragments.push(Fragment { sequence_number, offset: moof.offset, tracks });
+  }
+
+  Ok(fragments)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::{full_data_interpreter, FourCC};
+  use super::super::tests::leaf;
+
+  #[test]
+  fn fragmented() {
+    let boxes = full_data_interpreter(harness::fixtures::mp4::FRAGMENTED).unwrap();
+    let fragments = fragments(&boxes).unwrap();
+    assert_eq!(fragments.len(), 10);
+    assert_eq!(fragments.iter().map(|f| f.sequence_number).collect::<Vec<_>>(), (1..11).collect::<Vec<_>>());
+    assert_eq!((fragments[0].offset, fragments[1].offset, fragments[2].offset), (1114, 5202, 9674));
+
+    // defaults from trex, first sample flags
+    let video = &fragments[0].tracks[0];
+    assert_eq!((video.track_id, video.samples.len()), (1, 30));
+    let first = Sample { offset: 1590, size: 400, decode_time: 0, composition_offset: 0, keyframe: true, description_index: 1 };
+    assert_eq!(video.samples[0], first);
+    assert_eq!((video.samples[1].offset, video.samples[1].size, video.samples[1].decode_time), (1990, 53, 3000));
+    assert_eq!(video.samples.iter().filter(|s| s.keyframe).count(), 1);
+
+    // every field in the trun, signed composition offsets
+    let video = &fragments[1].tracks[0];
+    assert_eq!((video.samples[0].offset, video.samples[0].decode_time, video.samples[0].composition_offset), (6042, 90000, -3000));
+    assert_eq!((video.samples[1].size, video.samples[1].composition_offset, video.samples[1].keyframe), (60, 3000, false));
+    assert!(video.samples[0].keyframe);
+
+    let audio = &fragments[1].tracks[1];
+    assert_eq!((audio.track_id, audio.samples.len()), (2, 47));
+    assert_eq!((audio.samples[0].offset, audio.samples[0].decode_time), (8480, 47 * 1024));
+    assert_eq!((audio.samples[46].offset, audio.samples[46].size), (9649, 25));
+    assert!(audio.samples.iter().all(|s| s.keyframe));
+
+    // the samples of each fragment fill the following mdat
+    for f in &fragments {
+      let moof = boxes.iter().position(|b| b.offset == f.offset).unwrap();
+      let mdat = &boxes[moof + 1];
+      assert_eq!(mdat.content, MP4Box::Mdat);
+      let mut samples: Vec<_> = f.tracks.iter().flat_map(|t| &t.samples).collect();
+      samples.sort_by_key(|s| s.offset);
+      assert_eq!(samples[0].offset, (mdat.offset + 8) as u64);
+      for w in samples.windows(2) {
+        assert_eq!(w[0].offset + w[0].size as u64, w[1].offset);
+      }
+      let last = samples.last().unwrap();
+      assert_eq!(last.offset + last.size as u64, (mdat.offset + mdat.size) as u64);
+    }
+
+    let sidx = match boxes[2].content {
+      MP4Box::Sidx(ref sidx) => sidx,
+      ref b => panic!("{:?}", b),
+    };
+    assert_eq!((sidx.reference_id, sidx.timescale, sidx.first_offset, sidx.references.len()), (1, 90000, 0, 10));
+    let reference = SegmentReference {
+      is_index: false, size: 468 + 3620, subsegment_duration: 90000, starts_with_sap: true, sap_type: 1, sap_delta_time: 0,
+    };
+    assert_eq!(sidx.references[0], reference);
+
+    let mfra = boxes.last().unwrap();
+    let tfra = match mfra.children[0].content {
+      MP4Box::Tfra(ref tfra) => tfra,
+      ref b => panic!("{:?}", b),
+    };
+    assert_eq!((tfra.track_id, tfra.entries.len()), (1, 10));
+    assert_eq!(tfra.entries[1], RandomAccessPoint { time: 90000, moof_offset: 5202, traf_number: 1, trun_number: 1, sample: 1 });
+    assert_eq!(mfra.children[1].content, MP4Box::Mfro(mfra.size as u32));
+  }
+
+  #[test]
+  fn defaults() {
+    let trun = |data_offset, sizes: &[u32]| Node {
+      fourcc: FourCC(*b"trun"), offset: 50,
+      ..leaf(MP4Box::Trun(TrackRun {
+        data_offset,
+        first_sample_flags: None,
+        sample_count: sizes.len() as u32,
+        samples: sizes.iter().map(|&s| RunSample { duration: None, size: Some(s), flags: None, composition_offset: None }).collect(),
+      }))
+    };
+    let tfhd = TrackFragmentHeader {
+      track_id: 1, base_data_offset: None, sample_description_index: None, default_sample_duration: None,
+      default_sample_size: None, default_sample_flags: None, duration_is_empty: false, default_base_is_moof: false,
+    };
+    let traf = |tfhd, runs: Vec<Node<'static>>| Node {
+      content: MP4Box::Traf,
+      children: vec![Node { fourcc: FourCC(*b"tfhd"), ..leaf(MP4Box::Tfhd(tfhd)) }].into_iter().chain(runs).collect(),
+      ..leaf(MP4Box::Traf)
+    };
+    let moof = |offset, trafs: Vec<Node<'static>>| Node {
+      offset, size: 100, content: MP4Box::Moof,
+      children: vec![Node { fourcc: FourCC(*b"mfhd"), ..leaf(MP4Box::Mfhd(1)) }].into_iter().chain(trafs).collect(),
+      ..leaf(MP4Box::Moof)
+    };
+    let trex = TrackExtends { track_id: 1, sample_description_index: 2, sample_duration: 10, sample_size: 0, sample_flags: 0x10000 };
+    let moov = || Node {
+      content: MP4Box::Moov,
+      children: vec![Node { content: MP4Box::Mvex, children: vec![leaf(MP4Box::Trex(trex))], ..leaf(MP4Box::Mvex) }],
+      ..leaf(MP4Box::Moov)
+    };
+
+    // without data offset nor base, the data follows the moof, then the
+    // previous run; without tfdt, the time follows the previous fragment
+    let boxes = vec![
+      moov(),
+      moof(100, vec![traf(tfhd, vec![trun(None, &[5, 6]), trun(None, &[7])])]),
+      moof(200, vec![traf(TrackFragmentHeader { base_data_offset: Some(1000), ..tfhd }, vec![trun(Some(-10), &[8])])]),
+    ];
+    let f = fragments(&boxes).unwrap();
+    let samples = |n: usize| f[n].tracks[0].samples.iter()
+      .map(|s| (s.offset, s.size, s.decode_time, s.keyframe, s.description_index))
+      .collect::<Vec<_>>();
+    assert_eq!(samples(0), [(100, 5, 0, false, 2), (105, 6, 10, false, 2), (111, 7, 20, false, 2)]);
+    assert_eq!(samples(1), [(990, 8, 30, false, 2)]);
+
+    // without trex, the trun or tfhd must give every field
+    let boxes = vec![moof(100, vec![traf(tfhd, vec![trun(Some(8), &[5])])])];
+    assert_eq!(fragments(&boxes), Err(BoxError::InvalidFragment { offset: 50, reason: "no sample duration, nor default" }));
+    let boxes = vec![moof(0, vec![traf(TrackFragmentHeader { default_sample_duration: Some(1), default_sample_flags: Some(0), ..tfhd }, vec![trun(Some(-8), &[5])])])];
+    assert_eq!(fragments(&boxes), Err(BoxError::InvalidFragment { offset: 50, reason: "data offset before the start of the file" }));
+
+    // runs taking every field from the defaults are bounded by the size of
+    // the moof, 100 bytes here
+    let empty = |sample_count| Node {
+      fourcc: FourCC(*b"trun"), offset: 50,
+      ..leaf(MP4Box::Trun(TrackRun { data_offset: None, first_sample_flags: None, sample_count, samples: Vec::new() }))
+    };
+    let boxes = |count: u32| vec![moov(), moof(100, vec![traf(tfhd, vec![empty(count / 2), empty(count - count / 2)])])];
+    assert_eq!(fragments(&boxes(1600)).unwrap()[0].tracks[0].samples.len(), 1600);
+    let too_many = Err(BoxError::InvalidFragment { offset: 100, reason: "more samples than the size of the fragment allows" });
+    assert_eq!(fragments(&boxes(1601)), too_many);
+    assert_eq!(fragments(&boxes(u32::MAX)), too_many);
+  }
+
+  #[test]
+  fn runs() {
+    // version 1, data offset, first sample flags, duration and signed
+    // composition offset
+    let trun = b"\x01\0\x09\x05\0\0\0\x02\xff\xff\xff\xf0\x02\0\0\0\0\0\0\x01\xff\xff\xff\xfe\0\0\0\x02\0\0\0\x03";
+    let (rest, run) = track_run(trun).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!((run.data_offset, run.first_sample_flags), (Some(-16), Some(0x02000000)));
+    assert_eq!(run.samples[0], RunSample { duration: Some(1), size: None, flags: None, composition_offset: Some(-2) });
+    assert_eq!(run.samples[1].composition_offset, Some(3));
+    // more samples than bytes
+    assert!(track_run(b"\0\0\x02\0\xff\xff\xff\xff\0\0\0\x01").is_err());
+    // samples taking every field from the defaults are not expanded
+    let run = track_run(b"\0\0\0\0\xff\xff\xff\xff").unwrap().1;
+    assert_eq!((run.sample_count, run.samples.len()), (u32::MAX, 0));
+
+    let tfhd = b"\0\x02\0\x3b\0\0\0\x07\0\0\0\0\0\0\x01\0\0\0\0\x02\0\0\0\x03\0\0\0\x04\0\0\0\x05";
+    let h = track_fragment_header(tfhd).unwrap().1;
+    assert_eq!((h.track_id, h.base_data_offset, h.sample_description_index), (7, Some(256), Some(2)));
+    assert_eq!((h.default_sample_duration, h.default_sample_size, h.default_sample_flags), (Some(3), Some(4), Some(5)));
+    assert!(h.default_base_is_moof && !h.duration_is_empty);
+  }
+}
@@ -0,0 +1,629 @@
+//! Synthetic files for the benchmarks and the tests.
+//!
+//! [`generate`] builds a box tree from a [`Config`] and writes it with
+//! [`serialize`], so that fixtures of any size can be made without checking
+//! them in. The same configuration always gives the same bytes. Even tracks
+//! are video, with a sync sample every 30 samples, odd tracks are audio; the
+//! sizes of the samples are drawn around the mean `size` gives, and their
+//! data is filler.
+//!
+//! [`dash`] writes the `fragmented.mp4` fixture in the same way.
+
+use std::convert::TryFrom;
+
+use super::fragment::{
+  DecodeTime, RandomAccessPoint, RunSample, SegmentIndex, SegmentReference, TrackExtends, TrackFragmentHeader,
+  TrackFragmentRandomAccess, TrackRun,
+};
+use super::header::{Fixed16, Fixed8, Handler, MediaHeader, MovieHeader, TrackHeader};
+use super::sample::{SampleEntry, SampleSizes, SampleToChunk, TimeToSample};
+use super::writer::serialize;
+use super::{FileType, FourCC, MP4Box, Node};
+
+/// Where the samples are described.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum Layout {
+  /// In the sample tables of `moov`, which follows a single `mdat` holding
+  /// chunks of `samples_per_chunk` samples of each track in turn.
+  Progressive { samples_per_chunk: u32 },
+  /// In `fragments` pairs of `moof` and `mdat` following the `moov`, each
+  /// holding an equal share of the samples of every track.
+  Fragmented { fragments: u32 },
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Config {
+  /// Number of tracks, video and audio in turn.
+  pub tracks:  u32,
+  /// Number of samples of each track.
+  pub samples: u32,
+  pub layout:  Layout,
+  /// Approximate size of the data of all the samples, the file is larger by
+  /// its boxes.
+  pub size:    u64,
+  /// Seed of the sample sizes.
+  pub seed:    u64,
+}
+
+impl Config {
+  /// A video and an audio track of samples of 512 bytes on average, in
+  /// chunks of 10 samples.
+  pub fn progressive(size: u64) -> Config {
+    Config {
+      tracks:  2,
+      samples: (size / 1024).max(1) as u32,
+      layout:  Layout::Progressive { samples_per_chunk: 10 },
+      size,
+      seed:    0x2545_f491_4f6c_dd1d,
+    }
+  }
+
+  /// The same tracks as [`Config::progressive`], in `fragments` fragments.
+  pub fn fragmented(size: u64, fragments: u32) -> Config {
+    Config { layout: Layout::Fragmented { fragments }, ..Config::progressive(size) }
+  }
+}
+
+/// What differs between video and audio tracks.
+struct Kind {
+  handler:      &'static [u8; 4],
+  name:         &'static [u8],
+  format:       &'static [u8; 4],
+  /// Fields of the sample entry after the common ones, without codec
+  /// configuration.
+  entry:        &'static [u8],
+  media_header: (&'static [u8; 4], &'static [u8]),
+  timescale:    u32,
+  duration:     u32,
+  /// Every sample is a sync sample when 1.
+  sync_every:   u32,
+  size:         (u32, u32),
+  volume:       i16,
+}
+
+const VIDEO: Kind = Kind {
+  handler:      b"vide",
+  name:         b"VideoHandler",
+  format:       b"avc1",
+  entry:        &[0; 70],
+  media_header: (b"vmhd", &[0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]),
+  timescale:    90000,
+  duration:     3000,
+  sync_every:   30,
+  size:         (640, 360),
+  volume:       0,
+};
+
+const AUDIO: Kind = Kind {
+  handler:      b"soun",
+  name:         b"SoundHandler",
+  format:       b"mp4a",
+  entry:        &[0; 20],
+  media_header: (b"smhd", &[0; 8]),
+  timescale:    48000,
+  duration:     1024,
+  sync_every:   1,
+  size:         (0, 0),
+  volume:       0x100,
+};
+
+/// `dref` holding a single `url ` entry, for data in the same file.
+const DREF: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 12, b'u', b'r', b'l', b' ', 0, 0, 0, 1];
+
+/// `sample_depends_on` = 2 for sync samples, and `sample_is_non_sync_sample`
+/// for the others.
+const SYNC: u32 = 0x0200_0000;
+const NON_SYNC: u32 = 0x0101_0000;
+
+const MATRIX: [i32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000];
+
+fn kind(track: u32) -> &'static Kind {
+  if track.is_multiple_of(2) { &VIDEO } else { &AUDIO }
+}
+
+fn leaf<'a>(fourcc: &[u8; 4], content: MP4Box<'a>) -> Node<'a> {
+  Node::new(FourCC(*fourcc), content, b"", Vec::new())
+}
+
+fn unknown<'a>(fourcc: &[u8; 4], payload: &'a [u8]) -> Node<'a> {
+  Node::new(FourCC(*fourcc), MP4Box::Unknown(FourCC(*fourcc)), payload, Vec::new())
+}
+
+fn container<'a>(fourcc: &[u8; 4], content: MP4Box<'a>, children: Vec<Node<'a>>) -> Node<'a> {
+  Node::new(FourCC(*fourcc), content, b"", children)
+}
+
+// xorshift, the sizes only have to look irregular
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+}
+
+/// Sizes of the samples of each track, from half to one and a half times
+/// the mean size.
+fn sample_sizes(config: &Config) -> Vec<Vec<u32>> {
+  let mut rng = Rng(config.seed | 1);
+  let mean = (config.size / (config.tracks as u64 * config.samples as u64).max(1)).clamp(1, u32::MAX as u64 / 2);
+  (0..config.tracks).map(|_| {
+    (0..config.samples).map(|_| (mean / 2 + rng.next() % (mean + 1)).max(1) as u32).collect()
+  }).collect()
+}
+
+fn file_type() -> Node<'static> {
+  leaf(b"ftyp", MP4Box::Ftyp(FileType {
+    major_brand:         "isom",
+    major_brand_version: &[0, 0, 2, 0],
+    compatible_brands:   vec!["isom", "iso6", "avc1", "mp41"],
+  }))
+}
+
+/// A track whose sample tables are `tables`.
+fn track<'a>(config: &Config, track: u32, tables: Vec<Node<'a>>) -> Node<'a> {
+  let kind = kind(track);
+  let media_duration = config.samples as u64 * kind.duration as u64;
+  let tkhd = TrackHeader {
+    version: 0, flags: 3, creation_time: 0, modification_time: 0, track_id: track + 1,
+    duration: media_duration * 1000 / kind.timescale as u64,
+    layer: 0, alternate_group: 0, volume: Fixed8(kind.volume), matrix: MATRIX,
+    width: Fixed16((kind.size.0 << 16) as i32), height: Fixed16((kind.size.1 << 16) as i32),
+  };
+  let mdhd = MediaHeader {
+    version: 0, creation_time: 0, modification_time: 0, timescale: kind.timescale,
+    duration: media_duration, language: *b"und",
+  };
+  let hdlr = Handler { handler_type: FourCC(*kind.handler), name: kind.name };
+  let stsd = vec![SampleEntry { format: FourCC(*kind.format), data_reference_index: 1, data: kind.entry }];
+
+  let mut stbl = vec![leaf(b"stsd", MP4Box::Stsd(stsd))];
+  stbl.extend(tables);
+  container(b"trak", MP4Box::Trak, vec![
+    leaf(b"tkhd", MP4Box::Tkhd(tkhd)),
+    container(b"mdia", MP4Box::Mdia, vec![
+      leaf(b"mdhd", MP4Box::Mdhd(mdhd)),
+      leaf(b"hdlr", MP4Box::Hdlr(hdlr)),
+      container(b"minf", MP4Box::Minf, vec![
+        unknown(kind.media_header.0, kind.media_header.1),
+        container(b"dinf", MP4Box::Dinf, vec![unknown(b"dref", DREF)]),
+        container(b"stbl", MP4Box::Stbl, stbl),
+      ]),
+    ]),
+  ])
+}
+
+fn movie_header(config: &Config) -> Node<'static> {
+  let duration = (0..config.tracks.min(2))
+    .map(|t| config.samples as u64 * kind(t).duration as u64 * 1000 / kind(t).timescale as u64)
+    .max().unwrap_or(0);
+  leaf(b"mvhd", MP4Box::Mvhd(MovieHeader {
+    version: 0, creation_time: 0, modification_time: 0, timescale: 1000, duration,
+    rate: Fixed16(0x10000), volume: Fixed8(0x100), matrix: MATRIX, next_track_id: config.tracks + 1,
+  }))
+}
+
+/// Filler for the sample data.
+fn filler(length: u64) -> Vec<u8> {
+  (0..length).map(|i| (i % 251) as u8).collect()
+}
+
+/// `ftyp`, then the `mdat` holding the chunks, then the `moov` with the
+/// sample tables.
+fn progressive(config: &Config, samples_per_chunk: u32) -> Vec<u8> {
+  let sizes = sample_sizes(config);
+  let samples_per_chunk = samples_per_chunk.max(1);
+  let total: u64 = sizes.iter().flatten().map(|&s| s as u64).sum();
+  let data = filler(total);
+
+  let ftyp = file_type();
+  let ftyp_length = serialize(std::slice::from_ref(&ftyp)).unwrap().len() as u64;
+  let mdat_header = if total + 8 > u32::MAX as u64 { 16 } else { 8 };
+
+  // chunk offsets of each track, the chunks of the tracks in turn
+  let chunks = config.samples.div_ceil(samples_per_chunk) as usize;
+  let mut offsets = vec![Vec::with_capacity(chunks); config.tracks as usize];
+  let mut position = ftyp_length + mdat_header;
+  for c in 0..chunks {
+    for (t, sizes) in sizes.iter().enumerate() {
+      offsets[t].push(position);
+      let chunk = &sizes[c * samples_per_chunk as usize..((c + 1) * samples_per_chunk as usize).min(sizes.len())];
+      position += chunk.iter().map(|&s| s as u64).sum::<u64>();
+    }
+  }
+  let wide = position > u32::MAX as u64;
+
+  let mut stsc = vec![SampleToChunk { first_chunk: 1, samples_per_chunk, sample_description_index: 1 }];
+  let last = config.samples % samples_per_chunk;
+  if last != 0 && chunks > 1 {
+    stsc.push(SampleToChunk { first_chunk: chunks as u32, samples_per_chunk: last, sample_description_index: 1 });
+  } else if last != 0 {
+    stsc[0].samples_per_chunk = last;
+  }
+
+  let mut moov = vec![movie_header(config)];
+  for (t, (sizes, offsets)) in sizes.into_iter().zip(offsets).enumerate() {
+    let kind = kind(t as u32);
+    let mut tables = vec![
+      leaf(b"stts", MP4Box::Stts(vec![TimeToSample { count: config.samples, delta: kind.duration }])),
+      leaf(b"stsc", MP4Box::Stsc(stsc.clone())),
+      leaf(b"stsz", MP4Box::Stsz(SampleSizes { constant: 0, count: config.samples, sizes })),
+      if wide { leaf(b"co64", MP4Box::Co64(offsets)) } else { leaf(b"stco", MP4Box::Stco(offsets)) },
+    ];
+    if kind.sync_every > 1 {
+      let sync = (1..=config.samples).step_by(kind.sync_every as usize).collect();
+      tables.push(leaf(b"stss", MP4Box::Stss(sync)));
+    }
+    moov.push(track(config, t as u32, tables));
+  }
+
+  let mdat = Node::new(FourCC(*b"mdat"), MP4Box::Mdat, &data, Vec::new());
+  serialize(&[ftyp, mdat, container(b"moov", MP4Box::Moov, moov)]).unwrap()
+}
+
+/// Offsets of the data of each track from the start of a `moof`, when the
+/// data starts `start` bytes after it. None when one does not fit in the
+/// signed 32 bits of a `trun`.
+fn data_offsets(start: u64, track_bytes: &[u64]) -> Option<Vec<i32>> {
+  let mut offsets = Vec::with_capacity(track_bytes.len());
+  let mut offset = start;
+  for bytes in track_bytes {
+    offsets.push(i32::try_from(offset).ok()?);
+    offset += bytes;
+  }
+  Some(offsets)
+}
+
+/// `ftyp`, the `moov` with empty sample tables and the defaults of the
+/// fragments, then each `moof` and its `mdat`.
+fn fragmented(config: &Config, fragments: u32) -> Vec<u8> {
+  let sizes = sample_sizes(config);
+  let fragments = fragments.max(1);
+
+  let mut moov = vec![movie_header(config)];
+  let mut trex = Vec::new();
+  for t in 0..config.tracks {
+    let tables = vec![
+      leaf(b"stts", MP4Box::Stts(Vec::new())),
+      leaf(b"stsc", MP4Box::Stsc(Vec::new())),
+      leaf(b"stsz", MP4Box::Stsz(SampleSizes { constant: 0, count: 0, sizes: Vec::new() })),
+      leaf(b"stco", MP4Box::Stco(Vec::new())),
+    ];
+    moov.push(track(config, t, tables));
+    let kind = kind(t);
+    trex.push(leaf(b"trex", MP4Box::Trex(TrackExtends {
+      track_id: t + 1, sample_description_index: 1, sample_duration: kind.duration, sample_size: 0,
+      sample_flags: if kind.sync_every > 1 { NON_SYNC } else { 0 },
+    })));
+  }
+  moov.push(container(b"mvex", MP4Box::Mvex, trex));
+
+  let range = |f: u32| {
+    let share = |f: u32| (f as u64 * config.samples as u64 / fragments as u64) as usize;
+    share(f)..share(f + 1)
+  };
+  let longest = (0..fragments)
+    .map(|f| sizes.iter().map(|s| s[range(f)].iter().map(|&s| s as u64).sum::<u64>()).sum::<u64>())
+    .max().unwrap_or(0);
+  let data = filler(longest);
+
+  let mut boxes = vec![file_type(), container(b"moov", MP4Box::Moov, moov)];
+  let mut position = serialize(&boxes).unwrap().len() as u64;
+  for f in 0..fragments {
+    let samples = range(f);
+    // the run of each track starts at the moof plus its data offset, or at
+    // its base data offset when the moof is too far from it. Their values do
+    // not change the size of the moof.
+    let moof = |offsets: &[(Option<u64>, Option<i32>)]| {
+      let mut children = vec![leaf(b"mfhd", MP4Box::Mfhd(f + 1))];
+      for (t, sizes) in sizes.iter().enumerate() {
+        let kind = kind(t as u32);
+        let tfhd = TrackFragmentHeader {
+          track_id: t as u32 + 1, base_data_offset: offsets[t].0, sample_description_index: None,
+          default_sample_duration: None, default_sample_size: None, default_sample_flags: None,
+          duration_is_empty: false, default_base_is_moof: true,
+        };
+        let run = TrackRun {
+          data_offset:        offsets[t].1,
+          first_sample_flags: if kind.sync_every > 1 { Some(SYNC) } else { None },
+          sample_count:       samples.len() as u32,
+          samples:            sizes[samples.clone()].iter()
+            .map(|&s| RunSample { duration: None, size: Some(s), flags: None, composition_offset: None })
+            .collect(),
+        };
+        let time = DecodeTime { version: 1, time: samples.start as u64 * kind.duration as u64 };
+        children.push(container(b"traf", MP4Box::Traf, vec![
+          leaf(b"tfhd", MP4Box::Tfhd(tfhd)),
+          leaf(b"tfdt", MP4Box::Tfdt(time)),
+          leaf(b"trun", MP4Box::Trun(run)),
+        ]));
+      }
+      container(b"moof", MP4Box::Moof, children)
+    };
+
+    let track_bytes: Vec<u64> = sizes.iter().map(|s| s[samples.clone()].iter().map(|&s| s as u64).sum()).collect();
+    let length: u64 = track_bytes.iter().sum();
+    let mdat_header = if length + 8 > u32::MAX as u64 { 16 } else { 8 };
+    let length_with = |offsets: &[(Option<u64>, Option<i32>)]| serialize(&[moof(offsets)]).unwrap().len() as u64;
+
+    let mut moof_length = length_with(&vec![(None, Some(0)); track_bytes.len()]);
+    let offsets = match data_offsets(moof_length + mdat_header, &track_bytes) {
+      Some(offsets) => offsets.into_iter().map(|o| (None, Some(o))).collect(),
+      None => {
+        moof_length = length_with(&vec![(Some(0), None); track_bytes.len()]);
+        let mut offsets = Vec::with_capacity(track_bytes.len());
+        let mut base = position + moof_length + mdat_header;
+        for bytes in &track_bytes {
+          offsets.push((Some(base), None));
+          base += bytes;
+        }
+        offsets
+      },
+    };
+    boxes.push(moof(&offsets));
+    boxes.push(Node::new(FourCC(*b"mdat"), MP4Box::Mdat, &data[..length as usize], Vec::new()));
+    position += moof_length + mdat_header + length;
+  }
+
+  serialize(&boxes).unwrap()
+}
+
+/// Writes the file described by `config`.
+pub fn generate(config: &Config) -> Vec<u8> {
+  match config.layout {
+    Layout::Progressive { samples_per_chunk } => progressive(config, samples_per_chunk),
+    Layout::Fragmented { fragments }          => fragmented(config, fragments),
+  }
+}
+
+/// Size of the data of each sample of a fragment of [`dash`], the video
+/// fragments start with a larger sync sample.
+fn dash_sizes(fragment: u32) -> (Vec<u32>, Vec<u32>) {
+  let video = (0..30).map(|s| if s == 0 { 400 } else { 40 + (fragment * 7 + s * 13) % 60 }).collect();
+  let audio = (0..47).map(|s| 20 + (fragment * 3 + s * 5) % 12).collect();
+  (video, audio)
+}
+
+/// The `moof` of fragment `fragment` of [`dash`], whose runs start at
+/// `offsets` from it. Even video fragments take their defaults from `trex`,
+/// odd ones from `tfhd` and give every field of their samples, with signed
+/// composition offsets.
+fn dash_fragment(fragment: u32, sizes: &(Vec<u32>, Vec<u32>), offsets: (i32, i32)) -> Node<'static> {
+  let odd = fragment % 2 == 1;
+  let video_header = TrackFragmentHeader {
+    track_id: 1, base_data_offset: None, sample_description_index: None,
+    default_sample_duration: if odd { Some(VIDEO.duration) } else { None }, default_sample_size: None,
+    default_sample_flags: if odd { Some(NON_SYNC) } else { None },
+    duration_is_empty: false, default_base_is_moof: true,
+  };
+  let video_run = TrackRun {
+    data_offset:        Some(offsets.0),
+    first_sample_flags: if odd { None } else { Some(SYNC) },
+    sample_count:       sizes.0.len() as u32,
+    samples:            sizes.0.iter().enumerate().map(|(s, &size)| if odd {
+      RunSample {
+        duration: Some(VIDEO.duration), size: Some(size), flags: Some(if s == 0 { SYNC } else { NON_SYNC }),
+        composition_offset: Some(if s % 2 == 1 { 3000 } else { -3000 }),
+      }
+    } else {
+      RunSample { duration: None, size: Some(size), flags: None, composition_offset: None }
+    }).collect(),
+  };
+  let audio_header = TrackFragmentHeader {
+    track_id: 2, base_data_offset: None, sample_description_index: Some(1),
+    default_sample_duration: Some(AUDIO.duration), default_sample_size: None, default_sample_flags: None,
+    duration_is_empty: false, default_base_is_moof: true,
+  };
+  let audio_run = TrackRun {
+    data_offset:        Some(offsets.1),
+    first_sample_flags: None,
+    sample_count:       sizes.1.len() as u32,
+    samples:            sizes.1.iter()
+      .map(|&size| RunSample { duration: None, size: Some(size), flags: None, composition_offset: None })
+      .collect(),
+  };
+  let video_time = DecodeTime { version: 1, time: (fragment as usize * sizes.0.len()) as u64 * VIDEO.duration as u64 };
+  let audio_time = DecodeTime { version: 0, time: (fragment as usize * sizes.1.len()) as u64 * AUDIO.duration as u64 };
+
+  container(b"moof", MP4Box::Moof, vec![
+    leaf(b"mfhd", MP4Box::Mfhd(fragment + 1)),
+    container(b"traf", MP4Box::Traf, vec![
+      leaf(b"tfhd", MP4Box::Tfhd(video_header)),
+      leaf(b"tfdt", MP4Box::Tfdt(video_time)),
+      leaf(b"trun", MP4Box::Trun(video_run)),
+    ]),
+    container(b"traf", MP4Box::Traf, vec![
+      leaf(b"tfhd", MP4Box::Tfhd(audio_header)),
+      leaf(b"tfdt", MP4Box::Tfdt(audio_time)),
+      leaf(b"trun", MP4Box::Trun(audio_run)),
+    ]),
+  ])
+}
+
+/// Writes `fragmented.mp4`, a file as streamed with DASH or CMAF: an empty
+/// `moov` with `mvex`, a `sidx`, 10 `moof` and `mdat` pairs with a video and
+/// an audio track, and a `mfra`. The fragments use different `tfhd` and
+/// `trun` flags, so that every optional field is exercised.
+pub fn dash() -> Vec<u8> {
+  const FRAGMENTS: u32 = 10;
+
+  let ftyp = leaf(b"ftyp", MP4Box::Ftyp(FileType {
+    major_brand:         "iso6",
+    major_brand_version: &[0; 4],
+    compatible_brands:   vec!["iso6", "cmfc", "dash"],
+  }));
+
+  let mut moov = vec![leaf(b"mvhd", MP4Box::Mvhd(MovieHeader {
+    version: 0, creation_time: 0, modification_time: 0, timescale: 1000, duration: 0,
+    rate: Fixed16(0x10000), volume: Fixed8(0x100), matrix: MATRIX, next_track_id: 3,
+  }))];
+  for (t, kind) in [&VIDEO, &AUDIO].iter().enumerate() {
+    let tkhd = TrackHeader {
+      version: 0, flags: 3, creation_time: 0, modification_time: 0, track_id: t as u32 + 1, duration: 0,
+      layer: 0, alternate_group: 0, volume: Fixed8(kind.volume), matrix: MATRIX,
+      width: Fixed16((kind.size.0 << 16) as i32), height: Fixed16((kind.size.1 << 16) as i32),
+    };
+    let mdhd = MediaHeader {
+      version: 0, creation_time: 0, modification_time: 0, timescale: kind.timescale, duration: 0, language: *b"eng",
+    };
+    let hdlr = Handler { handler_type: FourCC(*kind.handler), name: b"fragmented" };
+    let stsd = vec![SampleEntry { format: FourCC(*kind.format), data_reference_index: 1, data: &[0; 16] }];
+    moov.push(container(b"trak", MP4Box::Trak, vec![
+      leaf(b"tkhd", MP4Box::Tkhd(tkhd)),
+      container(b"mdia", MP4Box::Mdia, vec![
+        leaf(b"mdhd", MP4Box::Mdhd(mdhd)),
+        leaf(b"hdlr", MP4Box::Hdlr(hdlr)),
+        container(b"minf", MP4Box::Minf, vec![
+          unknown(kind.media_header.0, kind.media_header.1),
+          container(b"dinf", MP4Box::Dinf, vec![unknown(b"dref", DREF)]),
+          container(b"stbl", MP4Box::Stbl, vec![
+            leaf(b"stsd", MP4Box::Stsd(stsd)),
+            leaf(b"stts", MP4Box::Stts(Vec::new())),
+            leaf(b"stsc", MP4Box::Stsc(Vec::new())),
+            leaf(b"stsz", MP4Box::Stsz(SampleSizes { constant: 0, count: 0, sizes: Vec::new() })),
+            leaf(b"stco", MP4Box::Stco(Vec::new())),
+          ]),
+        ]),
+      ]),
+    ]));
+  }
+  moov.push(container(b"mvex", MP4Box::Mvex, vec![
+    leaf(b"trex", MP4Box::Trex(TrackExtends {
+      track_id: 1, sample_description_index: 1, sample_duration: VIDEO.duration, sample_size: 0, sample_flags: NON_SYNC,
+    })),
+    leaf(b"trex", MP4Box::Trex(TrackExtends {
+      track_id: 2, sample_description_index: 1, sample_duration: AUDIO.duration, sample_size: 0, sample_flags: 0,
+    })),
+  ]));
+  let head = vec![ftyp, container(b"moov", MP4Box::Moov, moov)];
+
+  // the data offsets depend on the size of the moof, which does not depend
+  // on their values
+  let sizes: Vec<_> = (0..FRAGMENTS).map(dash_sizes).collect();
+  let data: Vec<Vec<u8>> = (0..FRAGMENTS).zip(&sizes).map(|(f, s)| {
+    let length = s.0.iter().chain(&s.1).sum::<u32>();
+    (0..length).map(|i| (f + i) as u8).collect()
+  }).collect();
+  let mut fragments = Vec::with_capacity(2 * FRAGMENTS as usize);
+  let mut references = Vec::with_capacity(FRAGMENTS as usize);
+  for (f, (sizes, data)) in sizes.iter().zip(&data).enumerate() {
+    let f = f as u32;
+    let start = serialize(&[dash_fragment(f, sizes, (0, 0))]).unwrap().len() as u32 + 8;
+    let video_bytes = sizes.0.iter().sum::<u32>();
+    fragments.push(dash_fragment(f, sizes, (start as i32, (start + video_bytes) as i32)));
+    fragments.push(Node::new(FourCC(*b"mdat"), MP4Box::Mdat, data, Vec::new()));
+    references.push(SegmentReference {
+      is_index: false, size: start + data.len() as u32, subsegment_duration: sizes.0.len() as u32 * VIDEO.duration,
+      starts_with_sap: true, sap_type: 1, sap_delta_time: 0,
+    });
+  }
+  let sidx = leaf(b"sidx", MP4Box::Sidx(SegmentIndex {
+    version: 1, reference_id: 1, timescale: VIDEO.timescale, earliest_presentation_time: 0, first_offset: 0,
+    references: references.clone(),
+  }));
+
+  // a random access point at the start of each fragment
+  let mut offset = serialize(&head).unwrap().len() as u64 + serialize(std::slice::from_ref(&sidx)).unwrap().len() as u64;
+  let mut entries = Vec::with_capacity(FRAGMENTS as usize);
+  for (f, reference) in references.iter().enumerate() {
+    entries.push(RandomAccessPoint {
+      time: f as u64 * reference.subsegment_duration as u64, moof_offset: offset, traf_number: 1, trun_number: 1, sample: 1,
+    });
+    offset += reference.size as u64;
+  }
+  let tfra = leaf(b"tfra", MP4Box::Tfra(TrackFragmentRandomAccess { version: 1, track_id: 1, entries }));
+  let mfra_length = 8 + serialize(std::slice::from_ref(&tfra)).unwrap().len() as u32 + 16;
+  let mfra = container(b"mfra", MP4Box::Mfra, vec![tfra, leaf(b"mfro", MP4Box::Mfro(mfra_length))]);
+
+  let mut boxes = head;
+  boxes.push(sidx);
+  boxes.extend(fragments);
+  boxes.push(mfra);
+  serialize(&boxes).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::full_data_interpreter;
+  use super::super::fragment;
+  use super::super::header::Movie;
+  use super::super::sample::index;
+
+  #[test]
+  fn progressive() {
+    let config = Config { tracks: 3, samples: 100, layout: Layout::Progressive { samples_per_chunk: 7 }, size: 50_000, seed: 1 };
+    let file = generate(&config);
+    assert_eq!(generate(&config), file);
+    assert_ne!(generate(&Config { seed: 2, ..config }), file);
+
+    let boxes = full_data_interpreter(&file).unwrap();
+    assert_eq!(boxes.iter().map(|b| b.content.name()).collect::<Vec<_>>(), ["ftyp", "mdat", "moov"]);
+    let movie = Movie::new(&boxes).unwrap();
+    assert_eq!(movie.tracks.iter().map(|t| t.handler.name).collect::<Vec<_>>(), [&b"VideoHandler"[..], b"SoundHandler", b"VideoHandler"]);
+    assert_eq!(movie.header.duration, 3333);
+
+    let tracks = index(&boxes).unwrap();
+    assert_eq!(tracks.iter().map(|t| (t.track_id, t.samples.len())).collect::<Vec<_>>(), [(1, 100), (2, 100), (3, 100)]);
+    assert_eq!(tracks[0].samples.iter().filter(|s| s.keyframe).count(), 4);
+    assert_eq!(tracks[1].samples[99].decode_time, 99 * 1024);
+
+    // the samples fill the mdat
+    let mdat = &boxes[1];
+    let mut samples: Vec<_> = tracks.iter().flat_map(|t| &t.samples).collect();
+    samples.sort_by_key(|s| s.offset);
+    assert_eq!(samples[0].offset, mdat.offset as u64 + 8);
+    for w in samples.windows(2) {
+      assert_eq!(w[0].offset + w[0].size as u64, w[1].offset);
+    }
+    let total: u64 = samples.iter().map(|s| s.size as u64).sum();
+    assert_eq!(total, mdat.payload.len() as u64);
+    assert!(total > 40_000 && total < 60_000);
+  }
+
+  #[test]
+  fn fragmented() {
+    let config = Config { layout: Layout::Fragmented { fragments: 7 }, ..Config::progressive(100_000) };
+    let file = generate(&config);
+    let boxes = full_data_interpreter(&file).unwrap();
+    assert_eq!(boxes.len(), 2 + 2 * 7);
+
+    let fragments = fragment::fragments(&boxes).unwrap();
+    assert_eq!(fragments.len(), 7);
+    for (i, f) in fragments.iter().enumerate() {
+      let mdat = &boxes[2 + 2 * i + 1];
+      let mut samples: Vec<_> = f.tracks.iter().flat_map(|t| &t.samples).collect();
+      samples.sort_by_key(|s| s.offset);
+      assert_eq!(samples[0].offset, mdat.offset as u64 + 8);
+      let last = samples.last().unwrap();
+      assert_eq!(last.offset + last.size as u64, (mdat.offset + mdat.size) as u64);
+      assert!(f.tracks[0].samples[0].keyframe && !f.tracks[0].samples[1].keyframe);
+    }
+
+    let video: Vec<_> = fragments.iter().flat_map(|f| &f.tracks[0].samples).collect();
+    assert_eq!(video.len(), config.samples as usize);
+    assert!(video.iter().enumerate().all(|(n, s)| s.decode_time == n as u64 * 3000));
+
+    // more fragments than samples leaves some empty
+    let config = Config { samples: 3, layout: Layout::Fragmented { fragments: 5 }, ..config };
+    let fragments = fragment::fragments(&full_data_interpreter(&generate(&config)).unwrap()).unwrap();
+    assert_eq!(fragments.iter().map(|f| f.tracks[1].samples.len()).sum::<usize>(), 3);
+  }
+
+  #[test]
+  fn dash() {
+    // the committed fixture, written by mp4dash
+    assert!(super::dash() == harness::fixtures::mp4::FRAGMENTED, "fragmented.mp4 differs from generate::dash()");
+  }
+
+  #[test]
+  fn data_offsets() {
+    assert_eq!(super::data_offsets(100, &[10, 20]), Some(vec![100, 110]));
+    assert_eq!(super::data_offsets(100, &[i32::MAX as u64 - 100, 1]), Some(vec![100, i32::MAX]));
+    assert_eq!(super::data_offsets(100, &[i32::MAX as u64 - 99, 1]), None);
+    assert_eq!(super::data_offsets(1 << 31, &[]), Some(vec![]));
+  }
+}
@@ -0,0 +1,305 @@
+//! Movie, track and media headers: the `mvhd`, `tkhd`, `mdhd` and `hdlr`
+//! boxes, ISO/IEC 14496-12 sections 8.2.2, 8.3.2, 8.4.2 and 8.4.3.
+//!
+//! Version 1 of the headers widens the times and the duration to 64 bits,
+//! the parsers return them as `u64` for both versions.
+
+use nom::{IResult,be_i16,be_u8,be_u16,be_u32,be_u64};
+
+use super::{fourcc, moov, BoxError, FourCC, MP4Box, Node};
+
+/// Signed 16.16 fixed point number.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Fixed16(pub i32);
+
+impl Fixed16 {
+  pub fn to_f64(self) -> f64 {
+    self.0 as f64 / 65536.0
+  }
+}
+
+/// Signed 8.8 fixed point number.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Fixed8(pub i16);
+
+impl Fixed8 {
+  pub fn to_f64(self) -> f64 {
+    self.0 as f64 / 256.0
+  }
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct MovieHeader {
+  pub version:           u8,
+  /// Seconds since 1904-01-01, in UTC.
+  pub creation_time:     u64,
+  pub modification_time: u64,
+  /// Time units per second of `duration`.
+  pub timescale:         u32,
+  pub duration:          u64,
+  /// Preferred playback rate, 1.0 is normal.
+  pub rate:              Fixed16,
+  /// Preferred volume, 1.0 is full.
+  pub volume:            Fixed8,
+  /// Transformation of the video, a 3x3 matrix in row order.
+  pub matrix:            [i32; 9],
+  pub next_track_id:     u32,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct TrackHeader {
+  pub version:           u8,
+  /// Bit 0 enabled, 1 in movie, 2 in preview.
+  pub flags:             u32,
+  pub creation_time:     u64,
+  pub modification_time: u64,
+  pub track_id:          u32,
+  /// In the timescale of the movie header.
+  pub duration:          u64,
+  pub layer:             i16,
+  pub alternate_group:   i16,
+  pub volume:            Fixed8,
+  pub matrix:            [i32; 9],
+  /// Visual presentation size, 0 for the other tracks.
+  pub width:             Fixed16,
+  pub height:            Fixed16,
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct MediaHeader {
+  pub version:           u8,
+  pub creation_time:     u64,
+  pub modification_time: u64,
+  /// Time units per second of `duration` and of the sample tables.
+  pub timescale:         u32,
+  pub duration:          u64,
+  /// ISO 639-2/T language code, such as `eng`, or `und` when undetermined.
+  pub language:          [u8; 3],
+}
+
+impl MediaHeader {
+  pub fn language(&self) -> &str {
+    std::str::from_utf8(&self.language).unwrap_or("und")
+  }
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Handler<'a> {
+  /// `vide`, `soun`, `hint`, `meta`...
+  pub handler_type: FourCC,
+  /// Name of the track, up to its terminating 0.
+  pub name:         &'a [u8],
+}
+
+/// Version and flags of a full box.
+pub(crate) fn full_box(input:&[u8]) -> IResult<&[u8], (u8, u32)> {
+  do_parse!(input,
+    version: be_u8                  >>
+    flags:   take!(3)               >>
+    (version, (flags[0] as u32) << 16 | (flags[1] as u32) << 8 | flags[2] as u32)
+  )
+}
+
+/// Creation time, modification time, timescale or track id, and duration,
+/// whose sizes depend on the version.
+fn times(input:&[u8], version: u8) -> IResult<&[u8], (u64, u64, u32, u64)> {
+  if version == 1 {
+    do_parse!(input,
+      c: be_u64 >> m: be_u64 >> t: be_u32 >> d: be_u64 >>
+      (c, m, t, d)
+    )
+  } else {
+    do_parse!(input,
+      c: be_u32 >> m: be_u32 >> t: be_u32 >> d: be_u32 >>
+      (c as u64, m as u64, t, d as u64)
+    )
+  }
+}
+
+fn fixed16(input:&[u8]) -> IResult<&[u8], Fixed16> {
+  map!(input, be_u32, |n| Fixed16(n as i32))
+}
+
+fn fixed8(input:&[u8]) -> IResult<&[u8], Fixed8> {
+  map!(input, be_i16, Fixed8)
+}
+
+fn matrix(input:&[u8]) -> IResult<&[u8], [i32; 9]> {
+  let mut m = [0; 9];
+  let mut i = input;
+  for v in m.iter_mut() {
+    let (rest, n) = be_u32(i)?;
+    *v = n as i32;
+    i = rest;
+  }
+  Ok((i, m))
+}
+
+pub fn movie_header(input:&[u8]) -> IResult<&[u8], MovieHeader> {
+  let (i, (version, _)) = full_box(input)?;
+  let (i, (creation_time, modification_time, timescale, duration)) = times(i, version)?;
+  do_parse!(i,
+    rate:          fixed16  >>
+    volume:        fixed8   >>
+                   take!(10) >>
+    matrix:        matrix   >>
+                   take!(24) >>
+    next_track_id: be_u32   >>
+    (MovieHeader {
+      version, creation_time, modification_time, timescale, duration,
+      rate, volume, matrix, next_track_id
+    })
+  )
+}
+
+pub fn track_header(input:&[u8]) -> IResult<&[u8], TrackHeader> {
+  let (i, (version, flags)) = full_box(input)?;
+  // the track id sits between the times and the duration, followed by 4
+  // reserved bytes
+  let (i, (creation_time, modification_time, track_id, duration)) = if version == 1 {
+    do_parse!(i,
+      c: be_u64 >> m: be_u64 >> t: be_u32 >> take!(4) >> d: be_u64 >>
+      (c, m, t, d)
+    )?
+  } else {
+    do_parse!(i,
+      c: be_u32 >> m: be_u32 >> t: be_u32 >> take!(4) >> d: be_u32 >>
+      (c as u64, m as u64, t, d as u64)
+    )?
+  };
+  do_parse!(i,
+                     take!(8) >>
+    layer:           be_i16   >>
+    alternate_group: be_i16   >>
+    volume:          fixed8   >>
+                     take!(2) >>
+    matrix:          matrix   >>
+    width:           fixed16  >>
+    height:          fixed16  >>
+    (TrackHeader {
+      version, flags, creation_time, modification_time, track_id, duration,
+      layer, alternate_group, volume, matrix, width, height
+    })
+  )
+}
+
+pub fn media_header(input:&[u8]) -> IResult<&[u8], MediaHeader> {
+  let (i, (version, _)) = full_box(input)?;
+  let (i, (creation_time, modification_time, timescale, duration)) = times(i, version)?;
+  // three 5 bit letters, offset by 0x60, after a pad bit
+  let (i, packed) = be_u16(i)?;
+  let letter = |shift: u16| ((packed >> shift) & 0x1f) as u8 + 0x60;
+  let (i, _) = take!(i, 2)?;
+  Ok((i, MediaHeader {
+    version, creation_time, modification_time, timescale, duration,
+    language: [letter(10), letter(5), letter(0)],
+  }))
+}
+
+pub fn handler(input:&[u8]) -> IResult<&[u8], Handler<'_>> {
+  let (i, handler_type) = do_parse!(input,
+                  full_box  >>
+                  take!(4)  >>
+    handler_type: fourcc    >>
+                  take!(12) >>
+    (handler_type)
+  )?;
+  // some writers leave out the terminating 0
+  let name = i.split(|&c| c == 0).next().unwrap_or(i);
+  Ok((&i[name.len()..], Handler { handler_type, name }))
+}
+
+/// Headers of a track.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Track<'a> {
+  pub header:  TrackHeader,
+  pub media:   MediaHeader,
+  pub handler: Handler<'a>,
+}
+
+/// Headers of a movie and of its tracks.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct Movie<'a> {
+  pub header: MovieHeader,
+  pub tracks: Vec<Track<'a>>,
+}
+
+// `box_content` decodes every box of these types, or fails
+macro_rules! decoded (
+  ($node:expr, $variant:path) => (
+    match $node.content {
+      $variant(h) => h,
+      _           => unreachable!(),
+    }
+  );
+);
+
+impl<'a> Movie<'a> {
+  /// Gathers the headers of the `moov` box among the top level `boxes`.
+  pub fn new(boxes: &[Node<'a>]) -> Result<Movie<'a>, BoxError> {
+    let moov = moov(boxes)?;
+
+    let header = decoded!(moov.child(b"mvhd")?, MP4Box::Mvhd);
+    let mut tracks = Vec::new();
+    for trak in moov.children.iter().filter(|c| c.content == MP4Box::Trak) {
+      let mdia = trak.child(b"mdia")?;
+      tracks.push(Track {
+        header:  decoded!(trak.child(b"tkhd")?, MP4Box::Tkhd),
+        media:   decoded!(mdia.child(b"mdhd")?, MP4Box::Mdhd),
+        handler: decoded!(mdia.child(b"hdlr")?, MP4Box::Hdlr),
+      });
+    }
+
+    Ok(Movie { header, tracks })
+  }
+
+  /// Parses a file and gathers its headers.
+  pub fn parse(input: &'a [u8]) -> Result<Movie<'a>, BoxError> {
+    Movie::new(&super::full_data_interpreter(input)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn small() {
+    let movie = Movie::parse(harness::fixtures::mp4::SMALL).unwrap();
+    assert_eq!((movie.header.timescale, movie.header.duration), (90000, 501120));
+    assert_eq!((movie.header.rate.to_f64(), movie.header.volume.to_f64()), (1.0, 1.0));
+    assert_eq!(movie.header.matrix, [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x40000000]);
+    assert_eq!(movie.header.next_track_id, 3);
+    assert_eq!(movie.tracks.len(), 2);
+
+    let video = &movie.tracks[0];
+    assert_eq!((video.header.track_id, video.header.flags, video.header.duration), (1, 1, 498000));
+    assert_eq!((video.header.width.to_f64(), video.header.height.to_f64()), (560.0, 320.0));
+    assert_eq!((video.media.timescale, video.media.duration, video.media.language()), (90000, 498000, "und"));
+    assert_eq!((video.handler.handler_type, video.handler.name), (FourCC(*b"vide"), &b""[..]));
+
+    let audio = &movie.tracks[1];
+    assert_eq!((audio.header.track_id, audio.header.volume.to_f64(), audio.header.width.0), (2, 1.0, 0));
+    assert_eq!((audio.media.timescale, audio.media.duration, audio.media.language()), (48000, 267264, "eng"));
+    assert_eq!(audio.handler.handler_type, FourCC(*b"soun"));
+  }
+
+  #[test]
+  fn version_1() {
+    let mut mdhd = vec![1, 0, 0, 0];
+    mdhd.extend_from_slice(&1u64.to_be_bytes());
+    mdhd.extend_from_slice(&2u64.to_be_bytes());
+    mdhd.extend_from_slice(&1000u32.to_be_bytes());
+    mdhd.extend_from_slice(&(1u64 << 40).to_be_bytes());
+    mdhd.extend_from_slice(&[0x15, 0xc7, 0, 0]);
+    let (rest, h) = media_header(&mdhd).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!((h.creation_time, h.modification_time, h.timescale, h.duration), (1, 2, 1000, 1 << 40));
+    assert_eq!(h.language(), "eng");
+
+    let hdlr = b"\0\0\0\0\0\0\0\0metaxxxxxxxxxxxxname";
+    assert_eq!(handler(hdlr).unwrap().1, Handler { handler_type: FourCC(*b"meta"), name: b"name" });
+
+    assert_eq!(Movie::parse(b"\0\0\0\x08moov"), Err(BoxError::MissingBox { offset: 0, fourcc: FourCC(*b"mvhd") }));
+  }
+}
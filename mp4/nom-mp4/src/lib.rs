@@ -0,0 +1,593 @@
+#[macro_use]
+extern crate nom;
+
+extern crate harness;
+extern crate serde_json;
+
+use harness::ParserUnderTest;
+use nom::{IResult,be_u32,be_u64};
+
+use std::fmt;
+use std::str::from_utf8;
+
+pub mod dump;
+pub mod fragment;
+pub mod generate;
+pub mod header;
+pub mod reader;
+pub mod sample;
+pub mod writer;
+
+use fragment::{DecodeTime, SegmentIndex, TrackExtends, TrackFragmentHeader, TrackFragmentRandomAccess, TrackRun};
+use header::{Handler, MediaHeader, MovieHeader, TrackHeader};
+use sample::{CompositionOffset, SampleEntry, SampleSizes, SampleToChunk, TimeToSample};
+
+/// Type code of a box.
+#[derive(Clone,Copy,PartialEq,Eq,Hash)]
+pub struct FourCC(pub [u8; 4]);
+
+impl FourCC {
+  pub fn as_str(&self) -> Option<&str> {
+    from_utf8(&self.0).ok()
+  }
+}
+
+impl fmt::Display for FourCC {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for &c in &self.0 {
+      if c.is_ascii_graphic() || c == b' ' {
+        write!(f, "{}", c as char)?;
+      } else {
+        write!(f, "\\x{:02x}", c)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Debug for FourCC {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "FourCC({})", self)
+  }
+}
+
+fn fourcc(input:&[u8]) -> IResult<&[u8], FourCC> {
+  map!(input, take!(4), |t: &[u8]| FourCC([t[0], t[1], t[2], t[3]]))
+}
+
+/// Header of a box, as laid out in ISO/IEC 14496-12 section 4.2.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct BoxHeader {
+  pub fourcc: FourCC,
+  /// Extended type of the `uuid` boxes.
+  pub uuid:   Option<[u8; 16]>,
+  /// Size of the box, header included. `None` when the box extends to the
+  /// end of the file, written as a size of 0.
+  pub size:   Option<u64>,
+  /// Length of the header: 8 bytes, 8 more for a 64 bit size (written after
+  /// a size of 1), and 16 more for the extended type.
+  pub length: usize,
+}
+
+/// Parses the header of the box at the start of `input`, without checking
+/// its size.
+pub fn box_header(input:&[u8]) -> IResult<&[u8], BoxHeader> {
+  let (i, size) = be_u32(input)?;
+  let (i, t) = fourcc(i)?;
+  let (i, size, length) = match size {
+    0 => (i, None, 8),
+    1 => {
+      let (i, largesize) = be_u64(i)?;
+      (i, Some(largesize), 16)
+    }
+    n => (i, Some(n as u64), 8),
+  };
+  let (i, uuid, length) = if t.0 == *b"uuid" {
+    let (i, u) = take!(i, 16)?;
+    let mut uuid = [0; 16];
+    uuid.copy_from_slice(u);
+    (i, Some(uuid), length + 16)
+  } else {
+    (i, None, length)
+  };
+
+  Ok((i, BoxHeader { fourcc: t, uuid, size, length }))
+}
+
+/// Why a box could not be parsed. Offsets are from the start of the parsed
+/// input.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum BoxError {
+  /// The input ends in the middle of the box header.
+  TruncatedHeader { offset: usize },
+  /// The box size is smaller than its header.
+  SizeTooSmall { offset: usize, size: u64, header: usize },
+  /// The box extends past the end of its parent, or of the input for a top
+  /// level box, which has `available` bytes left.
+  Truncated { offset: usize, size: u64, available: usize },
+  /// Containers are nested deeper than `MAX_DEPTH`.
+  TooDeep { offset: usize },
+  /// The payload does not match the box type.
+  InvalidPayload { offset: usize, fourcc: FourCC },
+  /// A box needed by the caller is missing from the container at `offset`.
+  MissingBox { offset: usize, fourcc: FourCC },
+  /// The tables of the `stbl` box at `offset` contradict each other.
+  InvalidSampleTable { offset: usize, reason: &'static str },
+  /// The samples of the fragment box at `offset` cannot be resolved.
+  InvalidFragment { offset: usize, reason: &'static str },
+}
+
+impl BoxError {
+  /// Position of the box in error.
+  pub fn offset(&self) -> usize {
+    match *self {
+      BoxError::TruncatedHeader { offset } |
+      BoxError::SizeTooSmall { offset, .. } |
+      BoxError::Truncated { offset, .. } |
+      BoxError::TooDeep { offset } |
+      BoxError::InvalidPayload { offset, .. } |
+      BoxError::MissingBox { offset, .. } |
+      BoxError::InvalidSampleTable { offset, .. } |
+      BoxError::InvalidFragment { offset, .. } => offset,
+    }
+  }
+}
+
+impl fmt::Display for BoxError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      BoxError::TruncatedHeader { offset } =>
+        write!(f, "truncated box header at {}", offset),
+      BoxError::SizeTooSmall { offset, size, header } =>
+        write!(f, "box at {} has size {}, smaller than its {} bytes header", offset, size, header),
+      BoxError::Truncated { offset, size, available } =>
+        write!(f, "box at {} has size {} but only {} bytes are left", offset, size, available),
+      BoxError::TooDeep { offset } =>
+        write!(f, "box at {} is nested more than {} levels deep", offset, MAX_DEPTH),
+      BoxError::InvalidPayload { offset, fourcc } =>
+        write!(f, "invalid payload for {} box at {}", fourcc, offset),
+      BoxError::MissingBox { offset, fourcc } =>
+        write!(f, "no {} box in the box at {}", fourcc, offset),
+      BoxError::InvalidSampleTable { offset, reason } =>
+        write!(f, "invalid sample table at {}: {}", offset, reason),
+      BoxError::InvalidFragment { offset, reason } =>
+        write!(f, "invalid fragment at {}: {}", offset, reason),
+    }
+  }
+}
+
+impl std::error::Error for BoxError {}
+
+/// Splits the box at the start of `input`, found `offset` bytes into the
+/// parsed data, into its header and its payload. A box of size 0 extends to
+/// the end of `input`.
+fn mp4_box(input:&[u8], offset: usize) -> Result<(&[u8], BoxHeader, &[u8]), BoxError> {
+  let header = match box_header(input) {
+    Ok((_, header)) => header,
+    Err(_)          => return Err(BoxError::TruncatedHeader { offset }),
+  };
+
+  let size = header.size.unwrap_or(input.len() as u64);
+  if size < header.length as u64 {
+    return Err(BoxError::SizeTooSmall { offset, size, header: header.length });
+  }
+  if size > input.len() as u64 {
+    return Err(BoxError::Truncated { offset, size, available: input.len() });
+  }
+
+  let size = size as usize;
+  Ok((&input[size..], header, &input[header.length..size]))
+}
+
+#[derive(PartialEq,Eq,Debug)]
+pub struct FileType<'a> {
+  pub major_brand:         &'a str,
+  pub major_brand_version: &'a [u8],
+  pub compatible_brands:   Vec<&'a str>
+}
+
+/// What is known of a box from its type. The children of the containers are
+/// in [`Node::children`].
+#[derive(PartialEq,Eq,Debug)]
+pub enum MP4Box<'a> {
+  Ftyp(FileType<'a>),
+  Mvhd(MovieHeader),
+  Tkhd(TrackHeader),
+  Mdhd(MediaHeader),
+  Hdlr(Handler<'a>),
+  Stsd(Vec<SampleEntry<'a>>),
+  Stts(Vec<TimeToSample>),
+  Ctts(Vec<CompositionOffset>),
+  Stsc(Vec<SampleToChunk>),
+  Stsz(SampleSizes),
+  Stz2(SampleSizes),
+  Stco(Vec<u64>),
+  Co64(Vec<u64>),
+  Stss(Vec<u32>),
+  Trex(TrackExtends),
+  Mfhd(u32),
+  Tfhd(TrackFragmentHeader),
+  Tfdt(DecodeTime),
+  Trun(TrackRun),
+  Sidx(SegmentIndex),
+  Tfra(TrackFragmentRandomAccess),
+  Mfro(u32),
+  Moov,
+  Trak,
+  Mdia,
+  Minf,
+  Stbl,
+  Udta,
+  Edts,
+  Dinf,
+  Mvex,
+  Moof,
+  Traf,
+  Mfra,
+  Mdat,
+  Free,
+  Skip,
+  Wide,
+  Unknown(FourCC)
+}
+
+impl<'a> MP4Box<'a> {
+  pub fn name(&self) -> &'static str {
+    match *self {
+      MP4Box::Ftyp(_)    => "ftyp",
+      MP4Box::Mvhd(_)    => "mvhd",
+      MP4Box::Tkhd(_)    => "tkhd",
+      MP4Box::Mdhd(_)    => "mdhd",
+      MP4Box::Hdlr(_)    => "hdlr",
+      MP4Box::Stsd(_)    => "stsd",
+      MP4Box::Stts(_)    => "stts",
+      MP4Box::Ctts(_)    => "ctts",
+      MP4Box::Stsc(_)    => "stsc",
+      MP4Box::Stsz(_)    => "stsz",
+      MP4Box::Stz2(_)    => "stz2",
+      MP4Box::Stco(_)    => "stco",
+      MP4Box::Co64(_)    => "co64",
+      MP4Box::Stss(_)    => "stss",
+      MP4Box::Trex(_)    => "trex",
+      MP4Box::Mfhd(_)    => "mfhd",
+      MP4Box::Tfhd(_)    => "tfhd",
+      MP4Box::Tfdt(_)    => "tfdt",
+      MP4Box::Trun(_)    => "trun",
+      MP4Box::Sidx(_)    => "sidx",
+      MP4Box::Tfra(_)    => "tfra",
+      MP4Box::Mfro(_)    => "mfro",
+      MP4Box::Moov       => "moov",
+      MP4Box::Trak       => "trak",
+      MP4Box::Mdia       => "mdia",
+      MP4Box::Minf       => "minf",
+      MP4Box::Stbl       => "stbl",
+      MP4Box::Udta       => "udta",
+      MP4Box::Edts       => "edts",
+      MP4Box::Dinf       => "dinf",
+      MP4Box::Mvex       => "mvex",
+      MP4Box::Moof       => "moof",
+      MP4Box::Traf       => "traf",
+      MP4Box::Mfra       => "mfra",
+      MP4Box::Mdat       => "mdat",
+      MP4Box::Free       => "free",
+      MP4Box::Skip       => "skip",
+      MP4Box::Wide       => "wide",
+      MP4Box::Unknown(_) => "unknown",
+    }
+  }
+
+  /// Whether the payload of the box is a sequence of boxes.
+  pub fn is_container(&self) -> bool {
+    match *self {
+      MP4Box::Moov | MP4Box::Trak | MP4Box::Mdia | MP4Box::Minf |
+      MP4Box::Stbl | MP4Box::Udta | MP4Box::Edts | MP4Box::Dinf |
+      MP4Box::Mvex | MP4Box::Moof | MP4Box::Traf | MP4Box::Mfra => true,
+      _ => false,
+    }
+  }
+}
+
+/// A box of the file, with its position and the boxes it contains.
+#[derive(PartialEq,Eq,Debug)]
+pub struct Node<'a> {
+  pub fourcc:   FourCC,
+  /// Extended type of the `uuid` boxes.
+  pub uuid:     Option<[u8; 16]>,
+  /// Position of the box header from the start of the parsed input.
+  pub offset:   usize,
+  /// Size of the box, header included, resolved for the boxes extending to
+  /// the end of the file.
+  pub size:     usize,
+  /// The box payload, after its header.
+  pub payload:  &'a [u8],
+  pub content:  MP4Box<'a>,
+  /// Boxes of a container, empty for the other boxes.
+  pub children: Vec<Node<'a>>
+}
+
+impl<'a> Node<'a> {
+  /// A box to write with [`writer::serialize`], which sets its position
+  /// and size. `payload` is only written for the boxes whose content is not
+  /// decoded.
+  pub fn new(fourcc: FourCC, content: MP4Box<'a>, payload: &'a [u8], children: Vec<Node<'a>>) -> Node<'a> {
+    Node { fourcc, uuid: None, offset: 0, size: 0, payload, content, children }
+  }
+
+  /// This node and its descendants in depth first order, with their depth
+  /// relative to this node.
+  pub fn walk(&self) -> Vec<(usize, &Node<'a>)> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![(0, self)];
+    while let Some((depth, node)) = stack.pop() {
+      nodes.push((depth, node));
+      stack.extend(node.children.iter().rev().map(|c| (depth + 1, c)));
+    }
+    nodes
+  }
+
+  /// First child of type `fourcc`.
+  pub fn child(&self, fourcc: &[u8; 4]) -> Result<&Node<'a>, BoxError> {
+    self.children.iter()
+      .find(|c| c.fourcc.0 == *fourcc)
+      .ok_or(BoxError::MissingBox { offset: self.offset, fourcc: FourCC(*fourcc) })
+  }
+}
+
+/// The `moov` box among the top level `boxes`.
+fn moov<'a, 'b>(boxes: &'b [Node<'a>]) -> Result<&'b Node<'a>, BoxError> {
+  boxes.iter().find(|b| b.content == MP4Box::Moov)
+    .ok_or(BoxError::MissingBox { offset: 0, fourcc: FourCC(*b"moov") })
+}
+
+named!(brand_name<&[u8],&str>, map_res!(take!(4), from_utf8));
+
+fn filetype_box<'a>(input: &'a[u8]) -> IResult<&'a [u8], MP4Box<'a>> {
+  //println!("ftyp:\n{}", input.to_hex(8));
+  do_parse!(input,
+    m: brand_name                    >>
+    v: take!(4)                      >>
+    c: many0!(complete!(brand_name)) >>
+    (
+      MP4Box::Ftyp(FileType{
+        major_brand: m,
+        major_brand_version: v,
+        compatible_brands: c
+      })
+    )
+  )
+}
+
+fn box_content(t: FourCC, payload: &[u8]) -> IResult<&[u8], MP4Box<'_>> {
+  let content = match &t.0 {
+    b"ftyp" => return filetype_box(payload),
+    b"mvhd" => return map!(payload, header::movie_header, MP4Box::Mvhd),
+    b"tkhd" => return map!(payload, header::track_header, MP4Box::Tkhd),
+    b"mdhd" => return map!(payload, header::media_header, MP4Box::Mdhd),
+    b"hdlr" => return map!(payload, header::handler, MP4Box::Hdlr),
+    b"stsd" => return map!(payload, sample::sample_description, MP4Box::Stsd),
+    b"stts" => return map!(payload, sample::time_to_sample, MP4Box::Stts),
+    b"ctts" => return map!(payload, sample::composition_offset, MP4Box::Ctts),
+    b"stsc" => return map!(payload, sample::sample_to_chunk, MP4Box::Stsc),
+    b"stsz" => return map!(payload, sample::sample_size, MP4Box::Stsz),
+    b"stz2" => return map!(payload, sample::compact_sample_size, MP4Box::Stz2),
+    b"stco" => return map!(payload, sample::chunk_offset, MP4Box::Stco),
+    b"co64" => return map!(payload, sample::chunk_offset_64, MP4Box::Co64),
+    b"stss" => return map!(payload, sample::sync_sample, MP4Box::Stss),
+    b"trex" => return map!(payload, fragment::track_extends, MP4Box::Trex),
+    b"mfhd" => return map!(payload, fragment::movie_fragment_header, MP4Box::Mfhd),
+    b"tfhd" => return map!(payload, fragment::track_fragment_header, MP4Box::Tfhd),
+    b"tfdt" => return map!(payload, fragment::track_fragment_decode_time, MP4Box::Tfdt),
+    b"trun" => return map!(payload, fragment::track_run, MP4Box::Trun),
+    b"sidx" => return map!(payload, fragment::segment_index, MP4Box::Sidx),
+    b"tfra" => return map!(payload, fragment::track_fragment_random_access, MP4Box::Tfra),
+    b"mfro" => return map!(payload, fragment::movie_fragment_random_access_offset, MP4Box::Mfro),
+    b"moov" => MP4Box::Moov,
+    b"trak" => MP4Box::Trak,
+    b"mdia" => MP4Box::Mdia,
+    b"minf" => MP4Box::Minf,
+    b"stbl" => MP4Box::Stbl,
+    b"udta" => MP4Box::Udta,
+    b"edts" => MP4Box::Edts,
+    b"dinf" => MP4Box::Dinf,
+    b"mvex" => MP4Box::Mvex,
+    b"moof" => MP4Box::Moof,
+    b"traf" => MP4Box::Traf,
+    b"mfra" => MP4Box::Mfra,
+    b"mdat" => MP4Box::Mdat,
+    b"free" => MP4Box::Free,
+    b"skip" => MP4Box::Skip,
+    b"wide" => MP4Box::Wide,
+    _       => MP4Box::Unknown(t),
+  };
+  Ok((payload, content))
+}
+
+/// Deepest nesting of containers accepted, so that crafted files cannot
+/// exhaust the stack.
+const MAX_DEPTH: usize = 32;
+
+/// Parses the box at the start of `input`, found `offset` bytes into the
+/// file, and the boxes it contains.
+fn node(input: &[u8], offset: usize, depth: usize) -> Result<(&[u8], Node<'_>), BoxError> {
+  let (rest, header, payload) = mp4_box(input, offset)?;
+  let content = match box_content(header.fourcc, payload) {
+    Ok((_, content)) => content,
+    Err(_)           => return Err(BoxError::InvalidPayload { offset, fourcc: header.fourcc }),
+  };
+
+  let children = if content.is_container() {
+    if depth == MAX_DEPTH {
+      return Err(BoxError::TooDeep { offset });
+    }
+    boxes(payload, offset + header.length, depth + 1)?
+  } else {
+    Vec::new()
+  };
+
+  Ok((rest, Node {
+    fourcc: header.fourcc,
+    uuid: header.uuid,
+    offset,
+    size: input.len() - rest.len(),
+    payload,
+    content,
+    children,
+  }))
+}
+
+/// Parses the whole of `input`, found `offset` bytes into the file, as a
+/// sequence of boxes.
+fn boxes(input: &[u8], offset: usize, depth: usize) -> Result<Vec<Node<'_>>, BoxError> {
+  let mut nodes = Vec::new();
+  let mut i = input;
+  while !i.is_empty() {
+    // QuickTime ends some `udta` boxes with a 32 bit 0
+    if depth > 0 && i == [0, 0, 0, 0] {
+      break;
+    }
+    let (rest, n) = node(i, offset + input.len() - i.len(), depth)?;
+    nodes.push(n);
+    i = rest;
+  }
+  Ok(nodes)
+}
+
+/// Parses the top level boxes of a file, each with its descendants. The
+/// whole input must be made of complete boxes.
+pub fn full_data_interpreter(input: &[u8]) -> Result<Vec<Node<'_>>, BoxError> {
+  boxes(input, 0, 0)
+}
+
+fn paths(nodes: &[Node]) -> Vec<String> {
+  let mut paths = Vec::new();
+  let mut path: Vec<String> = Vec::new();
+  for n in nodes {
+    for (depth, n) in n.walk() {
+      path.truncate(depth);
+      path.push(n.fourcc.to_string());
+      paths.push(path.join("/"));
+    }
+  }
+  paths
+}
+
+pub struct NomMp4;
+
+impl ParserUnderTest for NomMp4 {
+  /// Path of every box, such as `moov/trak/tkhd`, depth first.
+  type Output = Vec<String>;
+
+  const NAME: &'static str = "nom";
+
+  fn parse(&mut self, input: &[u8]) -> Result<usize, harness::Error> {
+    match full_data_interpreter(input) {
+      Ok(_)  => Ok(input.len()),
+      Err(e) => Err(harness::Error(e.to_string())),
+    }
+  }
+
+  fn parse_normalized(&mut self, input: &[u8]) -> Result<(usize, Self::Output), harness::Error> {
+    match full_data_interpreter(input) {
+      Ok(o)  => Ok((input.len(), paths(&o))),
+      Err(e) => Err(harness::Error(e.to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A box decoded as `content`, without children, for the tables the
+  /// tests build by hand.
+  pub fn leaf(content: MP4Box<'static>) -> Node<'static> {
+    Node::new(FourCC(*b"xxxx"), content, b"", Vec::new())
+  }
+
+  #[test]
+  fn small() {
+    let boxes = full_data_interpreter(harness::fixtures::mp4::SMALL).unwrap();
+    assert_eq!(boxes.iter().map(|b| b.content.name()).collect::<Vec<_>>(), ["ftyp", "free", "mdat", "moov", "free"]);
+
+    let moov = &boxes[3];
+    assert_eq!((moov.offset, moov.size), (380040, 3459));
+    let children: Vec<_> = moov.children.iter().map(|c| c.fourcc.to_string()).collect();
+    assert_eq!(children, ["mvhd", "iods", "trak", "trak", "udta"]);
+    assert_eq!(moov.children[1].content, MP4Box::Unknown(FourCC(*b"iods")));
+
+    let paths = NomMp4.parse_normalized(harness::fixtures::mp4::SMALL).unwrap().1;
+    assert_eq!(paths.len(), 43);
+    assert!(paths.contains(&"moov/trak/mdia/minf/stbl/stsz".to_string()));
+    assert!(paths.contains(&"moov/trak/mdia/minf/dinf/dref".to_string()));
+    assert!(paths.contains(&"moov/trak/udta/name".to_string()));
+
+    let stbl = moov.walk().into_iter().find(|(_, n)| n.content == MP4Box::Stbl).unwrap();
+    assert_eq!(stbl.0, 4);
+    assert_eq!((stbl.1.offset, stbl.1.size), (380417, 1309));
+    assert_eq!(stbl.1.children[0].offset, 380425);
+  }
+
+  #[test]
+  fn sizes() {
+    // 0 extends to the end of the input
+    let (rest, header, payload) = mp4_box(b"\0\0\0\0mdat1234", 0).unwrap();
+    assert_eq!((rest, header.size, payload), (&b""[..], None, &b"1234"[..]));
+    // 1 is followed by a 64 bit size
+    let (rest, header, payload) = mp4_box(b"\0\0\0\x01mdat\0\0\0\0\0\0\0\x12ab12", 0).unwrap();
+    assert_eq!((rest, header.size, header.length, payload), (&b"12"[..], Some(18), 16, &b"ab"[..]));
+    assert_eq!(mp4_box(b"\0\0\0\x01mdat\0\0", 4), Err(BoxError::TruncatedHeader { offset: 4 }));
+    assert_eq!(
+      mp4_box(b"\0\0\0\x01mdat\0\0\0\0\0\0\0\x0f", 0),
+      Err(BoxError::SizeTooSmall { offset: 0, size: 15, header: 16 })
+    );
+    assert_eq!(
+      mp4_box(b"\0\0\0\x01mdat\xff\0\0\0\0\0\0\0", 0),
+      Err(BoxError::Truncated { offset: 0, size: 0xff << 56, available: 16 })
+    );
+
+    for sz in 2..8u8 {
+      let input = [0, 0, 0, sz, b'f', b'r', b'e', b'e'];
+      assert_eq!(mp4_box(&input, 0), Err(BoxError::SizeTooSmall { offset: 0, size: sz as u64, header: 8 }));
+    }
+    assert_eq!(mp4_box(b"\0\0\0\x09free", 0), Err(BoxError::Truncated { offset: 0, size: 9, available: 8 }));
+    assert_eq!(mp4_box(b"\0\0\0", 0), Err(BoxError::TruncatedHeader { offset: 0 }));
+
+    let mut input = b"\0\0\0\x1auuid".to_vec();
+    input.extend(0..16);
+    input.extend_from_slice(b"xy");
+    let n = full_data_interpreter(&input).unwrap();
+    assert_eq!(n[0].fourcc, FourCC(*b"uuid"));
+    assert_eq!(n[0].uuid, Some([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]));
+    assert_eq!(n[0].payload, b"xy");
+  }
+
+  #[test]
+  fn containers() {
+    // a child larger than its container
+    let input = b"\0\0\0\x10moov\0\0\0\x10free";
+    assert_eq!(node(input, 100, 0).unwrap_err(), BoxError::Truncated { offset: 108, size: 16, available: 8 });
+
+    // a child of size 0 extends to the end of its container
+    let input = b"\0\0\0\x12moov\0\0\0\0free12\0\0\0\x08skip";
+    let n = full_data_interpreter(input).unwrap();
+    assert_eq!((n[0].children[0].size, n[0].children[0].payload), (10, &b"12"[..]));
+    assert_eq!(n[1].offset, 18);
+
+    assert_eq!(
+      full_data_interpreter(b"\0\0\0\x0cftyp\xff\xff\xff\xff"),
+      Err(BoxError::InvalidPayload { offset: 0, fourcc: FourCC(*b"ftyp") })
+    );
+
+    // `n` containers nested in each other
+    let nested = |n: usize| {
+      let mut input = Vec::new();
+      for i in 0..n {
+        input.extend_from_slice(&(8 * (n - i) as u32).to_be_bytes());
+        input.extend_from_slice(b"trak");
+      }
+      input
+    };
+    assert!(node(&nested(MAX_DEPTH), 0, 0).is_ok());
+    assert_eq!(node(&nested(MAX_DEPTH + 1), 0, 0).unwrap_err(), BoxError::TooDeep { offset: 8 * MAX_DEPTH });
+  }
+}
@@ -1,151 +1,141 @@
-#[macro_use]
-extern crate bencher;
-
-#[macro_use]
-extern crate nom;
-
-use bencher::{Bencher,black_box};
-use nom::{IResult,Needed,be_u32};
-use nom::IResult::*;
-
-use std::str::from_utf8;
-
-fn mp4_box(input:&[u8]) -> IResult<&[u8], &[u8]> {
-  match be_u32(input) {
-    Done(i, offset) => {
-      let sz: usize = offset as usize;
-      //println!("size: {}", sz);
-      if i.len() >= sz - 4 {
-        return Done(&i[(sz-4)..], &i[0..(sz-4)])
-      } else {
-        return Incomplete(Needed::Size(4 + offset as usize))
-      }
-    }
-    Error(e)      => Error(e),
-    Incomplete(e) => Incomplete(e)
-  }
+extern crate harness;
+extern crate mp4;
+
+use std::io::Cursor;
+use std::sync::OnceLock;
+
+use harness::{black_box, Bencher};
+use harness::runner::{self, Benchmark};
+
+use mp4::NomMp4;
+use mp4::generate::{generate, Config};
+use mp4::header::Movie;
+use mp4::reader::Reader;
+use mp4::writer;
+use mp4::{fragment, sample};
+
+harness::count_allocations!();
+
+// the parser skips the sample data of the mdat boxes, so the throughput only
+// counts the bytes around it
+fn parsed_bytes(input: &[u8]) -> u64 {
+  let boxes = mp4::full_data_interpreter(input).unwrap();
+  let samples: usize = boxes.iter().filter(|b| b.fourcc.0 == *b"mdat").map(|b| b.payload.len()).sum();
+  (input.len() - samples) as u64
 }
 
-#[derive(PartialEq,Eq,Debug)]
-struct FileType<'a> {
-  major_brand:         &'a str,
-  major_brand_version: &'a [u8],
-  compatible_brands:   Vec<&'a str>
+// parses the box tree
+fn tree(b: &mut Bencher, input: &[u8]) {
+  b.bytes = parsed_bytes(input);
+  b.iter(|| harness::parse_all(&mut NomMp4, black_box(input)));
 }
 
-#[derive(Debug)]
-enum MP4Box<'a> {
-  Ftyp(FileType<'a>),
-  Moov,
-  Mdat,
-  Free,
-  Skip,
-  Wide,
-  Unknown
+fn small_test(b: &mut Bencher) {
+  tree(b, harness::fixtures::mp4::SMALL)
 }
 
-named!(brand_name<&[u8],&str>, map_res!(take!(4), from_utf8));
-named!(ftyp, tag!("ftyp"));
-
-fn filetype_box<'a>(input: &'a[u8]) -> IResult<&'a [u8], MP4Box > {
-  //println!("ftyp:\n{}", input.to_hex(8));
-  do_parse!(input,
-       ftyp               >>
-    m: brand_name         >>
-    v: take!(4)           >>
-    c: many0!(brand_name) >>
-    (
-      MP4Box::Ftyp(FileType{
-        major_brand: m,
-        major_brand_version: v,
-        compatible_brands: c
-      })
-    )
-  )
+// parses the box tree and gathers the movie, track and media headers
+fn small_headers_test(b: &mut Bencher) {
+  let input = harness::fixtures::mp4::SMALL;
+  b.bytes = parsed_bytes(input);
+  b.iter(|| Movie::parse(black_box(input)).unwrap().tracks.len());
 }
 
-fn unknown_box(input:&[u8]) -> IResult<&[u8], MP4Box> {
-  Done(input, MP4Box::Unknown)
+// parses the box tree and resolves the offset, size and times of every
+// sample
+fn small_index_test(b: &mut Bencher) {
+  let input = harness::fixtures::mp4::SMALL;
+  b.bytes = parsed_bytes(input);
+  b.iter(|| {
+    let boxes = mp4::full_data_interpreter(black_box(input)).unwrap();
+    sample::index(&boxes).unwrap().len()
+  });
 }
 
-named!(box_parser_internal<&[u8], MP4Box>,
-  alt!(
-    filetype_box |
-    tag!("moov") => { |_| MP4Box::Moov } |
-    tag!("mdat") => { |_| MP4Box::Mdat } |
-    tag!("free") => { |_| MP4Box::Free } |
-    tag!("skip") => { |_| MP4Box::Skip } |
-    tag!("wide") => { |_| MP4Box::Wide } |
-    unknown_box
-  )
-);
-
-fn box_parser(input:&[u8]) -> IResult<&[u8], MP4Box> {
-  //mp4_box(input).flat_map(box_parser_internal)
-  flat_map!(input, call!(mp4_box), call!(box_parser_internal))
+// the same index, read through `Reader`: only the box headers and the moov
+// box are copied out of the file
+fn small_reader_index_test(b: &mut Bencher) {
+  let input = harness::fixtures::mp4::SMALL;
+  b.bytes = parsed_bytes(input);
+  b.iter(|| Reader::new(Cursor::new(black_box(input))).unwrap().index().unwrap().len());
 }
 
-fn data_interpreter(bytes:&[u8]) -> IResult<&[u8], MP4Box> {
-  //println!("bytes:\n{}", bytes.to_hex(8));
-  //println!("bytes length: {}", bytes.len());
-  match box_parser(bytes) {
-    Done(i, o) => {
-      /*match o {
-        MP4Box::Ftyp(f) => println!("-> FTYP: {:?}", f),
-        MP4Box::Moov    => println!("-> MOOV"),
-        MP4Box::Mdat    => println!("-> MDAT"),
-        MP4Box::Free    => println!("-> FREE"),
-        MP4Box::Skip    => println!("-> SKIP"),
-        MP4Box::Wide    => println!("-> WIDE"),
-        MP4Box::Unknown => println!("-> UNKNOWN")
-      }*/
-      //println!("remaining:\n{}", i.to_hex(8));
-      //println!("got o");
-      Done(i,o)
-    },
-    Error(a) => {
-      println!("mp4 parsing error: {:?}", a);
-      assert!(false);
-      Error(a)
-    },
-    Incomplete(a) => {
-      //println!("mp4 incomplete: {:?}", a);
-      Incomplete(a)
-    }
-  }
+// writes back the box tree of small.mp4, parsed once. The mdat payload is
+// copied as is, the throughput counts the boxes encoded field by field
+fn small_write_test(b: &mut Bencher) {
+  let boxes = mp4::full_data_interpreter(harness::fixtures::mp4::SMALL).unwrap();
+  b.bytes = parsed_bytes(harness::fixtures::mp4::SMALL);
+  b.iter(|| writer::serialize(black_box(&boxes)).unwrap().len());
 }
 
-named!(full_data_interpreter(&[u8]) -> Vec<MP4Box>, many0!(data_interpreter));
+fn fragmented_test(b: &mut Bencher) {
+  tree(b, harness::fixtures::mp4::FRAGMENTED)
+}
 
-fn small_test(b: &mut Bencher) {
-  let data = include_bytes!("../../small.mp4");
-  b.iter(||{
-    let buf = black_box(data);
-    full_data_interpreter(buf).unwrap()
+// parses the box tree and resolves the samples of every fragment from the
+// trun, tfhd and trex defaults
+fn fragmented_index_test(b: &mut Bencher) {
+  let input = harness::fixtures::mp4::FRAGMENTED;
+  b.bytes = parsed_bytes(input);
+  b.iter(|| {
+    let boxes = mp4::full_data_interpreter(black_box(input)).unwrap();
+    fragment::fragments(&boxes).unwrap().len()
   });
 }
 
-fn bigbuckbunny_test(b: &mut Bencher) {
-  let data = include_bytes!("../../bigbuckbunny.mp4");
-  b.iter(||{
-    let buf = black_box(data);
-    full_data_interpreter(buf).unwrap()
-  });
+// resolves every sample, from the sample tables and from the fragments
+fn samples(input: &[u8]) -> usize {
+  let boxes = mp4::full_data_interpreter(input).unwrap();
+  sample::index(&boxes).unwrap().len() + fragment::fragments(&boxes).unwrap().len()
+}
+
+// for each generated file: a function generating it on first use, and
+// benchmarks parsing its box tree and resolving its samples
+macro_rules! generated {
+  ($($file:ident, $tree:ident, $index:ident = $config:expr;)*) => {$(
+    fn $file() -> &'static [u8] {
+      static FILE: OnceLock<Vec<u8>> = OnceLock::new();
+      FILE.get_or_init(|| generate(&$config))
+    }
+
+    fn $tree(b: &mut Bencher) {
+      tree(b, $file())
+    }
+
+    fn $index(b: &mut Bencher) {
+      let input = $file();
+      b.bytes = parsed_bytes(input);
+      b.iter(|| samples(black_box(input)));
+    }
+  )*};
 }
 
-benchmark_group!(mp4, small_test, bigbuckbunny_test);
-benchmark_main!(mp4);
+generated! {
+  generated_64k, generated_64k_test, generated_64k_index_test = Config::progressive(64 << 10);
+  generated_1m, generated_1m_test, generated_1m_index_test = Config::progressive(1 << 20);
+  generated_16m, generated_16m_test, generated_16m_index_test = Config::progressive(16 << 20);
+  fragmented_1m, fragmented_1m_test, fragmented_1m_index_test = Config::fragmented(1 << 20, 16);
+  fragmented_16m, fragmented_16m_test, fragmented_16m_index_test = Config::fragmented(16 << 20, 256);
+}
 
-/*
 fn main() {
-  println!("Hello, world!");
-  let data = include_bytes!("../../small.mp4");
-  //println!("data:\n{}", data.to_hex(8));
-  loop {
-    //let res = full_data_interpreter(data);
-    //println!("res: {:?}", res);
-    data_interpreter(data);
-  }
+  runner::main("mp4", harness::name_of(&NomMp4), &harness::details_of(&NomMp4), &[
+    Benchmark { name: "small_test", run: small_test },
+    Benchmark { name: "small_headers_test", run: small_headers_test },
+    Benchmark { name: "small_index_test", run: small_index_test },
+    Benchmark { name: "small_reader_index_test", run: small_reader_index_test },
+    Benchmark { name: "small_write_test", run: small_write_test },
+    Benchmark { name: "fragmented_test", run: fragmented_test },
+    Benchmark { name: "fragmented_index_test", run: fragmented_index_test },
+    Benchmark { name: "generated_64k_test", run: generated_64k_test },
+    Benchmark { name: "generated_64k_index_test", run: generated_64k_index_test },
+    Benchmark { name: "generated_1m_test", run: generated_1m_test },
+    Benchmark { name: "generated_1m_index_test", run: generated_1m_index_test },
+    Benchmark { name: "generated_16m_test", run: generated_16m_test },
+    Benchmark { name: "generated_16m_index_test", run: generated_16m_index_test },
+    Benchmark { name: "fragmented_1m_test", run: fragmented_1m_test },
+    Benchmark { name: "fragmented_1m_index_test", run: fragmented_1m_index_test },
+    Benchmark { name: "fragmented_16m_test", run: fragmented_16m_test },
+    Benchmark { name: "fragmented_16m_index_test", run: fragmented_16m_index_test },
+  ]);
 }
-*/
-
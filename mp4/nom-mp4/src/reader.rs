@@ -0,0 +1,250 @@
+//! Reading boxes from a file rather than from a slice.
+//!
+//! [`Reader`] walks the top level box headers of any `Read + Seek` source
+//! and seeks over the boxes it is not asked for, so that indexing a file
+//! only reads its `moov` (and its `moof` when fragmented), whatever the size
+//! of its `mdat`. The boxes it loads are parsed by the slice parser, with
+//! their offsets in the file.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::fragment::{self, Fragment};
+use super::sample::{self, TrackIndex};
+use super::{box_header, node, BoxError, BoxHeader, Node};
+
+/// Why boxes could not be read.
+#[derive(Debug)]
+pub enum ReadError {
+  Io(io::Error),
+  Box(BoxError),
+}
+
+impl fmt::Display for ReadError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ReadError::Io(ref e)  => write!(f, "read error: {}", e),
+      ReadError::Box(ref e) => e.fmt(f),
+    }
+  }
+}
+
+impl std::error::Error for ReadError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match *self {
+      ReadError::Io(ref e)  => Some(e),
+      ReadError::Box(ref e) => Some(e),
+    }
+  }
+}
+
+impl From<io::Error> for ReadError {
+  fn from(e: io::Error) -> ReadError {
+    ReadError::Io(e)
+  }
+}
+
+impl From<BoxError> for ReadError {
+  fn from(e: BoxError) -> ReadError {
+    ReadError::Box(e)
+  }
+}
+
+/// Header of a top level box, with its position in the file.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Entry {
+  pub header: BoxHeader,
+  /// Position of the box header from the start of the file.
+  pub offset: u64,
+  /// Size of the box, header included, resolved for a box extending to the
+  /// end of the file.
+  pub size:   u64,
+}
+
+/// A box read in memory.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct LoadedBox {
+  /// Position of the box header from the start of the file.
+  pub offset: u64,
+  /// The whole box, header included.
+  pub data:   Vec<u8>,
+}
+
+impl LoadedBox {
+  /// Parses the box and its descendants, with their offsets in the file.
+  pub fn node(&self) -> Result<Node<'_>, BoxError> {
+    node(&self.data, self.offset as usize, 0).map(|(_, n)| n)
+  }
+}
+
+/// Longest box header: size, type, 64 bit size and extended type.
+const MAX_HEADER: usize = 32;
+
+/// Walks the top level boxes of a file.
+pub struct Reader<R> {
+  inner:    R,
+  /// Position of the next box header.
+  position: u64,
+  /// Length of the file.
+  end:      u64,
+}
+
+impl<R: Read + Seek> Reader<R> {
+  /// Reads the boxes of `inner` from the start, whatever its position.
+  pub fn new(mut inner: R) -> io::Result<Reader<R>> {
+    let end = inner.seek(SeekFrom::End(0))?;
+    Ok(Reader { inner, position: 0, end })
+  }
+
+  pub fn into_inner(self) -> R {
+    self.inner
+  }
+
+  /// Reads the header of the next box, and moves past the box. Returns
+  /// `None` at the end of the file.
+  pub fn next_box(&mut self) -> Result<Option<Entry>, ReadError> {
+    if self.position >= self.end {
+      return Ok(None);
+    }
+
+    let offset = self.position;
+    let available = self.end - offset;
+    let mut buf = [0; MAX_HEADER];
+    let length = (MAX_HEADER as u64).min(available) as usize;
+    self.inner.seek(SeekFrom::Start(offset))?;
+    self.inner.read_exact(&mut buf[..length])?;
+
+    // the same checks as the slice parser, with the rest of the file in
+    // place of the rest of the slice
+    let header = match box_header(&buf[..length]) {
+      Ok((_, header)) => header,
+      Err(_)          => return Err(BoxError::TruncatedHeader { offset: offset as usize }.into()),
+    };
+    let size = header.size.unwrap_or(available);
+    if size < header.length as u64 {
+      return Err(BoxError::SizeTooSmall { offset: offset as usize, size, header: header.length }.into());
+    }
+    if size > available {
+      return Err(BoxError::Truncated { offset: offset as usize, size, available: available as usize }.into());
+    }
+
+    self.position += size;
+    Ok(Some(Entry { header, offset, size }))
+  }
+
+  /// Reads the whole of a box found by [`Reader::next_box`].
+  pub fn load(&mut self, entry: &Entry) -> Result<LoadedBox, ReadError> {
+    // `next_box` checked the size against the length of the file
+    let mut data = vec![0; entry.size as usize];
+    self.inner.seek(SeekFrom::Start(entry.offset))?;
+    self.inner.read_exact(&mut data)?;
+    Ok(LoadedBox { offset: entry.offset, data })
+  }
+
+  /// Reads the remaining top level boxes of the types in `fourccs`, and
+  /// skips the others.
+  pub fn load_all(&mut self, fourccs: &[&[u8; 4]]) -> Result<Vec<LoadedBox>, ReadError> {
+    let mut boxes = Vec::new();
+    while let Some(entry) = self.next_box()? {
+      if fourccs.contains(&&entry.header.fourcc.0) {
+        boxes.push(self.load(&entry)?);
+      }
+    }
+    Ok(boxes)
+  }
+
+  /// Builds the sample index of every track, reading only the `moov` box.
+  pub fn index(&mut self) -> Result<Vec<TrackIndex>, ReadError> {
+    let loaded = self.load_all(&[b"moov"])?;
+    let boxes = nodes(&loaded)?;
+    Ok(sample::index(&boxes)?)
+  }
+
+  /// Resolves the samples of every fragment, reading only the `moov` and
+  /// `moof` boxes.
+  pub fn fragments(&mut self) -> Result<Vec<Fragment>, ReadError> {
+    let loaded = self.load_all(&[b"moov", b"moof"])?;
+    let boxes = nodes(&loaded)?;
+    Ok(fragment::fragments(&boxes)?)
+  }
+}
+
+fn nodes(loaded: &[LoadedBox]) -> Result<Vec<Node<'_>>, BoxError> {
+  loaded.iter().map(LoadedBox::node).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::{full_data_interpreter, FourCC};
+  use std::io::Cursor;
+
+  /// Counts the bytes read from the source.
+  struct Counting<'a> {
+    inner: Cursor<&'a [u8]>,
+    read:  usize,
+  }
+
+  impl<'a> Read for Counting<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      let n = self.inner.read(buf)?;
+      self.read += n;
+      Ok(n)
+    }
+  }
+
+  impl<'a> Seek for Counting<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+      self.inner.seek(pos)
+    }
+  }
+
+  #[test]
+  fn small() {
+    let input = harness::fixtures::mp4::SMALL;
+    let mut reader = Reader::new(Counting { inner: Cursor::new(input), read: 0 }).unwrap();
+    let tracks = reader.index().unwrap();
+    assert_eq!(tracks, sample::index(&full_data_interpreter(input).unwrap()).unwrap());
+    // the moov box and the headers of the 5 top level boxes, the mdat is
+    // skipped
+    assert_eq!(reader.into_inner().read, 3459 + 5 * MAX_HEADER);
+
+    let mut reader = Reader::new(Cursor::new(input)).unwrap();
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_box().unwrap() {
+      entries.push((entry.header.fourcc.to_string(), entry.offset, entry.size));
+    }
+    let boxes = full_data_interpreter(input).unwrap();
+    assert_eq!(entries, boxes.iter().map(|b| (b.fourcc.to_string(), b.offset as u64, b.size as u64)).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn fragmented() {
+    let input = harness::fixtures::mp4::FRAGMENTED;
+    let fragments = Reader::new(Cursor::new(input)).unwrap().fragments().unwrap();
+    assert_eq!(fragments, fragment::fragments(&full_data_interpreter(input).unwrap()).unwrap());
+  }
+
+  #[test]
+  fn errors() {
+    // a size of 0 extends to the end of the file
+    let mut reader = Reader::new(Cursor::new(&b"\0\0\0\x08free\0\0\0\0mdat1234"[..])).unwrap();
+    reader.next_box().unwrap();
+    let mdat = reader.next_box().unwrap().unwrap();
+    assert_eq!((mdat.offset, mdat.size), (8, 12));
+    assert_eq!(reader.load(&mdat).unwrap().data, b"\0\0\0\0mdat1234");
+    assert!(reader.next_box().unwrap().is_none());
+
+    let error = |input: &'static [u8]| match Reader::new(Cursor::new(input)).unwrap().load_all(&[b"moov"]) {
+      Err(ReadError::Box(e)) => e,
+      r => panic!("{:?}", r),
+    };
+    assert_eq!(error(b"\0\0\0\x08free\0\0\0"), BoxError::TruncatedHeader { offset: 8 });
+    assert_eq!(error(b"\0\0\0\x09free"), BoxError::Truncated { offset: 0, size: 9, available: 8 });
+    assert_eq!(error(b"\0\0\0\x04free"), BoxError::SizeTooSmall { offset: 0, size: 4, header: 8 });
+    // the offsets of the loaded boxes are those of the file
+    let input = &b"\0\0\0\x08free\0\0\0\x0cftyp\xff\xff\xff\xff"[..];
+    let loaded = Reader::new(Cursor::new(input)).unwrap().load_all(&[b"ftyp"]).unwrap();
+    assert_eq!(loaded[0].node().unwrap_err(), BoxError::InvalidPayload { offset: 8, fourcc: FourCC(*b"ftyp") });
+  }
+}
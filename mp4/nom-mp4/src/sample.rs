@@ -0,0 +1,441 @@
+//! Sample tables, the boxes of `stbl`, ISO/IEC 14496-12 section 8.5 to 8.7,
+//! and the index of the samples of a track built from them.
+//!
+//! The tables describe the samples in runs: `stts` gives the duration of
+//! runs of samples, `stsc` the number of samples of runs of chunks, `stco`
+//! or `co64` the offset of each chunk in the file. [`samples`] expands them
+//! into one [`Sample`] per sample.
+
+use nom::{IResult,be_i32,be_u8,be_u16,be_u32,be_u64};
+
+use super::header::full_box;
+use super::{box_header, moov, BoxError, FourCC, MP4Box, Node};
+
+/// Entry of `stsd`, describing the coding of samples.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct SampleEntry<'a> {
+  /// Codec, such as `avc1` or `mp4a`.
+  pub format:               FourCC,
+  pub data_reference_index: u16,
+  /// Codec specific fields and boxes following the common fields.
+  pub data:                 &'a [u8],
+}
+
+/// Run of `count` samples lasting `delta` each, from `stts`.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct TimeToSample {
+  pub count: u32,
+  pub delta: u32,
+}
+
+/// Run of `count` samples presented `offset` after their decoding time,
+/// from `ctts`.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct CompositionOffset {
+  pub count:  u32,
+  /// Unsigned in version 0 of the box, signed in version 1.
+  pub offset: i64,
+}
+
+/// From `first_chunk` until the next entry, chunks hold `samples_per_chunk`
+/// samples each, from `stsc`.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct SampleToChunk {
+  /// Index of the first chunk of the run, from 1.
+  pub first_chunk:              u32,
+  pub samples_per_chunk:        u32,
+  /// Index in `stsd` of the description of these samples, from 1.
+  pub sample_description_index: u32,
+}
+
+/// Sizes of the samples, from `stsz` or `stz2`.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct SampleSizes {
+  /// Size of every sample when they all have the same, otherwise 0.
+  pub constant: u32,
+  pub count:    u32,
+  /// Size of each sample, empty when `constant` is set.
+  pub sizes:    Vec<u32>,
+}
+
+impl SampleSizes {
+  pub fn get(&self, sample: usize) -> u32 {
+    if self.constant != 0 { self.constant } else { self.sizes[sample] }
+  }
+}
+
+/// Parses `count` entries of `size` bytes with `f`, after checking that they
+/// fit in `input`, so that a bogus count does not reserve memory it will not
+/// use.
+fn entries<'a, T, F>(input: &'a [u8], count: u32, size: usize, f: F) -> IResult<&'a [u8], Vec<T>>
+  where F: Fn(&'a [u8]) -> IResult<&'a [u8], T> {
+  if count as u64 * size as u64 > input.len() as u64 {
+    return Err(nom::Err::Error(nom::Context::Code(input, nom::ErrorKind::Count)));
+  }
+  let mut v = Vec::with_capacity(count as usize);
+  let mut i = input;
+  for _ in 0..count {
+    let (rest, e) = f(i)?;
+    v.push(e);
+    i = rest;
+  }
+  Ok((i, v))
+}
+
+fn sample_entry(input:&[u8]) -> IResult<&[u8], SampleEntry<'_>> {
+  let (_, header) = box_header(input)?;
+  let size = header.size.map_or(input.len(), |s| s as usize);
+  if size < header.length + 8 || size > input.len() {
+    return Err(nom::Err::Error(nom::Context::Code(input, nom::ErrorKind::LengthValue)));
+  }
+  let (data, data_reference_index) = do_parse!(&input[header.length..size],
+                          take!(6) >>
+    data_reference_index: be_u16   >>
+    (data_reference_index)
+  )?;
+  Ok((&input[size..], SampleEntry { format: header.fourcc, data_reference_index, data }))
+}
+
+pub fn sample_description(input:&[u8]) -> IResult<&[u8], Vec<SampleEntry<'_>>> {
+  let (i, _) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  // each entry is at least a 16 bytes box
+  entries(i, count, 16, sample_entry)
+}
+
+pub fn time_to_sample(input:&[u8]) -> IResult<&[u8], Vec<TimeToSample>> {
+  let (i, _) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  entries(i, count, 8, |i| do_parse!(i,
+    count: be_u32 >> delta: be_u32 >>
+    (TimeToSample { count, delta })
+  ))
+}
+
+pub fn composition_offset(input:&[u8]) -> IResult<&[u8], Vec<CompositionOffset>> {
+  let (i, (version, _)) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  entries(i, count, 8, |i| {
+    let (i, count) = be_u32(i)?;
+    let (i, offset) = if version == 0 {
+      map!(i, be_u32, |o| o as i64)?
+    } else {
+      map!(i, be_i32, |o| o as i64)?
+    };
+    Ok((i, CompositionOffset { count, offset }))
+  })
+}
+
+pub fn sample_to_chunk(input:&[u8]) -> IResult<&[u8], Vec<SampleToChunk>> {
+  let (i, _) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  entries(i, count, 12, |i| do_parse!(i,
+    first_chunk:              be_u32 >>
+    samples_per_chunk:        be_u32 >>
+    sample_description_index: be_u32 >>
+    (SampleToChunk { first_chunk, samples_per_chunk, sample_description_index })
+  ))
+}
+
+pub fn sample_size(input:&[u8]) -> IResult<&[u8], SampleSizes> {
+  let (i, _) = full_box(input)?;
+  let (i, constant) = be_u32(i)?;
+  let (i, count) = be_u32(i)?;
+  let (i, sizes) = if constant == 0 { entries(i, count, 4, be_u32)? } else { (i, Vec::new()) };
+  Ok((i, SampleSizes { constant, count, sizes }))
+}
+
+/// `stz2`, with 4, 8 or 16 bits per size.
+pub fn compact_sample_size(input:&[u8]) -> IResult<&[u8], SampleSizes> {
+  let (i, _) = full_box(input)?;
+  let (i, _) = take!(i, 3)?;
+  let (i, field_size) = be_u8(i)?;
+  let (i, count) = be_u32(i)?;
+  let (i, sizes) = match field_size {
+    4 => {
+      let (i, packed) = entries(i, count.div_ceil(2), 1, be_u8)?;
+      let sizes = packed.iter().flat_map(|b| [(b >> 4) as u32, (b & 0xf) as u32]).take(count as usize).collect();
+      (i, sizes)
+    }
+    8  => entries(i, count, 1, |i| map!(i, be_u8, |s| s as u32))?,
+    16 => entries(i, count, 2, |i| map!(i, be_u16, |s| s as u32))?,
+    _  => return Err(nom::Err::Error(nom::Context::Code(i, nom::ErrorKind::Switch))),
+  };
+  Ok((i, SampleSizes { constant: 0, count, sizes }))
+}
+
+pub fn chunk_offset(input:&[u8]) -> IResult<&[u8], Vec<u64>> {
+  let (i, _) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  entries(i, count, 4, |i| map!(i, be_u32, |o| o as u64))
+}
+
+pub fn chunk_offset_64(input:&[u8]) -> IResult<&[u8], Vec<u64>> {
+  let (i, _) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  entries(i, count, 8, be_u64)
+}
+
+/// Numbers of the sync samples, from 1.
+pub fn sync_sample(input:&[u8]) -> IResult<&[u8], Vec<u32>> {
+  let (i, _) = full_box(input)?;
+  let (i, count) = be_u32(i)?;
+  entries(i, count, 4, be_u32)
+}
+
+/// A sample of a track, resolved from its sample tables.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct Sample {
+  /// Position of the sample in the file.
+  pub offset:             u64,
+  pub size:               u32,
+  /// In the timescale of the media header.
+  pub decode_time:        u64,
+  /// Presentation time minus decode time.
+  pub composition_offset: i64,
+  /// Whether decoding can start at this sample.
+  pub keyframe:           bool,
+  /// Index in `stsd` of the description of the sample, from 1.
+  pub description_index:  u32,
+}
+
+/// Most samples a sample table, or a movie fragment, may describe per byte
+/// of its boxes. A constant size `stsz`, or a `trun` taking every field from
+/// the defaults, describes any number of samples in a few bytes: this keeps
+/// the memory used by an index to a multiple of the size of the input.
+pub const MAX_SAMPLES_PER_BYTE: u64 = 16;
+
+fn invalid(stbl: &Node, reason: &'static str) -> BoxError {
+  BoxError::InvalidSampleTable { offset: stbl.offset, reason }
+}
+
+/// Expands the sample tables of `stbl` into the list of its samples.
+pub fn samples(stbl: &Node) -> Result<Vec<Sample>, BoxError> {
+  let missing = |fourcc: &[u8; 4]| BoxError::MissingBox { offset: stbl.offset, fourcc: FourCC(*fourcc) };
+
+  let (mut stts, mut ctts, mut stsc, mut sizes, mut chunks, mut stss) = (None, None, None, None, None, None);
+  for c in &stbl.children {
+    match c.content {
+      MP4Box::Stts(ref t) => stts = Some(t),
+      MP4Box::Ctts(ref t) => ctts = Some(t),
+      MP4Box::Stsc(ref t) => stsc = Some(t),
+      MP4Box::Stsz(ref t) | MP4Box::Stz2(ref t) => sizes = Some(t),
+      MP4Box::Stco(ref t) | MP4Box::Co64(ref t) => chunks = Some(t),
+      MP4Box::Stss(ref t) => stss = Some(t),
+      _ => {}
+    }
+  }
+  let stts = stts.ok_or_else(|| missing(b"stts"))?;
+  let stsc = stsc.ok_or_else(|| missing(b"stsc"))?;
+  let sizes = sizes.ok_or_else(|| missing(b"stsz"))?;
+  let chunks = chunks.ok_or_else(|| missing(b"stco"))?;
+
+  // the chunk runs, and the number of samples they hold, checked before
+  // expanding anything
+  let mut total: u64 = 0;
+  for (run, entry) in stsc.iter().enumerate() {
+    let first = entry.first_chunk as usize;
+    let end = stsc.get(run + 1).map_or(chunks.len() + 1, |next| next.first_chunk as usize);
+    if (run == 0 && first != 1) || first == 0 || end < first || end > chunks.len() + 1 {
+      return Err(invalid(stbl, "sample to chunk entries do not match the chunk offsets"));
+    }
+    total = ((end - first) as u64).checked_mul(entry.samples_per_chunk as u64)
+      .and_then(|n| total.checked_add(n))
+      .ok_or_else(|| invalid(stbl, "chunks hold more samples than there are sizes"))?;
+  }
+  let count = sizes.count as u64;
+  if total > count {
+    return Err(invalid(stbl, "chunks hold more samples than there are sizes"));
+  }
+  if total < count {
+    return Err(invalid(stbl, "chunks hold fewer samples than there are sizes"));
+  }
+  if stts.iter().map(|t| t.count as u64).sum::<u64>() < count {
+    return Err(invalid(stbl, "time to sample entries cover fewer samples"));
+  }
+  // a constant size stsz gives any count in a few bytes
+  if count > MAX_SAMPLES_PER_BYTE * stbl.size as u64 {
+    return Err(invalid(stbl, "more samples than the size of the tables allows"));
+  }
+  let mut samples = Vec::with_capacity(count as usize);
+
+  // offsets and sizes, chunk by chunk
+  for (run, entry) in stsc.iter().enumerate() {
+    let first = entry.first_chunk as usize;
+    let end = stsc.get(run + 1).map_or(chunks.len() + 1, |next| next.first_chunk as usize);
+    for chunk in first..end {
+      let mut offset = chunks[chunk - 1];
+      for _ in 0..entry.samples_per_chunk {
+        let size = sizes.get(samples.len());
+        samples.push(Sample {
+          offset,
+          size,
+          decode_time: 0,
+          composition_offset: 0,
+          keyframe: stss.is_none(),
+          description_index: entry.sample_description_index,
+        });
+        offset = offset.checked_add(size as u64).ok_or_else(|| invalid(stbl, "sample offset overflow"))?;
+      }
+    }
+  }
+
+  let mut time = 0;
+  let mut deltas = stts.iter().flat_map(|t| (0..t.count).map(move |_| t.delta));
+  for s in samples.iter_mut() {
+    s.decode_time = time;
+    let delta = deltas.next().ok_or_else(|| invalid(stbl, "time to sample entries cover fewer samples"))?;
+    time = time.checked_add(delta as u64).ok_or_else(|| invalid(stbl, "decode time overflow"))?;
+  }
+
+  if let Some(ctts) = ctts {
+    let mut offsets = ctts.iter().flat_map(|c| (0..c.count).map(move |_| c.offset));
+    for s in samples.iter_mut() {
+      s.composition_offset = offsets.next().ok_or_else(|| invalid(stbl, "composition offsets cover fewer samples"))?;
+    }
+  }
+
+  for &n in stss.into_iter().flatten() {
+    match samples.get_mut((n as usize).wrapping_sub(1)) {
+      Some(s) => s.keyframe = true,
+      None    => return Err(invalid(stbl, "sync sample out of range")),
+    }
+  }
+
+  Ok(samples)
+}
+
+/// Samples of a track.
+#[derive(Clone,PartialEq,Eq,Debug)]
+pub struct TrackIndex {
+  pub track_id:  u32,
+  /// Time units per second of the decode times.
+  pub timescale: u32,
+  pub samples:   Vec<Sample>,
+}
+
+/// Builds the sample index of every track of the `moov` box among the top
+/// level `boxes`.
+pub fn index(boxes: &[Node]) -> Result<Vec<TrackIndex>, BoxError> {
+  let mut tracks = Vec::new();
+  for trak in moov(boxes)?.children.iter().filter(|c| c.content == MP4Box::Trak) {
+    let mdia = trak.child(b"mdia")?;
+    // `box_content` decodes every tkhd and mdhd box, or fails
+    let (track_id, timescale) = match (&trak.child(b"tkhd")?.content, &mdia.child(b"mdhd")?.content) {
+      (MP4Box::Tkhd(t), MP4Box::Mdhd(m)) => (t.track_id, m.timescale),
+      _ => unreachable!(),
+    };
+    let stbl = mdia.child(b"minf")?.child(b"stbl")?;
+    tracks.push(TrackIndex { track_id, timescale, samples: samples(stbl)? });
+  }
+  Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::full_data_interpreter;
+  use super::super::tests::leaf;
+
+  /// A `stbl` at offset 10 holding `tables`.
+  fn stbl(tables: Vec<MP4Box<'static>>) -> Node<'static> {
+    Node {
+      fourcc: FourCC(*b"stbl"), offset: 10, size: 100, content: MP4Box::Stbl,
+      children: tables.into_iter().map(leaf).collect(),
+      ..leaf(MP4Box::Stbl)
+    }
+  }
+
+  #[test]
+  fn small() {
+    let boxes = full_data_interpreter(harness::fixtures::mp4::SMALL).unwrap();
+    let tracks = index(&boxes).unwrap();
+    assert_eq!(tracks.len(), 2);
+
+    let video = &tracks[0];
+    assert_eq!((video.track_id, video.timescale, video.samples.len()), (1, 90000, 166));
+    let first = Sample { offset: 168, size: 22055, decode_time: 0, composition_offset: 0, keyframe: true, description_index: 1 };
+    assert_eq!(video.samples[0], first);
+    assert_eq!((video.samples[1].offset, video.samples[1].size, video.samples[1].keyframe), (22223, 2848, false));
+    let last = video.samples[165];
+    assert_eq!((last.offset, last.size, last.decode_time), (379090, 629, 495000));
+    assert_eq!(video.samples.iter().filter(|s| s.keyframe).count(), 1);
+
+    let audio = &tracks[1];
+    assert_eq!((audio.track_id, audio.timescale, audio.samples.len()), (2, 48000, 261));
+    assert_eq!((audio.samples[1].offset, audio.samples[1].size, audio.samples[1].decode_time), (28292, 219, 1024));
+    assert_eq!((audio.samples[260].offset, audio.samples[260].decode_time), (379931, 266240));
+    assert!(audio.samples.iter().all(|s| s.keyframe));
+
+    // every sample lies in the mdat box
+    let mdat = boxes.iter().find(|b| b.content == MP4Box::Mdat).unwrap();
+    for s in tracks.iter().flat_map(|t| &t.samples) {
+      assert!(s.offset >= mdat.offset as u64 && s.offset + s.size as u64 <= (mdat.offset + mdat.size) as u64);
+    }
+  }
+
+  #[test]
+  fn inconsistent_tables() {
+    let sizes = |count| MP4Box::Stsz(SampleSizes { constant: 10, count, sizes: Vec::new() });
+    let stts = |count| MP4Box::Stts(vec![TimeToSample { count, delta: 5 }]);
+    let stsc = |first_chunk, samples_per_chunk| MP4Box::Stsc(vec![SampleToChunk { first_chunk, samples_per_chunk, sample_description_index: 1 }]);
+
+    let samples_of = |tables| samples(&stbl(tables));
+    let ok = samples_of(vec![sizes(4), stts(4), stsc(1, 2), MP4Box::Stco(vec![100, 200]), MP4Box::Stss(vec![3])]).unwrap();
+    assert_eq!(ok.iter().map(|s| (s.offset, s.decode_time, s.keyframe)).collect::<Vec<_>>(),
+      [(100, 0, false), (110, 5, false), (200, 10, true), (210, 15, false)]);
+
+    let reason = |r: Result<Vec<Sample>, BoxError>| match r {
+      Err(BoxError::InvalidSampleTable { offset: 10, reason }) => reason,
+      r => panic!("{:?}", r),
+    };
+    assert_eq!(reason(samples_of(vec![sizes(5), stts(5), stsc(1, 2), MP4Box::Stco(vec![100, 200])])),
+      "chunks hold fewer samples than there are sizes");
+    assert_eq!(reason(samples_of(vec![sizes(3), stts(3), stsc(1, 2), MP4Box::Stco(vec![100, 200])])),
+      "chunks hold more samples than there are sizes");
+    assert_eq!(reason(samples_of(vec![sizes(4), stts(3), stsc(1, 2), MP4Box::Stco(vec![100, 200])])),
+      "time to sample entries cover fewer samples");
+    assert_eq!(reason(samples_of(vec![sizes(4), stts(4), stsc(3, 2), MP4Box::Stco(vec![100, 200])])),
+      "sample to chunk entries do not match the chunk offsets");
+    assert_eq!(reason(samples_of(vec![sizes(4), stts(4), stsc(1, 2), MP4Box::Stco(vec![100, 200]), MP4Box::Stss(vec![0])])),
+      "sync sample out of range");
+    assert_eq!(samples_of(vec![sizes(4), stts(4), stsc(1, 2)]),
+      Err(BoxError::MissingBox { offset: 10, fourcc: FourCC(*b"stco") }));
+  }
+
+  #[test]
+  fn bounded_count() {
+    // a constant size, and a single chunk holding every sample
+    let tables = |count: u32| stbl(vec![
+      MP4Box::Stsz(SampleSizes { constant: 1, count, sizes: Vec::new() }),
+      MP4Box::Stts(vec![TimeToSample { count, delta: 1 }]),
+      MP4Box::Stsc(vec![SampleToChunk { first_chunk: 1, samples_per_chunk: count, sample_description_index: 1 }]),
+      MP4Box::Stco(vec![0]),
+    ]);
+    assert_eq!(samples(&tables(1600)).unwrap().len(), 1600);
+    let too_many = Err(BoxError::InvalidSampleTable { offset: 10, reason: "more samples than the size of the tables allows" });
+    assert_eq!(samples(&tables(1601)), too_many);
+    assert_eq!(samples(&tables(50_000_000)), too_many);
+    assert_eq!(samples(&tables(u32::MAX)), too_many);
+
+    // the chunks are counted before anything is expanded
+    let mut huge = tables(u32::MAX);
+    huge.children[2].content = MP4Box::Stsc(vec![SampleToChunk { first_chunk: 1, samples_per_chunk: u32::MAX, sample_description_index: 1 }]);
+    huge.children[3].content = MP4Box::Stco(vec![0; 3]);
+    assert_eq!(samples(&huge), Err(BoxError::InvalidSampleTable { offset: 10, reason: "chunks hold more samples than there are sizes" }));
+    huge.children[1].content = MP4Box::Stts(vec![TimeToSample { count: 1, delta: 1 }]);
+    huge.children[3].content = MP4Box::Stco(vec![0]);
+    assert_eq!(samples(&huge), Err(BoxError::InvalidSampleTable { offset: 10, reason: "time to sample entries cover fewer samples" }));
+  }
+
+  #[test]
+  fn compact_sizes() {
+    let stz2 = b"\0\0\0\0\0\0\0\x04\0\0\0\x03\x12\x30";
+    assert_eq!(compact_sample_size(stz2).unwrap().1.sizes, [1, 2, 3]);
+    let stz2 = b"\0\0\0\0\0\0\0\x10\0\0\0\x02\x01\x02\x03\x04";
+    assert_eq!(compact_sample_size(stz2).unwrap().1.sizes, [0x102, 0x304]);
+    assert!(compact_sample_size(b"\0\0\0\0\0\0\0\x07\0\0\0\0").is_err());
+    // more entries than bytes
+    assert!(sample_size(b"\0\0\0\0\0\0\0\0\xff\xff\xff\xff\0\0\0\x01").is_err());
+  }
+}
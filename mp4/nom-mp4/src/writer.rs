@@ -0,0 +1,687 @@
+//! Writing box trees back to bytes.
+//!
+//! [`serialize`] encodes the decoded content of each box, copies the payload
+//! of the boxes that are not decoded, and writes the children of the
+//! containers. The offsets and sizes of the nodes are not used: sizes are
+//! recomputed, and a box gets a 64 bit size only when it does not fit in 32
+//! bits. Reserved fields are written as 0. Full boxes are written with the
+//! version recorded in their decoded content, or else the one their values
+//! need, such as version 1 of `ctts` for negative offsets.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::fragment::{RandomAccessPoint, RunSample, SegmentIndex, TrackFragmentHeader, TrackFragmentRandomAccess, TrackRun};
+use super::header::{Handler, MediaHeader, MovieHeader, TrackHeader};
+use super::sample::SampleSizes;
+use super::{FileType, FourCC, MP4Box, Node};
+
+/// Why a box could not be written: one of its values does not fit in the
+/// layout of the box.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct WriteError {
+  pub fourcc: FourCC,
+  pub reason: &'static str,
+}
+
+impl fmt::Display for WriteError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "cannot write {} box: {}", self.fourcc, self.reason)
+  }
+}
+
+impl std::error::Error for WriteError {}
+
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+  out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+  out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+  out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn full_box(out: &mut Vec<u8>, version: u8, flags: u32) {
+  put_u32(out, (version as u32) << 24 | (flags & 0xff_ffff));
+}
+
+/// Writes the boxes of a file, each with its descendants.
+pub fn serialize(nodes: &[Node]) -> Result<Vec<u8>, WriteError> {
+  let mut out = Vec::new();
+  for n in nodes {
+    write_node(n, &mut out)?;
+  }
+  Ok(out)
+}
+
+/// Appends a box and its descendants to `out`.
+pub fn write_node(node: &Node, out: &mut Vec<u8>) -> Result<(), WriteError> {
+  write_box(node, out, u32::MAX as u64)
+}
+
+/// Writes `node` with a 64 bit size when it is larger than `max_32`, which
+/// the tests lower to exercise the 64 bit sizes.
+fn write_box(node: &Node, out: &mut Vec<u8>, max_32: u64) -> Result<(), WriteError> {
+  let start = out.len();
+  put_u32(out, 0);
+  out.extend_from_slice(&node.fourcc.0);
+  if let Some(uuid) = node.uuid {
+    out.extend_from_slice(&uuid);
+  }
+
+  if node.content.is_container() {
+    for c in &node.children {
+      write_box(c, out, max_32)?;
+    }
+  } else {
+    content(node, out)?;
+  }
+
+  let size = (out.len() - start) as u64;
+  if size <= max_32 {
+    out[start..start + 4].copy_from_slice(&(size as u32).to_be_bytes());
+  } else {
+    // the 64 bit size goes between the type and the extended type
+    out[start..start + 4].copy_from_slice(&1u32.to_be_bytes());
+    let at = start + 8;
+    out.splice(at..at, (size + 8).to_be_bytes().iter().cloned());
+  }
+  Ok(())
+}
+
+/// Encodes the payload of a box which is not a container.
+fn content(node: &Node, out: &mut Vec<u8>) -> Result<(), WriteError> {
+  let error = |reason| WriteError { fourcc: node.fourcc, reason };
+  let narrow = |v: u64, reason| u32::try_from(v).map_err(|_| error(reason));
+
+  match node.content {
+    MP4Box::Ftyp(ref f)  => file_type(f, out).map_err(error)?,
+    MP4Box::Mvhd(ref h)  => movie_header(h, out).map_err(error)?,
+    MP4Box::Tkhd(ref h)  => track_header(h, out).map_err(error)?,
+    MP4Box::Mdhd(ref h)  => media_header(h, out).map_err(error)?,
+    MP4Box::Hdlr(ref h)  => handler(h, out),
+    MP4Box::Stsd(ref entries) => {
+      full_box(out, 0, 0);
+      put_u32(out, narrow(entries.len() as u64, "too many entries")?);
+      for e in entries {
+        put_u32(out, narrow(16 + e.data.len() as u64, "sample entry too large")?);
+        out.extend_from_slice(&e.format.0);
+        out.extend_from_slice(&[0; 6]);
+        put_u16(out, e.data_reference_index);
+        out.extend_from_slice(e.data);
+      }
+    }
+    MP4Box::Stts(ref entries) => {
+      full_box(out, 0, 0);
+      put_u32(out, narrow(entries.len() as u64, "too many entries")?);
+      for e in entries {
+        put_u32(out, e.count);
+        put_u32(out, e.delta);
+      }
+    }
+    MP4Box::Ctts(ref entries) => {
+      // version 1 has signed offsets
+      let signed = entries.iter().any(|e| e.offset < 0);
+      full_box(out, signed as u8, 0);
+      put_u32(out, narrow(entries.len() as u64, "too many entries")?);
+      for e in entries {
+        put_u32(out, e.count);
+        let offset = if signed { i32::try_from(e.offset).map(|o| o as u32) } else { u32::try_from(e.offset) };
+        put_u32(out, offset.map_err(|_| error("composition offset out of range"))?);
+      }
+    }
+    MP4Box::Stsc(ref entries) => {
+      full_box(out, 0, 0);
+      put_u32(out, narrow(entries.len() as u64, "too many entries")?);
+      for e in entries {
+        put_u32(out, e.first_chunk);
+        put_u32(out, e.samples_per_chunk);
+        put_u32(out, e.sample_description_index);
+      }
+    }
+    MP4Box::Stsz(ref s)  => sample_size(s, out).map_err(error)?,
+    MP4Box::Stz2(ref s)  => compact_sample_size(s, out).map_err(error)?,
+    MP4Box::Stco(ref offsets) => {
+      full_box(out, 0, 0);
+      put_u32(out, narrow(offsets.len() as u64, "too many entries")?);
+      for &o in offsets {
+        put_u32(out, narrow(o, "chunk offset larger than 32 bits, use co64")?);
+      }
+    }
+    MP4Box::Co64(ref offsets) => {
+      full_box(out, 0, 0);
+      put_u32(out, narrow(offsets.len() as u64, "too many entries")?);
+      for &o in offsets {
+        put_u64(out, o);
+      }
+    }
+    MP4Box::Stss(ref samples) => {
+      full_box(out, 0, 0);
+      put_u32(out, narrow(samples.len() as u64, "too many entries")?);
+      for &s in samples {
+        put_u32(out, s);
+      }
+    }
+    MP4Box::Trex(ref t)  => {
+      full_box(out, 0, 0);
+      for &v in &[t.track_id, t.sample_description_index, t.sample_duration, t.sample_size, t.sample_flags] {
+        put_u32(out, v);
+      }
+    }
+    MP4Box::Mfhd(n) | MP4Box::Mfro(n) => {
+      full_box(out, 0, 0);
+      put_u32(out, n);
+    }
+    MP4Box::Tfhd(ref h)  => track_fragment_header(h, out),
+    MP4Box::Tfdt(t)      => {
+      full_box(out, t.version, 0);
+      if t.version == 1 {
+        put_u64(out, t.time);
+      } else {
+        put_u32(out, narrow(t.time, "decode time larger than 32 bits in version 0")?);
+      }
+    }
+    MP4Box::Trun(ref r)  => track_run(r, out).map_err(error)?,
+    MP4Box::Sidx(ref s)  => segment_index(s, out).map_err(error)?,
+    MP4Box::Tfra(ref t)  => track_fragment_random_access(t, out).map_err(error)?,
+    // not decoded, or containers without children
+    MP4Box::Moov | MP4Box::Trak | MP4Box::Mdia | MP4Box::Minf | MP4Box::Stbl |
+    MP4Box::Udta | MP4Box::Edts | MP4Box::Dinf | MP4Box::Mvex | MP4Box::Moof |
+    MP4Box::Traf | MP4Box::Mfra | MP4Box::Mdat | MP4Box::Free | MP4Box::Skip |
+    MP4Box::Wide | MP4Box::Unknown(_) => out.extend_from_slice(node.payload),
+  }
+  Ok(())
+}
+
+fn file_type(f: &FileType, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  if f.major_brand.len() != 4 || f.major_brand_version.len() != 4 || f.compatible_brands.iter().any(|b| b.len() != 4) {
+    return Err("brands and version are 4 bytes long");
+  }
+  out.extend_from_slice(f.major_brand.as_bytes());
+  out.extend_from_slice(f.major_brand_version);
+  for b in &f.compatible_brands {
+    out.extend_from_slice(b.as_bytes());
+  }
+  Ok(())
+}
+
+/// Creation time, modification time, timescale or track id, and duration,
+/// in 32 bits for version 0.
+fn times(out: &mut Vec<u8>, version: u8, c: u64, m: u64, t: u32, d: u64) -> Result<(), &'static str> {
+  if version == 1 {
+    put_u64(out, c);
+    put_u64(out, m);
+    put_u32(out, t);
+    put_u64(out, d);
+  } else {
+    let narrow = |v| u32::try_from(v).map_err(|_| "times larger than 32 bits in version 0");
+    put_u32(out, narrow(c)?);
+    put_u32(out, narrow(m)?);
+    put_u32(out, t);
+    put_u32(out, narrow(d)?);
+  }
+  Ok(())
+}
+
+fn matrix(out: &mut Vec<u8>, m: &[i32; 9]) {
+  for &v in m {
+    put_u32(out, v as u32);
+  }
+}
+
+fn movie_header(h: &MovieHeader, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  full_box(out, h.version, 0);
+  times(out, h.version, h.creation_time, h.modification_time, h.timescale, h.duration)?;
+  put_u32(out, h.rate.0 as u32);
+  put_u16(out, h.volume.0 as u16);
+  out.extend_from_slice(&[0; 10]);
+  matrix(out, &h.matrix);
+  out.extend_from_slice(&[0; 24]);
+  put_u32(out, h.next_track_id);
+  Ok(())
+}
+
+fn track_header(h: &TrackHeader, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  full_box(out, h.version, h.flags);
+  // the track id sits between the times and the duration, followed by 4
+  // reserved bytes
+  let mut t = Vec::with_capacity(32);
+  times(&mut t, h.version, h.creation_time, h.modification_time, h.track_id, h.duration)?;
+  let duration = t.len() - if h.version == 1 { 8 } else { 4 };
+  out.extend_from_slice(&t[..duration]);
+  out.extend_from_slice(&[0; 4]);
+  out.extend_from_slice(&t[duration..]);
+  out.extend_from_slice(&[0; 8]);
+  put_u16(out, h.layer as u16);
+  put_u16(out, h.alternate_group as u16);
+  put_u16(out, h.volume.0 as u16);
+  out.extend_from_slice(&[0; 2]);
+  matrix(out, &h.matrix);
+  put_u32(out, h.width.0 as u32);
+  put_u32(out, h.height.0 as u32);
+  Ok(())
+}
+
+fn media_header(h: &MediaHeader, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  full_box(out, h.version, 0);
+  times(out, h.version, h.creation_time, h.modification_time, h.timescale, h.duration)?;
+  if h.language.iter().any(|l| !(0x60..0x80).contains(l)) {
+    return Err("language is not 3 lower case letters");
+  }
+  let letter = |l: u8| (l - 0x60) as u16;
+  put_u16(out, letter(h.language[0]) << 10 | letter(h.language[1]) << 5 | letter(h.language[2]));
+  out.extend_from_slice(&[0; 2]);
+  Ok(())
+}
+
+fn handler(h: &Handler, out: &mut Vec<u8>) {
+  full_box(out, 0, 0);
+  out.extend_from_slice(&[0; 4]);
+  out.extend_from_slice(&h.handler_type.0);
+  out.extend_from_slice(&[0; 12]);
+  out.extend_from_slice(h.name);
+  out.push(0);
+}
+
+fn sample_size(s: &SampleSizes, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  full_box(out, 0, 0);
+  put_u32(out, s.constant);
+  put_u32(out, s.count);
+  if s.constant == 0 {
+    if s.sizes.len() as u64 != s.count as u64 {
+      return Err("sample count does not match the sizes");
+    }
+    for &size in &s.sizes {
+      put_u32(out, size);
+    }
+  }
+  Ok(())
+}
+
+/// `stz2`, with the smallest field size holding every size.
+fn compact_sample_size(s: &SampleSizes, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  if s.constant != 0 || s.sizes.len() as u64 != s.count as u64 {
+    return Err("sample count does not match the sizes");
+  }
+  let max = s.sizes.iter().cloned().max().unwrap_or(0);
+  let field_size = match max {
+    0..=0xf     => 4,
+    0x10..=0xff => 8,
+    0x100..=0xffff => 16,
+    _ => return Err("sample size larger than 16 bits"),
+  };
+  full_box(out, 0, 0);
+  out.extend_from_slice(&[0, 0, 0, field_size]);
+  put_u32(out, s.count);
+  match field_size {
+    4 => out.extend(s.sizes.chunks(2).map(|p| (p[0] << 4 | p.get(1).cloned().unwrap_or(0)) as u8)),
+    8 => out.extend(s.sizes.iter().map(|&size| size as u8)),
+    _ => s.sizes.iter().for_each(|&size| put_u16(out, size as u16)),
+  }
+  Ok(())
+}
+
+fn track_fragment_header(h: &TrackFragmentHeader, out: &mut Vec<u8>) {
+  let flags = h.base_data_offset.map_or(0, |_| 0x01)
+    | h.sample_description_index.map_or(0, |_| 0x02)
+    | h.default_sample_duration.map_or(0, |_| 0x08)
+    | h.default_sample_size.map_or(0, |_| 0x10)
+    | h.default_sample_flags.map_or(0, |_| 0x20)
+    | if h.duration_is_empty { 0x010000 } else { 0 }
+    | if h.default_base_is_moof { 0x020000 } else { 0 };
+  full_box(out, 0, flags);
+  put_u32(out, h.track_id);
+  if let Some(o) = h.base_data_offset { put_u64(out, o) }
+  for v in &[h.sample_description_index, h.default_sample_duration, h.default_sample_size, h.default_sample_flags] {
+    if let Some(v) = *v { put_u32(out, v) }
+  }
+}
+
+fn track_run(r: &TrackRun, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  // every sample has the same fields, those of the first one
+  let fields = |s: &RunSample|
+    (s.duration.is_some(), s.size.is_some(), s.flags.is_some(), s.composition_offset.is_some());
+  let (duration, size, sample_flags, composition) = r.samples.first().map_or((false, false, false, false), fields);
+  if r.samples.iter().any(|s| fields(s) != (duration, size, sample_flags, composition)) {
+    return Err("samples of a run have different fields");
+  }
+  // the samples of a run without fields are left out
+  if !r.samples.is_empty() && (r.samples.len() != r.sample_count as usize || !(duration || size || sample_flags || composition)) {
+    return Err("samples do not match the sample count");
+  }
+
+  let flags = r.data_offset.map_or(0, |_| 0x001)
+    | r.first_sample_flags.map_or(0, |_| 0x004)
+    | if duration { 0x100 } else { 0 }
+    | if size { 0x200 } else { 0 }
+    | if sample_flags { 0x400 } else { 0 }
+    | if composition { 0x800 } else { 0 };
+  // version 1 has signed composition offsets
+  let signed = r.samples.iter().any(|s| s.composition_offset.is_some_and(|o| o < 0));
+  full_box(out, signed as u8, flags);
+  put_u32(out, r.sample_count);
+  if let Some(o) = r.data_offset { put_u32(out, o as u32) }
+  if let Some(f) = r.first_sample_flags { put_u32(out, f) }
+  for s in &r.samples {
+    for v in &[s.duration, s.size, s.flags] {
+      if let Some(v) = *v { put_u32(out, v) }
+    }
+    if let Some(o) = s.composition_offset {
+      let o = if signed { i32::try_from(o).map(|o| o as u32) } else { u32::try_from(o) };
+      put_u32(out, o.map_err(|_| "composition offset out of range")?);
+    }
+  }
+  Ok(())
+}
+
+fn segment_index(s: &SegmentIndex, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  full_box(out, s.version, 0);
+  put_u32(out, s.reference_id);
+  put_u32(out, s.timescale);
+  if s.version == 1 {
+    put_u64(out, s.earliest_presentation_time);
+    put_u64(out, s.first_offset);
+  } else {
+    let narrow = |v| u32::try_from(v).map_err(|_| "times larger than 32 bits in version 0");
+    put_u32(out, narrow(s.earliest_presentation_time)?);
+    put_u32(out, narrow(s.first_offset)?);
+  }
+  put_u16(out, 0);
+  put_u16(out, u16::try_from(s.references.len()).map_err(|_| "too many references")?);
+  for r in &s.references {
+    if r.size > 0x7fff_ffff || r.sap_type > 7 || r.sap_delta_time > 0x0fff_ffff {
+      return Err("reference field out of range");
+    }
+    put_u32(out, (r.is_index as u32) << 31 | r.size);
+    put_u32(out, r.subsegment_duration);
+    put_u32(out, (r.starts_with_sap as u32) << 31 | (r.sap_type as u32) << 28 | r.sap_delta_time);
+  }
+  Ok(())
+}
+
+/// Number of bytes needed for `n`, from 1 to 4.
+fn length_of(n: u32) -> usize {
+  4 - (n.leading_zeros() as usize / 8).min(3)
+}
+
+fn track_fragment_random_access(t: &TrackFragmentRandomAccess, out: &mut Vec<u8>) -> Result<(), &'static str> {
+  if t.version == 0 && t.entries.iter().any(|e| e.time > u32::MAX as u64 || e.moof_offset > u32::MAX as u64) {
+    return Err("times larger than 32 bits in version 0");
+  }
+  let longest = |f: fn(&RandomAccessPoint) -> u32| t.entries.iter().map(|e| length_of(f(e))).max().unwrap_or(1);
+  let (traf, trun, sample) = (longest(|e| e.traf_number), longest(|e| e.trun_number), longest(|e| e.sample));
+
+  full_box(out, t.version, 0);
+  put_u32(out, t.track_id);
+  put_u32(out, ((traf - 1) << 4 | (trun - 1) << 2 | (sample - 1)) as u32);
+  put_u32(out, u32::try_from(t.entries.len()).map_err(|_| "too many entries")?);
+  for e in &t.entries {
+    if t.version == 1 {
+      put_u64(out, e.time);
+      put_u64(out, e.moof_offset);
+    } else {
+      put_u32(out, e.time as u32);
+      put_u32(out, e.moof_offset as u32);
+    }
+    for &(n, length) in &[(e.traf_number, traf), (e.trun_number, trun), (e.sample, sample)] {
+      out.extend_from_slice(&n.to_be_bytes()[4 - length..]);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::{full_data_interpreter, box_header};
+  use super::super::fragment::*;
+  use super::super::header::{Fixed16, Fixed8};
+  use super::super::sample::*;
+
+  #[test]
+  fn fixtures() {
+    // every reserved field of the fixtures is 0, so they are written back as
+    // they were
+    for input in &[harness::fixtures::mp4::SMALL, harness::fixtures::mp4::FRAGMENTED] {
+      let boxes = full_data_interpreter(input).unwrap();
+      assert_eq!(&serialize(&boxes).unwrap()[..], &input[..]);
+    }
+  }
+
+  // xorshift, to get reproducible trees without another dependency
+  struct Rng(u64);
+
+  impl Rng {
+    fn next(&mut self) -> u64 {
+      self.0 ^= self.0 << 13;
+      self.0 ^= self.0 >> 7;
+      self.0 ^= self.0 << 17;
+      self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+      self.next() % n
+    }
+
+    fn u32(&mut self) -> u32 {
+      self.next() as u32
+    }
+
+    fn option<T, F: FnOnce(&mut Rng) -> T>(&mut self, f: F) -> Option<T> {
+      if self.below(2) == 0 { Some(f(self)) } else { None }
+    }
+
+    fn vec<T, F: FnMut(&mut Rng) -> T>(&mut self, max: u64, mut f: F) -> Vec<T> {
+      (0..self.below(max + 1)).map(|_| f(self)).collect()
+    }
+  }
+
+  const BYTES: [u8; 256] = {
+    let mut b = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+      b[i] = i as u8;
+      i += 1;
+    }
+    b
+  };
+
+  /// Bytes without 0, for the handler names.
+  fn bytes(rng: &mut Rng) -> &'static [u8] {
+    let start = 1 + rng.below(255) as usize;
+    &BYTES[start..start + rng.below(256 - start as u64) as usize]
+  }
+
+  fn leaf(rng: &mut Rng) -> Node<'static> {
+    let version = rng.below(2) as u8;
+    let (fourcc, content) = match rng.below(22) {
+      0 => (b"ftyp", MP4Box::Ftyp(FileType {
+        major_brand: "isom", major_brand_version: &BYTES[..4],
+        compatible_brands: rng.vec(4, |r| ["isom", "iso6", "mp41", "avc1"][r.below(4) as usize]),
+      })),
+      1 => (b"mvhd", MP4Box::Mvhd(MovieHeader {
+        version, creation_time: rng.u32() as u64, modification_time: rng.u32() as u64, timescale: rng.u32(),
+        duration: if version == 1 { rng.next() } else { rng.u32() as u64 },
+        rate: Fixed16(rng.u32() as i32), volume: Fixed8(rng.u32() as i16),
+        matrix: [rng.u32() as i32; 9], next_track_id: rng.u32(),
+      })),
+      2 => (b"tkhd", MP4Box::Tkhd(TrackHeader {
+        version, flags: rng.u32() & 0xff_ffff, creation_time: rng.u32() as u64, modification_time: rng.u32() as u64,
+        track_id: rng.u32(), duration: if version == 1 { rng.next() } else { rng.u32() as u64 },
+        layer: rng.u32() as i16, alternate_group: -1, volume: Fixed8(0x100), matrix: [rng.u32() as i32; 9],
+        width: Fixed16(rng.u32() as i32), height: Fixed16(rng.u32() as i32),
+      })),
+      3 => (b"mdhd", MP4Box::Mdhd(MediaHeader {
+        version, creation_time: if version == 1 { rng.next() } else { 0 }, modification_time: 0, timescale: rng.u32(),
+        duration: rng.u32() as u64, language: [0x60 + rng.below(32) as u8, b'n', b'g'],
+      })),
+      4 => (b"hdlr", MP4Box::Hdlr(Handler { handler_type: FourCC(*b"vide"), name: bytes(rng) })),
+      5 => (b"stsd", MP4Box::Stsd(rng.vec(3, |r| SampleEntry {
+        format: FourCC(*b"avc1"), data_reference_index: r.u32() as u16, data: bytes(r),
+      }))),
+      6 => (b"stts", MP4Box::Stts(rng.vec(20, |r| TimeToSample { count: r.u32(), delta: r.u32() }))),
+      7 => (b"ctts", MP4Box::Ctts(rng.vec(20, |r| CompositionOffset {
+        count: r.u32(), offset: if version == 1 { r.u32() as i32 as i64 } else { r.u32() as i64 },
+      }))),
+      8 => (b"stsc", MP4Box::Stsc(rng.vec(20, |r| SampleToChunk {
+        first_chunk: r.u32(), samples_per_chunk: r.u32(), sample_description_index: r.u32(),
+      }))),
+      9 => {
+        let sizes = rng.vec(50, |r| r.u32());
+        let count = sizes.len() as u32;
+        (b"stsz", MP4Box::Stsz(if version == 1 {
+          SampleSizes { constant: 1 + rng.u32() / 2, count: rng.u32(), sizes: Vec::new() }
+        } else {
+          SampleSizes { constant: 0, count, sizes }
+        }))
+      }
+      10 => {
+        let max = [0x10, 0x100, 0x10000][rng.below(3) as usize];
+        let sizes = rng.vec(51, |r| r.below(max) as u32);
+        (b"stz2", MP4Box::Stz2(SampleSizes { constant: 0, count: sizes.len() as u32, sizes }))
+      }
+      11 => (b"stco", MP4Box::Stco(rng.vec(20, |r| r.u32() as u64))),
+      12 => (b"co64", MP4Box::Co64(rng.vec(20, |r| r.next()))),
+      13 => (b"stss", MP4Box::Stss(rng.vec(20, |r| r.u32()))),
+      14 => (b"trex", MP4Box::Trex(TrackExtends {
+        track_id: rng.u32(), sample_description_index: rng.u32(), sample_duration: rng.u32(), sample_size: rng.u32(), sample_flags: rng.u32(),
+      })),
+      15 => (b"mfhd", MP4Box::Mfhd(rng.u32())),
+      16 => (b"tfhd", MP4Box::Tfhd(TrackFragmentHeader {
+        track_id: rng.u32(), base_data_offset: rng.option(|r| r.next()), sample_description_index: rng.option(|r| r.u32()),
+        default_sample_duration: rng.option(|r| r.u32()), default_sample_size: rng.option(|r| r.u32()),
+        default_sample_flags: rng.option(|r| r.u32()), duration_is_empty: rng.below(2) == 0, default_base_is_moof: rng.below(2) == 0,
+      })),
+      17 => (b"tfdt", MP4Box::Tfdt(DecodeTime { version, time: if version == 1 { rng.next() } else { rng.u32() as u64 } })),
+      18 => {
+        // the fields of the first sample decide those of the others
+        let fields = [rng.below(2) == 0, rng.below(2) == 0, rng.below(2) == 0, rng.below(2) == 0];
+        let samples = rng.vec(20, |r| RunSample {
+          duration: if fields[0] { Some(r.u32()) } else { None },
+          size: if fields[1] { Some(r.u32()) } else { None },
+          flags: if fields[2] { Some(r.u32()) } else { None },
+          composition_offset: if !fields[3] { None } else if version == 1 { Some(r.u32() as i32 as i64) } else { Some(r.u32() as i64) },
+        });
+        let (sample_count, samples) = if fields.contains(&true) { (samples.len() as u32, samples) } else { (rng.u32(), Vec::new()) };
+        (b"trun", MP4Box::Trun(TrackRun {
+          data_offset: rng.option(|r| r.u32() as i32), first_sample_flags: rng.option(|r| r.u32()), sample_count, samples,
+        }))
+      }
+      19 => (b"sidx", MP4Box::Sidx(SegmentIndex {
+        version, reference_id: rng.u32(), timescale: rng.u32(),
+        earliest_presentation_time: if version == 1 { rng.next() } else { rng.u32() as u64 }, first_offset: rng.u32() as u64,
+        references: rng.vec(10, |r| SegmentReference {
+          is_index: r.below(2) == 0, size: r.u32() >> 1, subsegment_duration: r.u32(),
+          starts_with_sap: r.below(2) == 0, sap_type: r.below(8) as u8, sap_delta_time: r.u32() >> 4,
+        }),
+      })),
+      20 => (b"tfra", MP4Box::Tfra(TrackFragmentRandomAccess {
+        version, track_id: rng.u32(),
+        entries: rng.vec(10, |r| RandomAccessPoint {
+          time: if version == 1 { r.next() } else { r.u32() as u64 }, moof_offset: r.u32() as u64,
+          traf_number: r.u32() >> (8 * r.below(4)), trun_number: r.below(300) as u32, sample: r.u32() >> (8 * r.below(4)),
+        }),
+      })),
+      // boxes which are not decoded keep their payload
+      _ => {
+        let fourcc = [b"mdat", b"free", b"iods", b"uuid"][rng.below(4) as usize];
+        let content = match fourcc {
+          b"mdat" => MP4Box::Mdat,
+          b"free" => MP4Box::Free,
+          _       => MP4Box::Unknown(FourCC(*fourcc)),
+        };
+        let node = Node::new(FourCC(*fourcc), content, bytes(rng), Vec::new());
+        return if fourcc == b"uuid" { Node { uuid: Some([rng.u32() as u8; 16]), ..node } } else { node };
+      }
+    };
+    Node::new(FourCC(*fourcc), content, b"", Vec::new())
+  }
+
+  fn tree(rng: &mut Rng, depth: usize) -> Node<'static> {
+    if depth == 4 || rng.below(3) != 0 {
+      return leaf(rng);
+    }
+    let (fourcc, content) = match rng.below(6) {
+      0 => (b"moov", MP4Box::Moov),
+      1 => (b"trak", MP4Box::Trak),
+      2 => (b"stbl", MP4Box::Stbl),
+      3 => (b"moof", MP4Box::Moof),
+      4 => (b"traf", MP4Box::Traf),
+      _ => (b"mfra", MP4Box::Mfra),
+    };
+    let children = rng.vec(5, |r| tree(r, depth + 1));
+    Node::new(FourCC(*fourcc), content, b"", children)
+  }
+
+  /// Compares the trees, but for the positions, sizes and payloads set by
+  /// the writer.
+  fn assert_same(written: &Node, parsed: &Node) {
+    assert_eq!((written.fourcc, written.uuid, &written.content), (parsed.fourcc, parsed.uuid, &parsed.content));
+    if let MP4Box::Mdat | MP4Box::Free | MP4Box::Unknown(_) = written.content {
+      assert_eq!(written.payload, parsed.payload);
+    }
+    assert_eq!(written.children.len(), parsed.children.len());
+    for (w, p) in written.children.iter().zip(&parsed.children) {
+      assert_same(w, p);
+    }
+  }
+
+  #[test]
+  fn round_trip() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..500 {
+      let tree: Vec<Node> = rng.vec(4, |r| tree(r, 0));
+      let bytes = serialize(&tree).unwrap();
+      let parsed = full_data_interpreter(&bytes).unwrap();
+      assert_eq!(tree.len(), parsed.len());
+      for (w, p) in tree.iter().zip(&parsed) {
+        assert_same(w, p);
+      }
+      // a parsed tree is written back as it was read
+      assert_eq!(serialize(&parsed).unwrap(), bytes);
+      assert_eq!(full_data_interpreter(&serialize(&parsed).unwrap()).unwrap(), parsed);
+    }
+  }
+
+  #[test]
+  fn large_sizes() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..100 {
+      let tree = tree(&mut rng, 0);
+      // every box larger than 16 bytes gets a 64 bit size, written after
+      // its type
+      let mut bytes = Vec::new();
+      write_box(&tree, &mut bytes, 16).unwrap();
+      let parsed = full_data_interpreter(&bytes).unwrap();
+      assert_same(&tree, &parsed[0]);
+      for (_, n) in parsed[0].walk() {
+        let header = box_header(&bytes[n.offset..]).unwrap().1;
+        assert_eq!(header.size, Some(n.size as u64));
+        let large = header.length - if n.uuid.is_some() { 16 } else { 0 } == 16;
+        // the size the box would have with a 32 bit size
+        assert_eq!(large, n.size - if large { 8 } else { 0 } > 16);
+      }
+    }
+  }
+
+  #[test]
+  fn errors() {
+    let error = |content| serialize(&[Node::new(FourCC(*b"test"), content, b"", Vec::new())]).unwrap_err();
+    assert_eq!(error(MP4Box::Stco(vec![1 << 32])), WriteError { fourcc: FourCC(*b"test"), reason: "chunk offset larger than 32 bits, use co64" });
+    assert_eq!(error(MP4Box::Ctts(vec![CompositionOffset { count: 1, offset: 1 << 31 }, CompositionOffset { count: 1, offset: -1 }])).reason,
+      "composition offset out of range");
+    assert_eq!(error(MP4Box::Stz2(SampleSizes { constant: 0, count: 1, sizes: vec![1 << 16] })).reason, "sample size larger than 16 bits");
+    assert_eq!(error(MP4Box::Stsz(SampleSizes { constant: 0, count: 2, sizes: vec![1] })).reason, "sample count does not match the sizes");
+    assert_eq!(error(MP4Box::Tfdt(DecodeTime { version: 0, time: 1 << 32 })).reason, "decode time larger than 32 bits in version 0");
+    let run = TrackRun { data_offset: None, first_sample_flags: None, sample_count: 2, samples: vec![
+      RunSample { duration: Some(1), size: None, flags: None, composition_offset: None },
+      RunSample { duration: None, size: None, flags: None, composition_offset: None },
+    ]};
+    assert_eq!(error(MP4Box::Trun(run.clone())).reason, "samples of a run have different fields");
+    let run = TrackRun { sample_count: 3, samples: vec![run.samples[0]; 2], ..run };
+    assert_eq!(error(MP4Box::Trun(run)).reason, "samples do not match the sample count");
+  }
+}